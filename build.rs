@@ -8,23 +8,44 @@ fn main() {
 
 #[cfg(any(feature = "vulkan"))]
 fn build_spirv_kernel() {
-    use spirv_builder::{Capability, SpirvBuilder};
+    use spirv_builder::Capability;
     use std::path::PathBuf;
 
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
     let kernels_path = PathBuf::from(manifest_dir).join("shaders");
 
-    let result = SpirvBuilder::new(kernels_path, "spirv-unknown-vulkan1.4")
-        .scalar_block_layout(true)
-        .extension("SPV_EXT_shader_atomic_float_add")
-        // .capability(Capability::AtomicFloat16AddEXT)
-        .capability(Capability::AtomicFloat32AddEXT)
-        .capability(Capability::AtomicFloat64AddEXT)
-        .capability(Capability::VulkanMemoryModelDeviceScopeKHR)
-        .capability(Capability::Int8)
-        .print_metadata(spirv_builder::MetadataPrintout::Full)
+    let result = spirv_builder(&kernels_path).build().unwrap();
+
+    // The `device_address::` shaders dereference u64 addresses as pointers, which
+    // needs device features not every device has, so they get a module of their own
+    // that only `VulkanoDeviceAddressRunner` loads
+    let device_address_result = spirv_builder(&kernels_path)
+        .target_dir_path("spirv-builder-device-address")
+        .shader_crate_features(["device_address".to_string()])
+        .extension("SPV_KHR_physical_storage_buffer")
+        .capability(Capability::Int64)
+        .capability(Capability::PhysicalStorageBufferAddresses)
+        .build()
+        .unwrap();
+    println!(
+        "cargo:rustc-env=DEVICE_ADDRESS_SHADERS_SPV_PATH={}",
+        device_address_result.module.unwrap_single().display()
+    );
+
+    // Likewise the `descriptor_array::` shaders index a runtime array of storage
+    // buffers, which needs the descriptor indexing device features
+    let descriptor_array_result = spirv_builder(&kernels_path)
+        .target_dir_path("spirv-builder-descriptor-array")
+        .shader_crate_features(["descriptor_array".to_string()])
+        .extension("SPV_EXT_descriptor_indexing")
+        .capability(Capability::RuntimeDescriptorArray)
+        .capability(Capability::StorageBufferArrayDynamicIndexing)
         .build()
         .unwrap();
+    println!(
+        "cargo:rustc-env=DESCRIPTOR_ARRAY_SHADERS_SPV_PATH={}",
+        descriptor_array_result.module.unwrap_single().display()
+    );
 
     println!(
         "cargo:warning=building SPIRV to: {}",
@@ -54,3 +75,19 @@ fn build_spirv_kernel() {
     // Use the first entry point
     println!("cargo:rustc-env=SHADERS_ENTRY_ADDER=adder",);
 }
+
+/// The settings every SPIR-V module of the shaders crate is built with
+#[cfg(any(feature = "vulkan"))]
+fn spirv_builder(kernels_path: &std::path::Path) -> spirv_builder::SpirvBuilder {
+    use spirv_builder::{Capability, SpirvBuilder};
+
+    SpirvBuilder::new(kernels_path, "spirv-unknown-vulkan1.4")
+        .scalar_block_layout(true)
+        .extension("SPV_EXT_shader_atomic_float_add")
+        // .capability(Capability::AtomicFloat16AddEXT)
+        .capability(Capability::AtomicFloat32AddEXT)
+        .capability(Capability::AtomicFloat64AddEXT)
+        .capability(Capability::VulkanMemoryModelDeviceScopeKHR)
+        .capability(Capability::Int8)
+        .print_metadata(spirv_builder::MetadataPrintout::Full)
+}
@@ -1,9 +1,25 @@
+use core::mem::offset_of;
+
 use glam::UVec3;
+use shared::{
+    grid::GridCell,
+    particles::{MaterialPod, ParticleDeformation, ParticleMatrices},
+    AdderPushConstants, BindlessGridPushConstants, P2gHeapPushConstants, ParticlePushConstants,
+};
 use spirv_std::{
     glam::{self, vec2, Vec2, Vec4},
+    memory::{Scope, Semantics},
     spirv,
 };
 
+use crate::{
+    heap::{self, HeapValue},
+    p2g::P2gTransfer,
+};
+
+const SCOPE: u32 = Scope::Device as u32;
+const SEMANTICS: u32 = Semantics::NONE.bits();
+
 // ==============================================================================
 // BINDLESS COMPUTE SHADERS
 // ==============================================================================
@@ -23,25 +39,9 @@ use spirv_std::{
 // The main tradeoff is slightly more complex shader code, as we need to calculate
 // offsets. However, push constants are very fast to update, so this is typically
 // a worthwhile trade.
-
-/// Push constants for the adder shader
-/// These tell the shader where in the unified buffer each logical buffer starts
-#[repr(C)]
-pub struct AdderPushConstants {
-    pub a_offset: u32,    // Offset (in elements) where buffer 'a' starts
-    pub b_offset: u32,    // Offset (in elements) where buffer 'b' starts
-    pub buffer_size: u32, // Size of each logical buffer (number of elements to process)
-    pub _padding: u32,    // Padding to align to 16 bytes (required by Vulkan)
-}
-
-/// Push constants for particle shaders (step and wrap)
-#[repr(C)]
-pub struct ParticlePushConstants {
-    pub x_offset: u32,    // Offset (in elements) where buffer 'x' starts
-    pub v_offset: u32,    // Offset (in elements) where buffer 'v' starts (unused for wrap)
-    pub buffer_size: u32, // Size of each logical buffer (number of elements to process)
-    pub _padding: u32,    // Padding to align to 16 bytes
-}
+//
+// The push constant structs live in the `shared` crate, so the host fills in exactly
+// the layout each shader reads.
 
 /// Bindless adder shader
 ///
@@ -128,6 +128,84 @@ pub fn wrap_particles(
     }
 }
 
+/// Bindless clear_grid shader
+///
+/// A grid-sized kernel: dispatched over the grid cells rather than the particles,
+/// and bound to the unified GridCell buffer (the third element type added to the
+/// unified buffers, after u32 and Vec2).
+#[spirv(compute(threads(8, 8)))]
+pub fn clear_grid(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    unified_grid_buffer: &mut [GridCell],
+    #[spirv(push_constant)] push_constants: &BindlessGridPushConstants,
+) {
+    if id.x >= push_constants.n_grid_x || id.y >= push_constants.n_grid_y {
+        return;
+    }
+    let index = push_constants.grid_offset + id.y * push_constants.n_grid_x + id.x;
+    unified_grid_buffer[index as usize] = GridCell {
+        v: Vec2::ZERO,
+        mass: 0.0,
+    };
+}
+
+// ==============================================================================
+// BINDLESS HEAP SHADERS
+// ==============================================================================
+//
+// Instead of one unified buffer per element type, these shaders get a single
+// byte-addressed heap of u32 words holding logical buffers of any element type
+// (see `crate::heap`). Push constants carry the byte offset of each logical buffer.
+
+/// Bindless p2g over the heap
+///
+/// Does the same transfer as the bindful `p2g::p2g`, with the particle and grid
+/// buffers located in the heap by the byte offsets in the push constants.
+#[spirv(compute(threads(64)))]
+pub fn p2g(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] heap: &mut [u32],
+    #[spirv(push_constant)] offsets: &P2gHeapPushConstants,
+) {
+    let p = id.x;
+    if p >= offsets.num_particles {
+        return;
+    }
+    let mut matrices: ParticleMatrices = heap::load(heap, offsets.matrices, p);
+    let mut deformation: ParticleDeformation = heap::load(heap, offsets.deformation, p);
+    let material: MaterialPod = heap::load(heap, offsets.material, p);
+    let transfer = P2gTransfer::new(
+        heap::load(heap, offsets.x, p),
+        heap::load(heap, offsets.v, p),
+        matrices.C,
+        matrices.F,
+        deformation.J,
+        material.to_material(),
+    );
+
+    // save updated particle data
+    matrices.F = transfer.F;
+    deformation.J = transfer.Jp;
+    heap::store(heap, offsets.matrices, p, &matrices);
+    heap::store(heap, offsets.deformation, p, &deformation);
+
+    for o in 0..9 {
+        let node = transfer.node(o);
+        if node.index == usize::MAX {
+            continue;
+        }
+        let cell = offsets.grid + node.index as u32 * GridCell::SIZE;
+        let mass = cell + offset_of!(GridCell, mass) as u32;
+        let v = cell + offset_of!(GridCell, v) as u32;
+        unsafe {
+            heap::atomic_f_add::<SCOPE, SEMANTICS>(heap, mass, node.mass);
+            heap::atomic_f_add::<SCOPE, SEMANTICS>(heap, v, node.momentum.x);
+            heap::atomic_f_add::<SCOPE, SEMANTICS>(heap, v + 4, node.momentum.y);
+        }
+    }
+}
+
 // ==============================================================================
 // GRAPHICS SHADERS - For rendering points to the screen
 // ==============================================================================
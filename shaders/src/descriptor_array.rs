@@ -0,0 +1,67 @@
+//! Shaders for the descriptor indexing binding model
+//!
+//! Every logical buffer is its own allocation, bound as one element ("slot") of a
+//! runtime-sized array of storage buffer descriptors at binding 0. Shaders receive
+//! the slot of each logical buffer in push constants and index the array with it.
+//!
+//! Each entry point views the array with the element type it needs, so the same
+//! descriptors can hold `u32` and `Vec2` buffers side by side. These are the same
+//! kernels as the `bindless::` and `device_address::` ones.
+
+use glam::UVec3;
+use shared::{AdderSlotPushConstants, ParticleSlotPushConstants};
+use spirv_std::{
+    glam::{self, Vec2},
+    spirv, RuntimeArray, TypedBuffer,
+};
+
+/// Descriptor indexing adder shader: `a[i] += b[i]`
+#[spirv(compute(threads(64)))]
+pub fn adder(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] buffers: &mut RuntimeArray<
+        TypedBuffer<[u32]>,
+    >,
+    #[spirv(push_constant)] slots: &AdderSlotPushConstants,
+) {
+    let i = id.x as usize;
+    if i < slots.buffer_size as usize {
+        // The slots are the same for every invocation, so no NonUniform indexing needed
+        let b = unsafe { buffers.index(slots.b as usize) }[i];
+        let a = unsafe { buffers.index_mut(slots.a as usize) };
+        shared::add_update(&mut a[i], b);
+    }
+}
+
+/// Descriptor indexing step_particles shader: `x[i] += v[i]`
+#[spirv(compute(threads(64)))]
+pub fn step_particles(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] buffers: &mut RuntimeArray<
+        TypedBuffer<[Vec2]>,
+    >,
+    #[spirv(push_constant)] slots: &ParticleSlotPushConstants,
+) {
+    let i = id.x as usize;
+    if i < slots.buffer_size as usize {
+        let v = unsafe { buffers.index(slots.v as usize) }[i];
+        let x = unsafe { buffers.index_mut(slots.x as usize) };
+        x[i] += v;
+    }
+}
+
+/// Descriptor indexing wrap_particles shader: `x[i] = x[i] % 1.0`
+#[spirv(compute(threads(64)))]
+pub fn wrap_particles(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] buffers: &mut RuntimeArray<
+        TypedBuffer<[Vec2]>,
+    >,
+    #[spirv(push_constant)] slots: &ParticleSlotPushConstants,
+) {
+    let i = id.x as usize;
+    if i < slots.buffer_size as usize {
+        let x = unsafe { buffers.index_mut(slots.x as usize) };
+        x[i] %= Vec2::splat(1.0);
+    }
+}
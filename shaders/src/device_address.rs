@@ -0,0 +1,147 @@
+//! Shaders for the buffer device address binding model
+//!
+//! Each logical buffer is its own allocation, and instead of a descriptor binding
+//! the shader gets the buffer's `VkDeviceAddress` (a `u64`) in its push constants.
+//! On the GPU an address becomes a `PhysicalStorageBuffer` pointer; when the shaders
+//! crate is built for the host (`runners::cpu_dispatch`), it is a plain pointer.
+//!
+//! These are the same kernels as the `bindless::` ones, so the three binding models
+//! (bindful, bindless offsets, device addresses) can run the same compute pass.
+
+use core::marker::PhantomData;
+
+use glam::UVec3;
+use shared::{AdderAddressPushConstants, ParticleAddressPushConstants};
+use spirv_std::{
+    glam::{self, Vec2},
+    spirv,
+};
+
+/// A typed device address of an array of `T`
+///
+/// `T` must be 4-byte aligned (like every type in `shared`), which is the alignment
+/// the loads and stores declare to the driver.
+pub struct DevicePtr<T> {
+    address: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for DevicePtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DevicePtr<T> {}
+
+impl<T: Copy + Default> DevicePtr<T> {
+    #[inline]
+    pub fn new(address: u64) -> Self {
+        Self {
+            address,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Address of element `index`
+    #[inline]
+    fn element(self, index: u32) -> u64 {
+        self.address + index as u64 * core::mem::size_of::<T>() as u64
+    }
+
+    /// Load element `index`
+    ///
+    /// # Safety
+    /// The address must point to an array of at least `index + 1` elements of `T`.
+    #[inline]
+    pub unsafe fn load(self, index: u32) -> T {
+        let address = self.element(index);
+        #[cfg(target_arch = "spirv")]
+        {
+            let mut result = T::default();
+            core::arch::asm!(
+                "%ptr_type = OpTypePointer PhysicalStorageBuffer typeof*{result}",
+                "%ptr = OpConvertUToPtr %ptr_type {address}",
+                "%value = OpLoad _ %ptr Aligned 4",
+                "OpStore {result} %value",
+                address = in(reg) address,
+                result = in(reg) &mut result,
+            );
+            result
+        }
+        #[cfg(not(target_arch = "spirv"))]
+        {
+            core::ptr::read_unaligned(address as usize as *const T)
+        }
+    }
+
+    /// Store `value` to element `index`
+    ///
+    /// # Safety
+    /// The address must point to an array of at least `index + 1` elements of `T`,
+    /// and no other invocation may access that element concurrently.
+    #[inline]
+    pub unsafe fn store(self, index: u32, value: T) {
+        let address = self.element(index);
+        #[cfg(target_arch = "spirv")]
+        {
+            core::arch::asm!(
+                "%ptr_type = OpTypePointer PhysicalStorageBuffer typeof*{value}",
+                "%ptr = OpConvertUToPtr %ptr_type {address}",
+                "%value = OpLoad _ {value}",
+                "OpStore %ptr %value Aligned 4",
+                address = in(reg) address,
+                value = in(reg) &value,
+            );
+        }
+        #[cfg(not(target_arch = "spirv"))]
+        {
+            core::ptr::write_unaligned(address as usize as *mut T, value)
+        }
+    }
+}
+
+/// Device address adder shader: `a[i] += b[i]`
+#[spirv(compute(threads(64)))]
+pub fn adder(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(push_constant)] push_constants: &AdderAddressPushConstants,
+) {
+    let i = id.x;
+    if i < push_constants.buffer_size {
+        let a = DevicePtr::<u32>::new(push_constants.a);
+        let b = DevicePtr::<u32>::new(push_constants.b);
+        unsafe {
+            let mut sum = a.load(i);
+            shared::add_update(&mut sum, b.load(i));
+            a.store(i, sum);
+        }
+    }
+}
+
+/// Device address step_particles shader: `x[i] += v[i]`
+#[spirv(compute(threads(64)))]
+pub fn step_particles(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(push_constant)] push_constants: &ParticleAddressPushConstants,
+) {
+    let i = id.x;
+    if i < push_constants.buffer_size {
+        let x = DevicePtr::<Vec2>::new(push_constants.x);
+        let v = DevicePtr::<Vec2>::new(push_constants.v);
+        unsafe { x.store(i, x.load(i) + v.load(i)) }
+    }
+}
+
+/// Device address wrap_particles shader: `x[i] = x[i] % 1.0`
+#[spirv(compute(threads(64)))]
+pub fn wrap_particles(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(push_constant)] push_constants: &ParticleAddressPushConstants,
+) {
+    let i = id.x;
+    if i < push_constants.buffer_size {
+        let x = DevicePtr::<Vec2>::new(push_constants.x);
+        unsafe { x.store(i, x.load(i) % Vec2::splat(1.0)) }
+    }
+}
@@ -0,0 +1,83 @@
+use glam::UVec3;
+use shared::{
+    grid::{linear_grid_index_ivec, GridCell, STENCIL_OFFSETS},
+    mpm_utils::quadratic_weight_2d,
+    particles::ParticleMatrices,
+    DT, DX, INV_DX,
+};
+use spirv_std::{
+    glam::{self, vec2, Mat2, Vec2},
+    spirv,
+};
+
+/// Grid-to-particle transfer of MLS-MPM.
+///
+/// Gathers the grid velocities (written by `grid_update`) from the 3x3 stencil
+/// around each particle, rebuilds the APIC affine matrix `C`, and advects the
+/// particle position by `DT`.
+#[allow(non_snake_case)]
+#[spirv(compute(threads(64)))]
+pub fn g2p(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] xs: &mut [Vec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] vs: &mut [Vec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] grid: &[GridCell],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)]
+    particle_matrices: &mut [ParticleMatrices],
+) {
+    let p = id.x as usize;
+    if p >= xs.len() {
+        return;
+    }
+    let update = G2pUpdate::new(xs[p], grid);
+
+    vs[p] = update.v;
+    particle_matrices[p].C = update.C;
+    xs[p] = update.x;
+}
+
+/// The G2P transfer of one particle: its new position, velocity and affine matrix
+#[allow(non_snake_case)]
+pub struct G2pUpdate {
+    pub x: Vec2,
+    pub v: Vec2,
+    pub C: Mat2,
+}
+
+impl G2pUpdate {
+    /// Gather the grid velocities around the particle at `xp`
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn new(xp: Vec2, grid: &[GridCell]) -> Self {
+        let containing_cell = (xp * INV_DX).floor();
+        let containing_idx = containing_cell.as_ivec2();
+        let containing_center = (containing_cell + vec2(0.5, 0.5)) * DX;
+
+        let mut new_v = Vec2::ZERO;
+        let mut new_C = Mat2::ZERO;
+        for o in 0..9 {
+            let offset = STENCIL_OFFSETS[o];
+            let index = linear_grid_index_ivec(containing_idx + offset);
+            if index == usize::MAX {
+                continue;
+            }
+
+            let grid_pos = containing_center + offset.as_vec2() * DX;
+            let dpos = grid_pos - xp;
+            let weight = quadratic_weight_2d(dpos * INV_DX);
+
+            let grid_v = grid[index].v;
+            new_v += weight * grid_v;
+            // outer product grid_v * dpos^T, scaled by the inverse of the quadratic
+            // B-spline inertia tensor D^-1 = 4 / dx^2
+            new_C += (4.0 * INV_DX * INV_DX * weight)
+                * Mat2::from_cols(grid_v * dpos.x, grid_v * dpos.y);
+        }
+
+        Self {
+            x: xp + DT * new_v,
+            v: new_v,
+            C: new_C,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use glam::UVec3;
+use shared::{
+    grid::{linear_grid_index, update_grid_cell, GridCell, GridUpdateParams},
+    N_GRID_X,
+};
+use spirv_std::{glam, spirv};
+
+/// Grid update step of MLS-MPM.
+///
+/// Runs once per grid cell after p2g: normalizes the accumulated momentum by mass,
+/// applies gravity and external forces, and enforces the wall boundary condition.
+/// The parameters are read from the first element of `params`.
+#[spirv(compute(threads(8, 8)))]
+pub fn grid_update(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] grid: &mut [GridCell],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 8)] params: &[GridUpdateParams],
+) {
+    if id.x >= N_GRID_X || id.y >= N_GRID_X {
+        return;
+    }
+    let index = linear_grid_index(id.x, id.y);
+    grid[index] = update_grid_cell(grid[index], id.truncate(), &params[0]);
+}
@@ -0,0 +1,194 @@
+//! Typed access to a byte-addressed bindless heap
+//!
+//! The heap is a single `[u32]` storage buffer holding logical buffers of any
+//! element type. Each logical buffer starts at a byte offset (passed to the shader
+//! in push constants) and stores its elements with the same layout as on the host,
+//! so `GridCell`, `ParticleMatrices` and `MaterialPod` can share one binding.
+//!
+//! Offsets must be 4-byte aligned except for elements smaller than a word; the host
+//! side allocator (`runners::vulkano_bindless::heap`) takes care of that.
+
+use core::mem::{offset_of, size_of};
+
+use shared::{
+    grid::GridCell,
+    particles::{Material, MaterialPod, ParticleDeformation, ParticleMatrices},
+};
+use spirv_std::glam::{Mat2, Vec2};
+
+use crate::util::atomic_f_add_bits;
+
+/// A value that can be loaded from and stored to the heap at a byte offset
+pub trait HeapValue: Sized {
+    /// Size of the value in the heap, in bytes (equal to its host `size_of`)
+    const SIZE: u32;
+
+    fn load(heap: &[u32], byte_offset: u32) -> Self;
+    fn store(&self, heap: &mut [u32], byte_offset: u32);
+}
+
+/// Load element `index` of the logical buffer starting at byte `base`
+#[inline]
+pub fn load<T: HeapValue>(heap: &[u32], base: u32, index: u32) -> T {
+    T::load(heap, base + index * T::SIZE)
+}
+
+/// Store element `index` of the logical buffer starting at byte `base`
+#[inline]
+pub fn store<T: HeapValue>(heap: &mut [u32], base: u32, index: u32, value: &T) {
+    value.store(heap, base + index * T::SIZE)
+}
+
+/// Atomically add `val` to the `f32` at `byte_offset`, returning the previous value.
+///
+/// # Safety
+/// Invocations running concurrently must only access that word atomically.
+#[inline]
+pub unsafe fn atomic_f_add<const SCOPE: u32, const SEMANTICS: u32>(
+    heap: &mut [u32],
+    byte_offset: u32,
+    val: f32,
+) -> f32 {
+    atomic_f_add_bits::<SCOPE, SEMANTICS>(&mut heap[(byte_offset / 4) as usize], val)
+}
+
+impl HeapValue for u32 {
+    const SIZE: u32 = 4;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        heap[(byte_offset / 4) as usize]
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        heap[(byte_offset / 4) as usize] = *self;
+    }
+}
+
+impl HeapValue for f32 {
+    const SIZE: u32 = 4;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        f32::from_bits(u32::load(heap, byte_offset))
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.to_bits().store(heap, byte_offset)
+    }
+}
+
+impl HeapValue for Vec2 {
+    const SIZE: u32 = 8;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        Vec2::new(
+            f32::load(heap, byte_offset),
+            f32::load(heap, byte_offset + 4),
+        )
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.x.store(heap, byte_offset);
+        self.y.store(heap, byte_offset + 4);
+    }
+}
+
+impl HeapValue for Mat2 {
+    const SIZE: u32 = 16;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        Mat2::from_cols(
+            Vec2::load(heap, byte_offset),
+            Vec2::load(heap, byte_offset + 8),
+        )
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.x_axis.store(heap, byte_offset);
+        self.y_axis.store(heap, byte_offset + 8);
+    }
+}
+
+impl HeapValue for GridCell {
+    const SIZE: u32 = size_of::<GridCell>() as u32;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        GridCell {
+            v: Vec2::load(heap, byte_offset + offset_of!(GridCell, v) as u32),
+            mass: f32::load(heap, byte_offset + offset_of!(GridCell, mass) as u32),
+        }
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.v
+            .store(heap, byte_offset + offset_of!(GridCell, v) as u32);
+        self.mass
+            .store(heap, byte_offset + offset_of!(GridCell, mass) as u32);
+    }
+}
+
+impl HeapValue for ParticleMatrices {
+    const SIZE: u32 = size_of::<ParticleMatrices>() as u32;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        ParticleMatrices {
+            C: Mat2::load(heap, byte_offset + offset_of!(ParticleMatrices, C) as u32),
+            F: Mat2::load(heap, byte_offset + offset_of!(ParticleMatrices, F) as u32),
+        }
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.C
+            .store(heap, byte_offset + offset_of!(ParticleMatrices, C) as u32);
+        self.F
+            .store(heap, byte_offset + offset_of!(ParticleMatrices, F) as u32);
+    }
+}
+
+impl HeapValue for ParticleDeformation {
+    const SIZE: u32 = size_of::<ParticleDeformation>() as u32;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        ParticleDeformation {
+            J: f32::load(heap, byte_offset),
+        }
+    }
+
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        self.J.store(heap, byte_offset)
+    }
+}
+
+/// Materials are single bytes, packed four to a word
+impl HeapValue for MaterialPod {
+    const SIZE: u32 = 1;
+
+    #[inline]
+    fn load(heap: &[u32], byte_offset: u32) -> Self {
+        let shift = (byte_offset % 4) * 8;
+        let byte = (u32::load(heap, byte_offset) >> shift) & 0xff;
+        Material::from(byte).into()
+    }
+
+    /// Not atomic: invocations must not store to materials sharing a word concurrently
+    #[inline]
+    fn store(&self, heap: &mut [u32], byte_offset: u32) {
+        let shift = (byte_offset % 4) * 8;
+        let word = u32::load(heap, byte_offset);
+        let word = (word & !(0xff << shift)) | ((self.u8() as u32) << shift);
+        word.store(heap, byte_offset)
+    }
+}
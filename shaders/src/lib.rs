@@ -1,26 +1,37 @@
-// #![cfg_attr(target_arch = "spirv", no_std)]
-#![no_std]
+#![cfg_attr(target_arch = "spirv", no_std)]
+#![cfg_attr(target_arch = "spirv", feature(asm_experimental_arch))]
 // HACK(eddyb) can't easily see warnings otherwise from `spirv-builder` builds.
 // #![deny(warnings)]
 
 pub mod bindless;
+#[cfg(any(not(target_arch = "spirv"), feature = "descriptor_array"))]
+pub mod descriptor_array;
+#[cfg(any(not(target_arch = "spirv"), feature = "device_address"))]
+pub mod device_address;
+pub mod g2p;
+pub mod grid_update;
+pub mod heap;
 pub mod mult;
 pub mod p2g;
 pub mod render;
-pub mod svd;
 pub mod util;
 
 use core::u32;
 
+pub use shared::hash::{hash_many, wang32};
+
 pub use render::{
     grid_density::{grid_density_fs, grid_density_vs},
     particles::{particles_fs, particles_vs},
 };
 
 use glam::UVec3;
-use shared::grid::{linear_grid_index, linear_grid_index_unit_xy};
+use shared::{
+    grid::{linear_grid_index, linear_grid_index_unit_xy},
+    hash::rand_f32,
+    DispatchIndirectCommand,
+};
 use spirv_std::{
-    arch::atomic_f_add,
     glam::{self, vec2, Vec2},
     spirv,
 };
@@ -37,6 +48,19 @@ pub fn adder(
     shared::add_update(&mut a[i], b[i]);
 }
 
+#[spirv(compute(threads(64)))]
+pub fn add_scalar(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] a: &mut [u32],
+    #[spirv(push_constant)] push_constants: &shared::ScalarPushConstants,
+) {
+    let i = id.x as usize;
+    if i >= push_constants.num_elements as usize {
+        return;
+    }
+    shared::add_update(&mut a[i], push_constants.scalar);
+}
+
 #[spirv(compute(threads(64)))]
 pub fn step_particles(
     #[spirv(global_invocation_id)] id: UVec3,
@@ -53,47 +77,27 @@ pub fn wrap_particles(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] x: &mut [Vec2],
 ) {
     let i = id.x as usize;
-    if x[i].x < 0.0 {
-        x[i].x += 1.0;
-    }
-    if x[i].x >= 1.0 {
-        x[i].x -= 1.0;
-    }
-    if x[i].y < 0.0 {
-        x[i].y += 1.0;
-    }
-    if x[i].y >= 1.0 {
-        x[i].y -= 1.0;
-    }
+    x[i] = wrap_position(x[i]);
 }
 
+/// Wrap a particle position back into the unit square
 #[inline]
-pub fn wang32(mut x: u32) -> u32 {
-    x = x.wrapping_add(!x << 15);
-    x ^= x >> 10;
-    x = x.wrapping_add(x << 3);
-    x ^= x >> 6;
-    x = x.wrapping_add(!x << 11);
-    x ^ (x >> 16)
+pub fn wrap_position(x: Vec2) -> Vec2 {
+    x % Vec2::splat(1.0)
 }
 
-#[inline]
-pub fn hash_many<const N: usize>(xs: [u32; N]) -> u32 {
-    // seed
-    let mut acc: u32 = 0x9E37_79B9;
-
-    // FIXME use iterator
-    for i in 0..N {
-        let y = xs[i].wrapping_mul(0x9E37_79B9) ^ (xs[i] >> 16);
-        acc ^= y.wrapping_add(0x85EB_CA6B);
-        acc = acc.rotate_left(13);
+/// Write the workgroup count of an indirect dispatch of a 1d kernel over the first
+/// `count[0]` elements of its buffers
+#[spirv(compute(threads(1)))]
+pub fn indirect_args_1d(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 9)] count: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 10)]
+    args: &mut [DispatchIndirectCommand],
+) {
+    if id.x == 0 {
+        args[0] = DispatchIndirectCommand::over_1d(count[0]);
     }
-    wang32(acc)
-}
-
-#[inline]
-fn rand_f32<const N: usize>(xs: [u32; N]) -> f32 {
-    (hash_many(xs) as f32) / (u32::MAX as f32)
 }
 
 #[spirv(compute(threads(8, 8)))]
@@ -105,6 +109,12 @@ pub fn fill_grid_random(
     let y = id.y;
     let index = linear_grid_index(x, y);
 
+    grid[index] = random_grid_cell(x, y);
+}
+
+/// The pseudo-random contents `fill_grid_random` writes to the cell at `(x, y)`
+#[inline]
+pub fn random_grid_cell(x: u32, y: u32) -> shared::grid::GridCell {
     // Simple pseudo-random generation based on indices
     let mass = rand_f32([x, y, 0]);
     let velocity = vec2(rand_f32([x, y, 2]), rand_f32([x, y, 4]));
@@ -112,8 +122,7 @@ pub fn fill_grid_random(
     // let mass = 10.0;
     // let velocity = vec2(1.0, -9.0);
 
-    grid[index].mass = mass;
-    grid[index].v = velocity;
+    shared::grid::GridCell { v: velocity, mass }
 }
 
 #[spirv(compute(threads(8, 8)))]
@@ -147,5 +156,5 @@ pub fn p2g_simple_test(
 
     const SCOPE: u32 = Scope::Device as u32;
     const SEMANTICS: u32 = Semantics::NONE.bits();
-    unsafe { atomic_f_add::<_, SCOPE, SEMANTICS>(m, 0.1) };
+    unsafe { util::atomic_f_add::<SCOPE, SEMANTICS>(m, 0.1) };
 }
@@ -2,28 +2,83 @@
 
 use glam::UVec3;
 use shared::{
-    grid::{linear_grid_index_ivec_unchecked, STENCIL_OFFSETS},
-    mpm_utils::quadratic_weight_2d,
+    grid::{linear_grid_index_ivec, STENCIL_OFFSETS},
+    mpm_utils::{constitutive_update, quadratic_weight_2d, ConstitutiveUpdate},
     particles::{Material, MaterialPod, ParticleDeformation, ParticleMatrices},
-    DT, DX, INV_DX, LAMBDA_0, MU_0, N_GRID_X, P_MASS,
+    DX, INV_DX, P_MASS,
 };
 use spirv_std::{
-    arch::atomic_f_add,
-    glam::{self, vec2, Mat2, Vec2},
-    num_traits::float::Float,
+    glam::{self, vec2, IVec2, Mat2, Vec2},
     spirv,
 };
 
 use spirv_std::memory::{Scope, Semantics};
 
-use crate::{
-    svd::{svd2x2_exact, Svd2},
-    util::atomic_f_add_vec2,
-};
+use crate::util::{atomic_f_add, atomic_f_add_vec2};
 
 const SCOPE: u32 = Scope::Device as u32;
 const SEMANTICS: u32 = Semantics::NONE.bits();
 
+/// The contribution of one particle to one grid node
+pub struct P2gNode {
+    /// Linear index of the node, or `usize::MAX` if it is outside the grid
+    pub index: usize,
+    pub mass: f32,
+    pub momentum: Vec2,
+}
+
+/// The P2G transfer of one particle, independent of how the particle and grid
+/// buffers are bound (see the bindful [`p2g`] and the bindless heap `bindless::p2g`).
+#[allow(non_snake_case)]
+pub struct P2gTransfer {
+    /// Updated (elastic) deformation gradient, to be stored back to the particle
+    pub F: Mat2,
+    /// Updated plastic deformation determinant, to be stored back to the particle
+    pub Jp: f32,
+    xp: Vec2,
+    vp: Vec2,
+    affine: Mat2,
+    containing_idx: IVec2,
+    containing_center: Vec2,
+}
+
+impl P2gTransfer {
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn new(xp: Vec2, vp: Vec2, C: Mat2, F: Mat2, Jp: f32, material: Material) -> Self {
+        let ConstitutiveUpdate { F, Jp, affine } = constitutive_update(C, F, Jp, material);
+
+        let containing_cell = (xp * INV_DX).floor();
+        Self {
+            F,
+            Jp,
+            xp,
+            vp,
+            affine,
+            // index of the cell containing the particle (usting graphics coords from top-left)
+            containing_idx: containing_cell.as_ivec2(),
+            containing_center: (containing_cell + vec2(0.5, 0.5)) * DX,
+        }
+    }
+
+    /// The mass and momentum scattered to node `o` (in `0..9`) of the particle's stencil
+    #[inline]
+    pub fn node(&self, o: usize) -> P2gNode {
+        let offset = STENCIL_OFFSETS[o];
+        let index = linear_grid_index_ivec(self.containing_idx + offset);
+
+        let grid_pos = self.containing_center + (offset.as_vec2()) * DX;
+        let dpos = grid_pos - self.xp;
+        let weight = quadratic_weight_2d(dpos * INV_DX);
+
+        P2gNode {
+            index,
+            mass: weight * P_MASS,
+            momentum: weight * (P_MASS * self.vp + self.affine.mul_vec2(dpos)),
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 #[spirv(compute(threads(64)))]
 pub fn p2g(
@@ -39,91 +94,31 @@ pub fn p2g(
     particle_material: &mut [MaterialPod],
 ) {
     let p = id.x as usize;
-    let xp = xs[p];
-    let vp = vs[p];
-    let C = particle_matrices[p].C;
-    let mut F = particle_matrices[p].F;
-    let mut Jp = particle_deformation[p].J;
-    let material = particle_material[p].to_material();
-
-    // update deformation gradient F
-    F = Mat2::IDENTITY + DT * C.mul_mat2(&F);
-    // hardening coefficient
-    let h = (10.0 * (1.0 - particle_deformation[p].J))
-        .exp()
-        .clamp(0.1, 5.0);
-
-    let (mu, lambda) = match material {
-        Material::Fluid => (0.0, 0.0),
-        // jelly-like solid, less stiff; h=0.3
-        Material::Jelly => (MU_0 * 0.3, LAMBDA_0 * 0.3),
-        Material::Snow => (MU_0 * h, LAMBDA_0 * h),
-    };
-
-    let Svd2 {
-        u: U,
-        s: mut sig,
-        v: V,
-    } = svd2x2_exact(F.clone());
-    let mut J = 1.0;
-    for d in 0..2 {
-        let mut new_sig = sig[d];
-        if matches!(material, Material::Snow) {
-            // plasticity for snow
-            new_sig = new_sig.clamp(1.0 - 2.5e-2, 1.0 + 4.5e-3);
-        }
-        Jp *= sig[d] / new_sig;
-        sig[d] = new_sig;
-        J *= new_sig;
+    if p >= xs.len() {
+        return;
     }
-    match material {
-        Material::Fluid => {
-            F = Mat2::IDENTITY * J.sqrt();
-        }
-        Material::Snow | Material::Jelly => {
-            // Reconstruct F
-            F = U
-                .mul_mat2(&Mat2::from_diagonal(sig))
-                .mul_mat2(&V.transpose());
-        }
-    }
-    let stress = 2.0 * mu * (F - U.mul_mat2(&V.transpose())) * F.transpose()
-        + Mat2::IDENTITY * lambda * (J - 1.0) * J;
-    let affine_stress = stress + P_MASS * C;
+    let transfer = P2gTransfer::new(
+        xs[p],
+        vs[p],
+        particle_matrices[p].C,
+        particle_matrices[p].F,
+        particle_deformation[p].J,
+        particle_material[p].to_material(),
+    );
 
     // save updated particle data
-    particle_matrices[p].F = F;
-    particle_deformation[p].J = Jp;
+    particle_matrices[p].F = transfer.F;
+    particle_deformation[p].J = transfer.Jp;
 
     // do the actual P2G transfer
-    let containing_cell = (xp * INV_DX).floor();
-    // index of the cell containing the particle (usting graphics coords from top-left)
-    let containing_idx = containing_cell.as_ivec2();
-    let containing_center = (containing_cell + vec2(0.5, 0.5)) * DX;
     for o in 0..9 {
-        let offset = STENCIL_OFFSETS[o];
-        let grid_idx = containing_idx + offset;
-        if grid_idx.x < 0
-            || grid_idx.y < 0
-            || grid_idx.x >= N_GRID_X as i32
-            || grid_idx.y >= N_GRID_X as i32
-        {
+        let node = transfer.node(o);
+        if node.index == usize::MAX {
             continue;
         }
-        let index = unsafe {
-            // Safety: bounds just checked above
-            linear_grid_index_ivec_unchecked(grid_idx)
-        };
-
-        let grid_pos = containing_center + (offset.as_vec2()) * DX;
-
-        let weight = quadratic_weight_2d(xp - grid_pos);
-
-        let m = &mut grid[index].mass;
-        let v = &mut grid[index].v;
-        let mass_add = weight * P_MASS;
-        let v_add = weight * (P_MASS * vp + affine_stress.mul_vec2(grid_pos));
-        unsafe { atomic_f_add::<_, SCOPE, SEMANTICS>(m, mass_add) };
-        unsafe { atomic_f_add_vec2::<SCOPE, SEMANTICS>(v, v_add) };
+        let m = &mut grid[node.index].mass;
+        let v = &mut grid[node.index].v;
+        unsafe { atomic_f_add::<SCOPE, SEMANTICS>(m, node.mass) };
+        unsafe { atomic_f_add_vec2::<SCOPE, SEMANTICS>(v, node.momentum) };
     }
 }
@@ -1,6 +1,75 @@
-use spirv_std::{arch::atomic_f_add, glam::Vec2};
+use spirv_std::glam::Vec2;
+
+/// Atomically add `val` to `*dst`, returning the previous value.
+///
+/// On the GPU this is `OpAtomicFAddEXT`. When the shaders crate is built for the
+/// host (to run entry points through the CPU dispatch emulator), it is emulated
+/// with a compare-exchange loop on the bits of the float as an `AtomicU32`.
+///
+/// # Safety
+/// Invocations running concurrently must only access `*dst` atomically.
+#[inline]
+pub unsafe fn atomic_f_add<const SCOPE: u32, const SEMANTICS: u32>(dst: &mut f32, val: f32) -> f32 {
+    #[cfg(target_arch = "spirv")]
+    {
+        spirv_std::arch::atomic_f_add::<_, SCOPE, SEMANTICS>(dst, val)
+    }
+    #[cfg(not(target_arch = "spirv"))]
+    {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        let atomic = AtomicU32::from_ptr((dst as *mut f32).cast::<u32>());
+        let prev = atomic
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f32::from_bits(bits) + val).to_bits())
+            })
+            .unwrap_or_else(|bits| bits);
+        f32::from_bits(prev)
+    }
+}
 
 pub unsafe fn atomic_f_add_vec2<const SCOPE: u32, const SEMANTICS: u32>(dst: &mut Vec2, val: Vec2) {
-    atomic_f_add::<_, SCOPE, SEMANTICS>(&mut dst.x, val.x);
-    atomic_f_add::<_, SCOPE, SEMANTICS>(&mut dst.y, val.y);
+    atomic_f_add::<SCOPE, SEMANTICS>(&mut dst.x, val.x);
+    atomic_f_add::<SCOPE, SEMANTICS>(&mut dst.y, val.y);
+}
+
+/// Atomically add `val` to the `f32` whose bits are stored in `*dst`, returning the
+/// previous value.
+///
+/// Unlike [`atomic_f_add`] this only needs 32-bit integer atomics: it is a
+/// compare-exchange loop on the bits, so it works on the `u32` words of a raw heap.
+///
+/// # Safety
+/// Invocations running concurrently must only access `*dst` atomically.
+#[inline]
+pub unsafe fn atomic_f_add_bits<const SCOPE: u32, const SEMANTICS: u32>(
+    dst: &mut u32,
+    val: f32,
+) -> f32 {
+    #[cfg(target_arch = "spirv")]
+    {
+        let mut old = spirv_std::arch::atomic_load::<u32, SCOPE, SEMANTICS>(dst);
+        loop {
+            let new = (f32::from_bits(old) + val).to_bits();
+            let prev = spirv_std::arch::atomic_compare_exchange::<u32, SCOPE, SEMANTICS, SEMANTICS>(
+                dst, new, old,
+            );
+            if prev == old {
+                return f32::from_bits(prev);
+            }
+            old = prev;
+        }
+    }
+    #[cfg(not(target_arch = "spirv"))]
+    {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        let atomic = AtomicU32::from_ptr(dst as *mut u32);
+        let prev = atomic
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f32::from_bits(bits) + val).to_bits())
+            })
+            .unwrap_or_else(|bits| bits);
+        f32::from_bits(prev)
+    }
 }
@@ -3,8 +3,12 @@ use core::clone::Clone;
 use bytemuck::{Pod, Zeroable};
 use spirv_std::glam::{IVec2, UVec2, Vec2};
 
-use crate::N_GRID_X;
+use crate::{DT, N_GRID_X};
 
+/// A single grid node.
+///
+/// After p2g, `v` holds the accumulated *momentum* of the node; `grid_update`
+/// divides it by `mass` so that g2p reads a velocity.
 #[allow(non_snake_case)]
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -101,3 +105,211 @@ pub struct GridPushConstants {
     pub grid_width: u32,
     pub grid_height: u32,
 }
+
+/// How grid nodes next to the domain walls are treated by the grid update.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BoundaryCondition {
+    /// Nodes near a wall have their velocity zeroed.
+    Sticky = 0,
+    /// The velocity component normal to the wall is zeroed, tangential motion is kept.
+    Slip = 1,
+    /// Like `Slip`, but only when moving into the wall; material may separate from it.
+    Separate = 2,
+}
+
+impl TryFrom<u32> for BoundaryCondition {
+    type Error = &'static str;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(BoundaryCondition::Sticky),
+            1 => Ok(BoundaryCondition::Slip),
+            2 => Ok(BoundaryCondition::Separate),
+            _ => Err("Invalid BoundaryCondition value"),
+        }
+    }
+}
+
+/// Parameters for the grid update kernel, shared between CPU and GPU.
+///
+/// Positions are in screen-like coordinates (y points down, see the render shaders),
+/// so a downward gravity has a positive y component.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GridUpdateParams {
+    /// Acceleration applied to every node with mass
+    pub gravity: Vec2,
+    /// Additional force applied to every node with mass (divided by the node mass)
+    pub external_force: Vec2,
+    /// Time step used to integrate the accelerations
+    pub dt: f32,
+    /// A [`BoundaryCondition`] as u32
+    pub boundary_condition: u32,
+    /// Number of cells from each wall that the boundary condition applies to
+    pub boundary_thickness: u32,
+    pub _padding: u32,
+}
+
+impl GridUpdateParams {
+    #[inline]
+    pub fn new(gravity: Vec2, boundary_condition: BoundaryCondition) -> Self {
+        Self {
+            gravity,
+            external_force: Vec2::ZERO,
+            dt: DT,
+            boundary_condition: boundary_condition as u32,
+            boundary_thickness: 3,
+            _padding: 0,
+        }
+    }
+
+    /// The boundary condition, or `Sticky` if `boundary_condition` holds no valid one,
+    /// which still keeps the material inside the domain
+    #[inline]
+    pub fn boundary_condition(&self) -> BoundaryCondition {
+        BoundaryCondition::try_from(self.boundary_condition).unwrap_or(BoundaryCondition::Sticky)
+    }
+}
+
+/// Grid update for a single node at grid index `idx`.
+///
+/// Turns the momentum accumulated by p2g into a velocity, integrates gravity and
+/// external forces, and enforces the boundary condition on the border cells.
+/// Nodes without mass get a zero velocity.
+#[inline(always)]
+pub fn update_grid_cell(cell: GridCell, idx: UVec2, params: &GridUpdateParams) -> GridCell {
+    if cell.mass <= 0.0 {
+        return GridCell {
+            v: Vec2::ZERO,
+            mass: cell.mass,
+        };
+    }
+
+    let mut v = cell.v / cell.mass;
+    v += params.dt * (params.gravity + params.external_force / cell.mass);
+
+    let t = params.boundary_thickness;
+    let near_lo_x = idx.x < t;
+    let near_hi_x = idx.x + t >= N_GRID_X;
+    let near_lo_y = idx.y < t;
+    let near_hi_y = idx.y + t >= N_GRID_X;
+
+    match params.boundary_condition() {
+        BoundaryCondition::Sticky => {
+            if near_lo_x || near_hi_x || near_lo_y || near_hi_y {
+                v = Vec2::ZERO;
+            }
+        }
+        BoundaryCondition::Slip => {
+            if near_lo_x || near_hi_x {
+                v.x = 0.0;
+            }
+            if near_lo_y || near_hi_y {
+                v.y = 0.0;
+            }
+        }
+        BoundaryCondition::Separate => {
+            if (near_lo_x && v.x < 0.0) || (near_hi_x && v.x > 0.0) {
+                v.x = 0.0;
+            }
+            if (near_lo_y && v.y < 0.0) || (near_hi_y && v.y > 0.0) {
+                v.y = 0.0;
+            }
+        }
+    }
+
+    GridCell { v, mass: cell.mass }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(bc: BoundaryCondition) -> GridUpdateParams {
+        GridUpdateParams {
+            gravity: Vec2::new(0.0, 10.0),
+            external_force: Vec2::ZERO,
+            dt: 0.1,
+            boundary_condition: bc as u32,
+            boundary_thickness: 2,
+            _padding: 0,
+        }
+    }
+
+    #[test]
+    fn interior_cell_normalizes_momentum_and_applies_gravity() {
+        let cell = GridCell {
+            v: Vec2::new(2.0, 4.0),
+            mass: 2.0,
+        };
+        let out = update_grid_cell(cell, UVec2::new(10, 10), &params(BoundaryCondition::Sticky));
+        assert_eq!(out.mass, 2.0);
+        assert!((out.v - Vec2::new(1.0, 3.0)).length() < 1e-6, "{:?}", out.v);
+    }
+
+    #[test]
+    fn empty_cell_has_zero_velocity() {
+        let cell = GridCell {
+            v: Vec2::new(2.0, 4.0),
+            mass: 0.0,
+        };
+        let out = update_grid_cell(cell, UVec2::new(10, 10), &params(BoundaryCondition::Slip));
+        assert_eq!(out.v, Vec2::ZERO);
+    }
+
+    #[test]
+    fn boundary_conditions_at_left_wall() {
+        // moving into the left wall and downwards
+        let cell = GridCell {
+            v: Vec2::new(-1.0, 0.0),
+            mass: 1.0,
+        };
+        let idx = UVec2::new(1, 10);
+
+        let sticky = update_grid_cell(cell, idx, &params(BoundaryCondition::Sticky));
+        assert_eq!(sticky.v, Vec2::ZERO);
+
+        let slip = update_grid_cell(cell, idx, &params(BoundaryCondition::Slip));
+        assert_eq!(slip.v, Vec2::new(0.0, 1.0));
+
+        let separate = update_grid_cell(cell, idx, &params(BoundaryCondition::Separate));
+        assert_eq!(separate.v, Vec2::new(0.0, 1.0));
+
+        // moving away from the wall is only allowed by `Separate`
+        let leaving = GridCell {
+            v: Vec2::new(1.0, 0.0),
+            mass: 1.0,
+        };
+        let slip = update_grid_cell(leaving, idx, &params(BoundaryCondition::Slip));
+        assert_eq!(slip.v, Vec2::new(0.0, 1.0));
+        let separate = update_grid_cell(leaving, idx, &params(BoundaryCondition::Separate));
+        assert_eq!(separate.v, Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn invalid_boundary_condition_is_sticky() {
+        assert!(BoundaryCondition::try_from(3).is_err());
+
+        let cell = GridCell {
+            v: Vec2::new(-1.0, 0.0),
+            mass: 1.0,
+        };
+        let mut invalid = params(BoundaryCondition::Slip);
+        invalid.boundary_condition = 3;
+        assert_eq!(invalid.boundary_condition(), BoundaryCondition::Sticky);
+        let out = update_grid_cell(cell, UVec2::new(1, 10), &invalid);
+        assert_eq!(out.v, Vec2::ZERO);
+    }
+
+    #[test]
+    fn boundary_conditions_at_far_wall() {
+        let cell = GridCell {
+            v: Vec2::new(0.0, 5.0),
+            mass: 1.0,
+        };
+        let idx = UVec2::new(10, N_GRID_X - 1);
+        let separate = update_grid_cell(cell, idx, &params(BoundaryCondition::Separate));
+        assert_eq!(separate.v, Vec2::ZERO);
+    }
+}
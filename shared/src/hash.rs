@@ -0,0 +1,31 @@
+//! Integer hashing helpers for stateless pseudo-random numbers in shaders
+
+#[inline]
+pub fn wang32(mut x: u32) -> u32 {
+    x = x.wrapping_add(!x << 15);
+    x ^= x >> 10;
+    x = x.wrapping_add(x << 3);
+    x ^= x >> 6;
+    x = x.wrapping_add(!x << 11);
+    x ^ (x >> 16)
+}
+
+#[inline]
+pub fn hash_many<const N: usize>(xs: [u32; N]) -> u32 {
+    // seed
+    let mut acc: u32 = 0x9E37_79B9;
+
+    // FIXME use iterator
+    for i in 0..N {
+        let y = xs[i].wrapping_mul(0x9E37_79B9) ^ (xs[i] >> 16);
+        acc ^= y.wrapping_add(0x85EB_CA6B);
+        acc = acc.rotate_left(13);
+    }
+    wang32(acc)
+}
+
+/// Pseudo-random f32 in [0, 1] derived from `xs`
+#[inline]
+pub fn rand_f32<const N: usize>(xs: [u32; N]) -> f32 {
+    (hash_many(xs) as f32) / (u32::MAX as f32)
+}
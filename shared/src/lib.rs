@@ -2,8 +2,10 @@
 #![no_std]
 
 pub mod grid;
+pub mod hash;
 pub mod mpm_utils;
 pub mod particles;
+pub mod svd;
 
 pub struct RowA {
     pub x: u32,
@@ -45,8 +47,10 @@ pub const MU_0: f32 = YOUNGS_MODULUS / (2.0 * (1.0 + POISSON_RATIO));
 pub const LAMBDA_0: f32 =
     YOUNGS_MODULUS * POISSON_RATIO / ((1.0 + POISSON_RATIO) * (1.0 - 2.0 * POISSON_RATIO));
 
-/// Workgroup size for compute shaders
-/// IMPORTANT: This must be kept in sync with the literal value in kernel/src/lib.rs
+/// Workgroup size for compute shaders, for callers that count workgroups themselves
+///
+/// Kernels dispatched `over_buffer` or `over_grid_2d` read the size from the compiled
+/// shader instead; `tests/reflection.rs` checks these against it.
 pub const WORKGROUP_SIZE: u32 = 64;
 pub const GRID_WORKGROUP_SIZE: (u32, u32) = (8, 8);
 
@@ -68,6 +72,31 @@ pub fn num_workgroups_2d(num_elts_x: u32, num_elts_y: u32) -> [u32; 3] {
     ]
 }
 
+/// Workgroup counts of an indirect dispatch, laid out as `vkCmdDispatchIndirect`
+/// reads them
+///
+/// A kernel writes it into a buffer, and a later invocation of the chain dispatched
+/// with `invoc_spec_indirect` reads its workgroup count from the first element.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DispatchIndirectCommand {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl DispatchIndirectCommand {
+    /// Enough workgroups of `WORKGROUP_SIZE` for `num_elements` invocations
+    pub fn over_1d(num_elements: u32) -> Self {
+        let [x, y, z] = num_workgroups_1d(num_elements);
+        Self { x, y, z }
+    }
+
+    pub fn num_workgroups(&self) -> [u32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
 /// The constant used in the computation (index * 2 + COMPUTE_CONSTANT)
 pub const COMPUTE_CONSTANT: u32 = 42;
 
@@ -106,6 +135,115 @@ pub struct PushConstants {
     pub num_elements: u32,
 }
 
+/// Push constants of the `add_scalar` kernel
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ScalarPushConstants {
+    pub scalar: u32,
+    pub num_elements: u32,
+}
+
+/// Push constants of the bindless `adder` kernel: where the logical buffers `a` and
+/// `b` start (in elements) in the unified u32 buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AdderPushConstants {
+    pub a_offset: u32,
+    pub b_offset: u32,
+    /// Number of elements to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the bindless particle kernels (`step_particles`, `wrap_particles`):
+/// where `x` and `v` start (in elements) in the unified Vec2 buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ParticlePushConstants {
+    pub x_offset: u32,
+    /// Unused by `wrap_particles`
+    pub v_offset: u32,
+    /// Number of particles to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the bindless grid kernels: where the grid starts (in cells) in the
+/// unified `GridCell` buffer, and its dimensions
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BindlessGridPushConstants {
+    pub grid_offset: u32,
+    pub n_grid_x: u32,
+    pub n_grid_y: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the device address `adder` kernel: the `VkDeviceAddress` of the
+/// logical buffers `a` and `b`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AdderAddressPushConstants {
+    pub a: u64,
+    pub b: u64,
+    /// Number of elements to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the device address particle kernels (`step_particles`,
+/// `wrap_particles`): the `VkDeviceAddress` of `x` and `v`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ParticleAddressPushConstants {
+    pub x: u64,
+    /// Unused by `wrap_particles`
+    pub v: u64,
+    /// Number of particles to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the descriptor indexing `adder` kernel: the slots of the logical
+/// buffers `a` and `b` in the array of storage buffer descriptors
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AdderSlotPushConstants {
+    pub a: u32,
+    pub b: u32,
+    /// Number of elements to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the descriptor indexing particle kernels (`step_particles`,
+/// `wrap_particles`): the slots of `x` and `v`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ParticleSlotPushConstants {
+    pub x: u32,
+    /// Unused by `wrap_particles`
+    pub v: u32,
+    /// Number of particles to process
+    pub buffer_size: u32,
+    pub _padding: u32,
+}
+
+/// Push constants of the bindless heap `p2g` kernel: the byte offset of each
+/// logical buffer within the heap
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct P2gHeapPushConstants {
+    pub x: u32,
+    pub v: u32,
+    pub grid: u32,
+    pub matrices: u32,
+    pub deformation: u32,
+    pub material: u32,
+    pub num_particles: u32,
+    pub _padding: u32,
+}
+
 // Bitonic sort implementation
 // A comparison-based sorting algorithm well-suited for parallel execution on GPUs
 
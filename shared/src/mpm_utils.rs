@@ -1,4 +1,13 @@
-use spirv_std::glam::Vec2;
+use spirv_std::glam::{Mat2, Vec2};
+
+#[cfg(not(test))]
+use spirv_std::num_traits::float::Float;
+
+use crate::{
+    particles::Material,
+    svd::{svd2x2_exact, Svd2},
+    DT, INV_DX, LAMBDA_0, MU_0, P_MASS, P_VOL,
+};
 
 #[inline(always)]
 /// compute the weights for quadratic B-spline at position x, where x is scaled relative to a unit-sized grid cell.
@@ -12,7 +21,7 @@ pub fn quadratic_weight(x: f32) -> f32 {
     if x_abs < 0.5 {
         0.75 - x * x
     } else if x_abs < 1.5 {
-        0.5 * (1.5 - x) * (1.5 - x)
+        0.5 * (1.5 - x_abs) * (1.5 - x_abs)
     } else {
         0.0
     }
@@ -25,3 +34,120 @@ pub fn quadratic_weight(x: f32) -> f32 {
 pub fn quadratic_weight_2d(fx: Vec2) -> f32 {
     quadratic_weight(fx.x) * quadratic_weight(fx.y)
 }
+
+/// Result of the per-particle constitutive update performed at the start of p2g.
+#[allow(non_snake_case)]
+#[derive(Copy, Clone, Debug)]
+pub struct ConstitutiveUpdate {
+    /// Updated (elastic) deformation gradient
+    pub F: Mat2,
+    /// Updated plastic deformation determinant
+    pub Jp: f32,
+    /// Combined stress and APIC momentum term, to be applied as `affine * (x_i - x_p)`
+    /// when scattering momentum to grid node `x_i`.
+    pub affine: Mat2,
+}
+
+/// Advance the deformation gradient of a particle by `DT`, apply the material's
+/// plasticity model, and compute the affine momentum term used by p2g.
+///
+/// Follows the MLS-MPM scheme of "A Moving Least Squares Material Point Method
+/// with Displacement Discontinuity and Two-Way Rigid Body Coupling" (Hu et al. 2018).
+#[allow(non_snake_case)]
+#[inline(always)]
+pub fn constitutive_update(C: Mat2, F: Mat2, Jp: f32, material: Material) -> ConstitutiveUpdate {
+    // update deformation gradient F
+    let mut F = (Mat2::IDENTITY + DT * C).mul_mat2(&F);
+    let mut Jp = Jp;
+    // hardening coefficient
+    let h = (10.0 * (1.0 - Jp)).exp().clamp(0.1, 5.0);
+
+    let (mu, lambda) = match material {
+        Material::Fluid => (0.0, 0.0),
+        // jelly-like solid, less stiff; h=0.3
+        Material::Jelly => (MU_0 * 0.3, LAMBDA_0 * 0.3),
+        Material::Snow => (MU_0 * h, LAMBDA_0 * h),
+    };
+
+    let Svd2 {
+        u: U,
+        s: mut sig,
+        v: V,
+    } = svd2x2_exact(F);
+    let mut J = 1.0;
+    for d in 0..2 {
+        let mut new_sig = sig[d];
+        if matches!(material, Material::Snow) {
+            // plasticity for snow
+            new_sig = new_sig.clamp(1.0 - 2.5e-2, 1.0 + 4.5e-3);
+        }
+        Jp *= sig[d] / new_sig;
+        sig[d] = new_sig;
+        J *= new_sig;
+    }
+    match material {
+        Material::Fluid => {
+            F = Mat2::IDENTITY * J.sqrt();
+        }
+        Material::Snow | Material::Jelly => {
+            // Reconstruct F
+            F = U
+                .mul_mat2(&Mat2::from_diagonal(sig))
+                .mul_mat2(&V.transpose());
+        }
+    }
+    let stress = 2.0 * mu * (F - U.mul_mat2(&V.transpose())) * F.transpose()
+        + Mat2::IDENTITY * lambda * (J - 1.0) * J;
+    // scale by -dt * volume * D^-1, with D^-1 = 4 / dx^2 for quadratic B-splines
+    let affine = (-DT * P_VOL * 4.0 * INV_DX * INV_DX) * stress + P_MASS * C;
+
+    ConstitutiveUpdate { F, Jp, affine }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quadratic_weight_is_symmetric() {
+        for x in [0.0, 0.25, 0.5, 0.9, 1.4, 1.6] {
+            assert_eq!(quadratic_weight(x), quadratic_weight(-x), "x = {x}");
+        }
+        assert_eq!(quadratic_weight(1.5), 0.0);
+        assert_eq!(quadratic_weight(-2.0), 0.0);
+    }
+
+    #[test]
+    fn quadratic_weights_partition_unity_over_stencil() {
+        // particle anywhere inside the center cell of a 3x3 stencil of cell-centered nodes
+        for fx in [-0.5, -0.3, 0.0, 0.2, 0.49] {
+            for fy in [-0.45, 0.0, 0.1, 0.5] {
+                let p = Vec2::new(fx, fy);
+                let mut sum = 0.0;
+                for i in -1..=1 {
+                    for j in -1..=1 {
+                        sum += quadratic_weight_2d(p - Vec2::new(i as f32, j as f32));
+                    }
+                }
+                assert!((sum - 1.0).abs() < 1e-6, "sum = {sum} at {p:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fluid_at_rest_has_no_stress() {
+        let update = constitutive_update(Mat2::ZERO, Mat2::IDENTITY, 1.0, Material::Fluid);
+        assert_eq!(update.F, Mat2::IDENTITY);
+        assert_eq!(update.Jp, 1.0);
+        assert_eq!(update.affine, Mat2::ZERO);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn compressed_jelly_pushes_outward() {
+        let F = Mat2::from_diagonal(Vec2::new(0.9, 1.0));
+        let update = constitutive_update(Mat2::ZERO, F, 1.0, Material::Jelly);
+        // stress is negative under compression, so the -dt scaled affine term is positive
+        assert!(update.affine.x_axis.x > 0.0, "{:?}", update.affine);
+    }
+}
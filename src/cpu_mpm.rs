@@ -0,0 +1,196 @@
+//! CPU reference implementation of the MLS-MPM step.
+//!
+//! Mirrors the `clear_grid`, `p2g::p2g`, `grid_update::grid_update` and `g2p::g2p`
+//! kernels on plain `Vec`s, using the same math and constants from the `shared`
+//! crate and rayon for parallelism. It needs no GPU, so tests can check the
+//! physics on any machine, and GPU results have an oracle to compare against.
+
+use bytemuck::Zeroable;
+use glam::{vec2, Mat2, UVec2, Vec2};
+use rayon::prelude::*;
+use shared::{
+    grid::{linear_grid_index_ivec, update_grid_cell, GridCell, GridUpdateParams, STENCIL_OFFSETS},
+    mpm_utils::{constitutive_update, quadratic_weight_2d},
+    particles::{MaterialPod, ParticleDeformation, ParticleMatrices},
+    DT, DX, INV_DX, N_GRID_TOTAL, N_GRID_X, P_MASS,
+};
+
+/// Grid nodes in the 3x3 stencil around `xp` that lie inside the grid, as
+/// `(linear index, node position - xp, weight)`.
+fn stencil_nodes(xp: Vec2) -> impl Iterator<Item = (usize, Vec2, f32)> {
+    let containing_cell = (xp * INV_DX).floor();
+    let containing_idx = containing_cell.as_ivec2();
+    let containing_center = (containing_cell + vec2(0.5, 0.5)) * DX;
+    STENCIL_OFFSETS.into_iter().filter_map(move |offset| {
+        let index = linear_grid_index_ivec(containing_idx + offset);
+        if index == usize::MAX {
+            return None;
+        }
+        let dpos = containing_center + offset.as_vec2() * DX - xp;
+        Some((index, dpos, quadratic_weight_2d(dpos * INV_DX)))
+    })
+}
+
+/// Zero every grid node, like the `clear_grid` kernel.
+pub fn clear_grid(grid: &mut [GridCell]) {
+    grid.par_iter_mut().for_each(|cell| *cell = GridCell::zeroed());
+}
+
+/// Particle-to-grid transfer, like the `p2g::p2g` kernel.
+///
+/// Updates the deformation state of every particle and *adds* mass and momentum to
+/// `grid`, so the grid should be cleared first. Each rayon job scatters into its
+/// own copy of the grid; the copies are summed at the end instead of using atomics.
+pub fn p2g(
+    x: &[Vec2],
+    v: &[Vec2],
+    grid: &mut [GridCell],
+    particle_matrices: &mut [ParticleMatrices],
+    particle_deformation: &mut [ParticleDeformation],
+    particle_material: &[MaterialPod],
+) {
+    let affines = particle_matrices
+        .par_iter_mut()
+        .zip(particle_deformation.par_iter_mut())
+        .zip(particle_material.par_iter())
+        .map(|((matrices, deformation), material)| {
+            let update = constitutive_update(
+                matrices.C,
+                matrices.F,
+                deformation.J,
+                material.to_material(),
+            );
+            matrices.F = update.F;
+            deformation.J = update.Jp;
+            update.affine
+        })
+        .collect::<Vec<Mat2>>();
+
+    let n_cells = grid.len();
+    let scattered = x
+        .par_iter()
+        .zip(v.par_iter())
+        .zip(affines.par_iter())
+        .fold(
+            || vec![GridCell::zeroed(); n_cells],
+            |mut local, ((&xp, &vp), &affine)| {
+                for (index, dpos, weight) in stencil_nodes(xp) {
+                    local[index].mass += weight * P_MASS;
+                    local[index].v += weight * (P_MASS * vp + affine.mul_vec2(dpos));
+                }
+                local
+            },
+        )
+        .reduce(
+            || vec![GridCell::zeroed(); n_cells],
+            |mut acc, local| {
+                add_grids(&mut acc, &local);
+                acc
+            },
+        );
+    add_grids(grid, &scattered);
+}
+
+fn add_grids(dst: &mut [GridCell], src: &[GridCell]) {
+    dst.par_iter_mut().zip(src.par_iter()).for_each(|(d, s)| {
+        d.v += s.v;
+        d.mass += s.mass;
+    });
+}
+
+/// Grid update, like the `grid_update::grid_update` kernel.
+pub fn grid_update(grid: &mut [GridCell], params: &GridUpdateParams) {
+    grid.par_iter_mut().enumerate().for_each(|(i, cell)| {
+        let idx = UVec2::new(i as u32 % N_GRID_X, i as u32 / N_GRID_X);
+        *cell = update_grid_cell(*cell, idx, params);
+    });
+}
+
+/// Grid-to-particle transfer, like the `g2p::g2p` kernel.
+///
+/// Gathers velocities and the APIC matrix `C` from the grid and advects `x` by `DT`.
+#[allow(non_snake_case)]
+pub fn g2p(
+    x: &mut [Vec2],
+    v: &mut [Vec2],
+    grid: &[GridCell],
+    particle_matrices: &mut [ParticleMatrices],
+) {
+    x.par_iter_mut()
+        .zip(v.par_iter_mut())
+        .zip(particle_matrices.par_iter_mut())
+        .for_each(|((xp, vp), matrices)| {
+            let mut new_v = Vec2::ZERO;
+            let mut new_C = Mat2::ZERO;
+            for (index, dpos, weight) in stencil_nodes(*xp) {
+                let grid_v = grid[index].v;
+                new_v += weight * grid_v;
+                new_C += (4.0 * INV_DX * INV_DX * weight)
+                    * Mat2::from_cols(grid_v * dpos.x, grid_v * dpos.y);
+            }
+            *vp = new_v;
+            matrices.C = new_C;
+            *xp += DT * new_v;
+        });
+}
+
+/// All particle and grid state of a CPU simulation, laid out like the GPU buffers
+/// of the same names.
+pub struct CpuMpm {
+    pub x: Vec<Vec2>,
+    pub v: Vec<Vec2>,
+    pub particle_matrices: Vec<ParticleMatrices>,
+    pub particle_deformation: Vec<ParticleDeformation>,
+    pub particle_material: Vec<MaterialPod>,
+    pub grid: Vec<GridCell>,
+    pub grid_update_params: GridUpdateParams,
+}
+
+impl CpuMpm {
+    /// Create a simulation with undeformed particles and an empty grid.
+    ///
+    /// Panics if `x`, `v` and `particle_material` have different lengths.
+    pub fn new(
+        x: Vec<Vec2>,
+        v: Vec<Vec2>,
+        particle_material: Vec<MaterialPod>,
+        grid_update_params: GridUpdateParams,
+    ) -> Self {
+        let n = x.len();
+        assert_eq!(v.len(), n, "x and v must have the same length");
+        assert_eq!(
+            particle_material.len(),
+            n,
+            "x and particle_material must have the same length"
+        );
+        Self {
+            x,
+            v,
+            particle_matrices: vec![ParticleMatrices::new(); n],
+            particle_deformation: vec![ParticleDeformation::new(); n],
+            particle_material,
+            grid: vec![GridCell::zeroed(); N_GRID_TOTAL as usize],
+            grid_update_params,
+        }
+    }
+
+    /// Advance the simulation by one time step: clear grid, p2g, grid update, g2p.
+    pub fn step(&mut self) {
+        clear_grid(&mut self.grid);
+        p2g(
+            &self.x,
+            &self.v,
+            &mut self.grid,
+            &mut self.particle_matrices,
+            &mut self.particle_deformation,
+            &self.particle_material,
+        );
+        grid_update(&mut self.grid, &self.grid_update_params);
+        g2p(
+            &mut self.x,
+            &mut self.v,
+            &self.grid,
+            &mut self.particle_matrices,
+        );
+    }
+}
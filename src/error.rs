@@ -20,9 +20,148 @@ pub enum ChimeraError {
         found: usize,
     },
 
+    #[error("Invocation `{invocation_name}` has {found} buffers, but its push constants hold between {min} and {max}")]
+    PushConstantBufferCountOutOfRange {
+        invocation_name: String,
+        min: usize,
+        max: usize,
+        found: usize,
+    },
+
     #[error("No descriptor set with type `{0}` named `{1}` found")]
     TypedDescriptorSetNameNotFound(String, String),
 
+    #[error("Buffer `{name}` holds `{stored}` elements, not `{requested}`")]
+    BufferTypeMismatch {
+        name: String,
+        stored: String,
+        requested: String,
+    },
+
+    #[error("Buffer holds `{stored}` elements, not `{requested}`")]
+    AnyBufferTypeMismatch { stored: String, requested: String },
+
+    #[error("Buffer has {buffer_len} elements, but {data_len} were given")]
+    BufferLengthMismatch { buffer_len: usize, data_len: usize },
+
+    #[error("Writing {data_len} elements at offset {offset} overruns buffer `{name}` of {buffer_len} elements")]
+    BufferRangeOutOfBounds {
+        name: String,
+        offset: usize,
+        data_len: usize,
+        buffer_len: usize,
+    },
+
+    #[error(
+        "Buffer `{0}` is device-local: read it with `readback` and write it with `write_buffer`"
+    )]
+    DeviceLocalBuffer(String),
+
+    #[error("Buffer `{0}` can't have zero elements")]
+    ZeroLengthBuffer(String),
+
+    #[error("Buffer name `{0}` is used more than once")]
+    DuplicateBufferName(String),
+
+    #[error("Invocation `{invocation_name}` has no buffer at binding {binding}")]
+    BindingNotInInvocation {
+        invocation_name: String,
+        binding: u32,
+    },
+
+    #[error("Invocation `{invocation_name}` annotates the access to buffer `{buf_name}`, which it does not bind")]
+    AccessAnnotationNotBound {
+        invocation_name: String,
+        buf_name: String,
+    },
+
+    #[error("Invocation `{invocation_name}` is dispatched over buffer `{buf_name}`, which it does not bind")]
+    DispatchBufferNotBound {
+        invocation_name: String,
+        buf_name: String,
+    },
+
+    #[error("Invocation `{invocation_name}` computes its push constants from the length of buffer `{buf_name}`, which it does not bind")]
+    PushConstantBufferNotBound {
+        invocation_name: String,
+        buf_name: String,
+    },
+
+    #[error("Invocation `{invocation_name}` uses buffer `{buf_name}`, but its {fixed} can't follow a resize")]
+    FixedInvocationOverResizedBuffer {
+        invocation_name: String,
+        buf_name: String,
+        fixed: String,
+    },
+
+    #[error("Invocation `{invocation_name}` reads its workgroup count from buffer `{buf_name}` with {buffer_element_size} byte elements, but a `DispatchIndirectCommand` is 12 bytes")]
+    IndirectBufferElementSizeMismatch {
+        invocation_name: String,
+        buf_name: String,
+        buffer_element_size: usize,
+    },
+
+    #[error("Invocation `{invocation_name}` reads its workgroup count from buffer `{buf_name}`, which is empty")]
+    IndirectBufferEmpty {
+        invocation_name: String,
+        buf_name: String,
+    },
+
+    #[error("Entry point `{0}` has no LocalSize execution mode")]
+    MissingLocalSize(String),
+
+    #[error("A chain runs at least one substep per execution")]
+    ZeroSubsteps,
+
+    #[error("A chain needs at least one frame in flight")]
+    ZeroFramesInFlight,
+
+    #[error("{requested} buffers don't fit in a descriptor array of {capacity} slots")]
+    TooManyBufferSlots { requested: usize, capacity: u32 },
+
+    #[error("Host buffer `{0}` is already borrowed by the running invocation")]
+    HostBufferAliased(String),
+
+    #[error("Invalid SPIR-V module: {0}")]
+    SpirvReflection(String),
+
+    #[error("Invocation `{invocation_name}` binds {kernel_bindings:?}, but entry point `{entry_point}` uses storage buffer bindings {shader_bindings:?}")]
+    KernelBindingMismatch {
+        invocation_name: String,
+        entry_point: String,
+        kernel_bindings: Vec<u32>,
+        shader_bindings: Vec<u32>,
+    },
+
+    #[error("Invocation `{invocation_name}` push constants don't match entry point `{entry_point}` (shader has push constants: {shader_has_push_constants})")]
+    PushConstantsMismatch {
+        invocation_name: String,
+        entry_point: String,
+        shader_has_push_constants: bool,
+    },
+
+    #[error("Invocation `{0}` has no push constants")]
+    MissingPushConstants(String),
+
+    #[error("Entry point `{entry_point}` has no storage buffer parameter named `{param}`")]
+    ShaderParameterNotFound { entry_point: String, param: String },
+
+    #[error("Entry point `{entry_point}` uses descriptor set {descriptor_set} (binding {binding}); only set 0 is bound")]
+    UnsupportedDescriptorSet {
+        entry_point: String,
+        descriptor_set: u32,
+        binding: u32,
+    },
+
+    #[error("Invocation `{invocation_name}` binds buffer `{buf_name}` with {buffer_element_size} byte elements to binding {binding}, but the shader expects {shader_stride} byte elements")]
+    BufferElementSizeMismatch {
+        invocation_name: String,
+        buf_name: String,
+        binding: u32,
+        shader_stride: u32,
+        buffer_element_size: usize,
+    },
+
     #[error("Environment variable error: {0}")]
     VarError(#[from] std::env::VarError),
 
@@ -32,6 +171,15 @@ pub enum ChimeraError {
     #[error("No suitable Vulkan device found among {0} devices")]
     NoVulkanDevice(usize),
 
+    #[error(
+        "Device `{device_name}` does not support the features {missing} needed by {needed_by}"
+    )]
+    UnsupportedDeviceFeatures {
+        device_name: String,
+        needed_by: String,
+        missing: String,
+    },
+
     #[error("Buffer size overflow: {0} elements × {1} bytes per element")]
     BufferSizeOverflow(usize, usize),
 
@@ -6,11 +6,22 @@
 
 #![feature(once_cell_try)]
 
+pub mod cpu_mpm;
 pub mod error;
 pub mod graphics;
 pub mod runners;
 
 #[cfg(any(feature = "vulkano"))]
 pub const OTHER_SHADERS_SPIRV: &[u8] = include_bytes!(env!("SHADERS_SPV_PATH"));
+/// The `device_address::` shaders, in a module of their own because they need the
+/// `buffer_device_address` and `shader_int64` device features
+#[cfg(any(feature = "vulkano"))]
+pub const DEVICE_ADDRESS_SHADERS_SPIRV: &[u8] =
+    include_bytes!(env!("DEVICE_ADDRESS_SHADERS_SPV_PATH"));
+/// The `descriptor_array::` shaders, in a module of their own because they need the
+/// descriptor indexing device features
+#[cfg(any(feature = "vulkano"))]
+pub const DESCRIPTOR_ARRAY_SHADERS_SPIRV: &[u8] =
+    include_bytes!(env!("DESCRIPTOR_ARRAY_SHADERS_SPV_PATH"));
 #[cfg(any(feature = "vulkano"))]
 pub const SHADERS_ENTRY_ADDER: &str = env!("SHADERS_ENTRY_ADDER");
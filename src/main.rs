@@ -7,11 +7,15 @@ use bytemuck::Zeroable;
 use glam::Vec2;
 use rand::random;
 use rust_gpu_chimera_demo::{
+    error::CrateResult,
     graphics::GraphicsRenderer,
     runners::{
         vulkano::{
-            buffer_specs::{buf_spec, DescriptorSetByName, IntoDescriptorSetByName},
-            shader_pipeline_builder::{invoc_spec, kernel},
+            buffer_registry::RegistryBufferSpecs,
+            buffer_specs::{buf_spec, DescriptorSetByName, IntoDescriptorSetByName, Residency},
+            shader_pipeline_builder::{
+                inferred_kernel, invoc_spec, over_buffer, over_grid_2d, BufferAccess,
+            },
             typed_subbuffer_by_name::TypedSubbufferByName,
         },
         vulkano_compute_chain::VulkanoComputeChain,
@@ -19,8 +23,7 @@ use rust_gpu_chimera_demo::{
     *,
 };
 use shared::{
-    grid::GridCell,
-    num_workgroups_1d, num_workgroups_2d,
+    grid::{BoundaryCondition, GridCell, GridUpdateParams},
     particles::{Material, MaterialPod, ParticleDeformation, ParticleMatrices},
     MATERIAL_GROUP_SIZE, N_GRID_X, N_PARTICLES,
 };
@@ -29,9 +32,16 @@ use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
     event_loop::{ActiveEventLoop, EventLoop},
+    keyboard::Key,
     window::{Window, WindowId},
 };
 
+/// Simulation steps of `DT` run on the GPU for every rendered frame
+const SUBSTEPS_PER_FRAME: u32 = 20;
+
+/// Particles added by every press of S
+const SPAWNED_PARTICLES: usize = 1024;
+
 // Application state
 struct App<BS>
 where
@@ -108,16 +118,14 @@ where
         compute_chain.execute().unwrap();
 
         // Set up particle positions
-        let buffer_x = compute_chain.typed_subbuffer_by_name::<Vec2>("x").unwrap();
+        let buffer_x = compute_chain.snapshot::<Vec2>("x").unwrap();
         let num_particles = buffer_x.len() as usize;
         renderer
             .set_position_buffer(buffer_x, num_particles)
             .unwrap();
 
         // Set up grid buffer for heatmap rendering
-        let grid_buffer = compute_chain
-            .typed_subbuffer_by_name::<GridCell>("grid")
-            .unwrap();
+        let grid_buffer = compute_chain.snapshot::<GridCell>("grid").unwrap();
         // Grid is N x N where N is the number of particles (since grid is n*n elements)
         let grid_size = (num_particles as f64).sqrt() as u32;
         renderer
@@ -156,20 +164,40 @@ where
                     eprintln!("Resize error: {}", e);
                 }
             }
+            // R stops every particle, which then falls from rest
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed() && event.logical_key == Key::Character("r".into()) =>
+            {
+                let v = compute_chain
+                    .buffer_len("v")
+                    .map(|n_particles| vec![Vec2::ZERO; n_particles]);
+                if let Err(e) = v.and_then(|v| compute_chain.write_buffer("v", &v)) {
+                    eprintln!("Failed to reset velocities: {}", e);
+                }
+            }
+            // S adds a block of fluid particles
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state.is_pressed() && event.logical_key == Key::Character("s".into()) =>
+            {
+                let compute_chain = self.compute_chain.as_mut().unwrap();
+                match spawn_particles(compute_chain) {
+                    Ok(n_particles) => println!("{n_particles} particles"),
+                    Err(e) => eprintln!("Failed to spawn particles: {}", e),
+                }
+            }
             WindowEvent::RedrawRequested => {
-                // Run compute shader to update particle positions and grid
-                compute_chain.execute().unwrap();
+                // Start the next simulation steps without waiting for them; the frame
+                // shows the newest ones that finished
+                compute_chain.submit().unwrap();
 
-                let buffer_x = compute_chain.typed_subbuffer_by_name::<Vec2>("x").unwrap();
+                let buffer_x = compute_chain.snapshot::<Vec2>("x").unwrap();
                 let num_particles = buffer_x.len() as usize;
                 renderer
                     .set_position_buffer(buffer_x, num_particles)
                     .unwrap();
 
                 // Update grid buffer (it's regenerated each frame by fill_grid_random)
-                let grid_buffer = compute_chain
-                    .typed_subbuffer_by_name::<GridCell>("grid")
-                    .unwrap();
+                let grid_buffer = compute_chain.snapshot::<GridCell>("grid").unwrap();
 
                 renderer
                     .set_grid_buffer(grid_buffer, N_GRID_X, N_GRID_X)
@@ -182,29 +210,20 @@ where
 
                 self.frame_count += 1;
 
-                // Print progress every 60 frames
+                // Print progress every 60 frames, waiting for the steps in flight
                 if self.frame_count % 60 == 0 {
-                    let x_buf = self
-                        .compute_chain
-                        .as_ref()
-                        .unwrap()
-                        .typed_subbuffer_by_name::<Vec2>("x")
-                        .unwrap();
-                    let x_read = x_buf.read().unwrap();
+                    let x_read = compute_chain.readback::<Vec2>("x").unwrap();
                     let x_slice = &x_read[0..3];
 
-                    let grid_buf = self
-                        .compute_chain
-                        .as_ref()
-                        .unwrap()
-                        .typed_subbuffer_by_name::<GridCell>("grid")
-                        .unwrap();
-                    let g_read = grid_buf.read().unwrap();
+                    let g_read = compute_chain.readback::<GridCell>("grid").unwrap();
                     let g_slice = &g_read[0..3];
 
                     println!("Frame {}", self.frame_count);
                     println!("Particles (x) :\n   {:?}", x_slice);
-                    println!("GridCell buffer contents:\n   {:?}", g_slice)
+                    println!("GridCell buffer contents:\n   {:?}", g_slice);
+                    if let Some(profile) = compute_chain.profile().unwrap() {
+                        print!("GPU time per frame:\n{profile}");
+                    }
                 }
 
                 // Request next frame
@@ -215,6 +234,29 @@ where
     }
 }
 
+/// Grow every particle buffer by [`SPAWNED_PARTICLES`] fluid particles at rest in a
+/// random block, and return the new particle count
+fn spawn_particles<BS>(compute_chain: &mut VulkanoComputeChain<BS>) -> CrateResult<usize>
+where
+    BS: IntoDescriptorSetByName<Out: 'static + DescriptorSetByName + TypedSubbufferByName>,
+{
+    let n_particles = compute_chain.buffer_len("x")?;
+    let new_len = n_particles + SPAWNED_PARTICLES;
+
+    compute_chain.resize_buffer("x", new_len, Vec2::ZERO)?;
+    compute_chain.resize_buffer("v", new_len, Vec2::ZERO)?;
+    compute_chain.resize_buffer("particle_matrices", new_len, ParticleMatrices::new())?;
+    compute_chain.resize_buffer("particle_deformation", new_len, ParticleDeformation::new())?;
+    let fluid: MaterialPod = Material::Fluid.into();
+    compute_chain.resize_buffer("particle_material", new_len, fluid)?;
+
+    let x = (0..SPAWNED_PARTICLES)
+        .map(|_| Vec2::new(random::<f32>() * 0.2 + 0.4, random::<f32>() * 0.1 + 0.05))
+        .collect::<Vec<_>>();
+    compute_chain.write_range("x", n_particles, &x)?;
+    Ok(new_len)
+}
+
 fn main() -> Result<()> {
     println!("=== Rust GPU Compute + Graphics Demo ===\n");
 
@@ -271,66 +313,54 @@ fn main() -> Result<()> {
         .map(|_| GridCell::zeroed())
         .collect::<Vec<_>>();
 
-    let buf_specs = (
-        buf_spec("a", 0, &mut a),
-        buf_spec("b", 1, &mut b),
-        buf_spec("c", 2, &mut c),
-        buf_spec("d", 3, &mut d),
-        // particles
-        buf_spec("x", 2, &mut x),
-        buf_spec("v", 3, &mut v),
+    let mut grid_update_params = vec![GridUpdateParams::new(
+        Vec2::new(0.0, 9.8),
+        BoundaryCondition::Separate,
+    )];
+
+    let buf_specs = RegistryBufferSpecs::new()
+        .with(buf_spec("a", 0, &mut a))
+        .with(buf_spec("b", 1, &mut b))
+        .with(buf_spec("c", 2, &mut c))
+        .with(buf_spec("d", 3, &mut d))
+        // particles; only the kernels touch most of the state, which stays in device-local
+        // memory, while the host reads the positions back
+        .with(buf_spec("x", 2, &mut x).with_residency(Residency::Readback))
+        .with(buf_spec("v", 3, &mut v).with_residency(Residency::DeviceLocal))
         // grid
-        buf_spec("grid", 4, &mut grid),
-        buf_spec("particle_matrices", 5, &mut particle_matrices),
-        buf_spec("particle_deformation", 6, &mut particle_deformation),
-        buf_spec("particle_material", 7, &mut particle_material),
-    );
-
-    // Particle workgroups
-    let wg_particles = num_workgroups_1d(N_PARTICLES);
-    println!(
-        "Using {} workgroups for particles ({} total particles)",
-        wg_particles[0], N_PARTICLES
-    );
-    // particle kernels
-    let adder_kernel = kernel("adder", vec![0, 1], wg_particles);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_particles);
-    let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_particles);
-    let p2g_kernel = kernel("p2g::p2g", vec![2, 3, 4, 5, 6, 7], wg_particles);
-
-    // Grid workgroups
-    let wg_grid = num_workgroups_2d(N_GRID_X, N_GRID_X);
-    // grid kernels
-
-    let clear_grid_kernel = kernel("clear_grid", vec![4], wg_grid);
-
-    // let fill_grid_random_kernel = kernel("fill_grid_random", vec![4], wg_grid);
-    // let p2g_simple_test_kernel = kernel("p2g_simple_test", vec![2, 4], wg_particles);
+        .with(buf_spec("grid", 4, &mut grid).with_residency(Residency::DeviceLocal))
+        .with(
+            buf_spec("particle_matrices", 5, &mut particle_matrices)
+                .with_residency(Residency::DeviceLocal),
+        )
+        .with(
+            buf_spec("particle_deformation", 6, &mut particle_deformation)
+                .with_residency(Residency::DeviceLocal),
+        )
+        .with(buf_spec("particle_material", 7, &mut particle_material))
+        .with(buf_spec("grid_update_params", 8, &mut grid_update_params));
+
+    // particle kernels, one invocation per particle; the workgroup counts are
+    // computed from the buffer lengths when the chain is built
+    let adder_kernel = inferred_kernel("adder", over_buffer("a"))?;
+    let p2g_kernel = inferred_kernel("p2g::p2g", over_buffer("x"))?;
+    let g2p_kernel = inferred_kernel("g2p::g2p", over_buffer("x"))?;
+
+    // grid kernels, one invocation per cell
+    let grid_cells = over_grid_2d(N_GRID_X, N_GRID_X);
+    let clear_grid_kernel = inferred_kernel("clear_grid", grid_cells.clone())?;
+    let grid_update_kernel = inferred_kernel("grid_update::grid_update", grid_cells)?;
+
+    // let fill_grid_random_kernel = inferred_kernel("fill_grid_random", grid_cells.clone())?;
+    // let p2g_simple_test_kernel = inferred_kernel("p2g_simple_test", over_buffer("x"))?;
 
+    // Access annotations let the chain skip barriers between independent invocations
+    // (adder_ab and clear_grid run unordered)
     let invocation_chain = vec![
-        invoc_spec("adder_ab", vec!["a", "b"], adder_kernel.clone()),
-        invoc_spec(
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_2",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_3",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel.clone()),
-        invoc_spec("clear_grid", vec!["grid"], clear_grid_kernel.clone()),
+        invoc_spec("adder_ab", vec!["a", "b"], adder_kernel.clone())
+            .with_access("b", BufferAccess::Read),
+        invoc_spec("clear_grid", vec!["grid"], clear_grid_kernel.clone())
+            .with_access("grid", BufferAccess::Write),
         // invoc_spec(
         //     "fill_grid_random",
         //     vec!["grid"],
@@ -348,13 +378,42 @@ fn main() -> Result<()> {
                 "particle_material",
             ],
             p2g_kernel.clone(),
-        ),
+        )
+        .with_access("x", BufferAccess::Read)
+        .with_access("v", BufferAccess::Read)
+        .with_access("grid", BufferAccess::Atomic)
+        .with_access("particle_material", BufferAccess::Read),
+        invoc_spec(
+            "grid_update",
+            vec!["grid", "grid_update_params"],
+            grid_update_kernel.clone(),
+        )
+        .with_access("grid_update_params", BufferAccess::Read),
+        invoc_spec(
+            "g2p",
+            vec!["x", "v", "grid", "particle_matrices"],
+            g2p_kernel.clone(),
+        )
+        .with_access("grid", BufferAccess::Read),
     ];
 
     // Create compute runner
     println!("Initializing Vulkan compute...");
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)?;
+    // Rendering reads copies of the particle positions and the grid, so the next
+    // steps can run meanwhile
+    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)?
+        .with_frames_in_flight(2, &["x", "grid"])?
+        .with_substeps(SUBSTEPS_PER_FRAME)?
+        .with_profiling(60)?;
     println!("Compute runner initialized!");
+    let dependency_graph = compute_chain.dependency_graph();
+    print!("Invocation dependencies: {dependency_graph}");
+    for warning in dependency_graph.warnings() {
+        println!("Warning: {warning}");
+    }
+    if compute_chain.profile()?.is_none() {
+        println!("Warning: the queue can't write timestamps, the chain runs unprofiled");
+    }
 
     // Create application state
     let mut app = App::new(compute_chain, 0);
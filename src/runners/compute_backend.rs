@@ -0,0 +1,80 @@
+use std::ops::Range;
+
+use bytemuck::Pod;
+
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano::shader_pipeline_builder::ShaderPipelineSpec,
+};
+
+/// A backend that can run a chain of `invoc_spec`s over the buffers described by `buf_spec`s.
+///
+/// Implemented by [`VulkanoComputeChain`](crate::runners::vulkano_compute_chain::VulkanoComputeChain)
+/// for the GPU and by [`CpuComputeChain`](crate::runners::cpu_compute_chain::CpuComputeChain),
+/// which runs host implementations of the kernels and does not need a Vulkan driver.
+pub trait ComputeBackend: Sized {
+    /// The `buf_spec` tuple the backend creates its buffers from
+    type BufferSpecs;
+
+    /// Create the backend's buffers from `buffer_specs` and prepare the invocation chain.
+    fn create(
+        buffer_specs: &Self::BufferSpecs,
+        pipeline_specs: Vec<ShaderPipelineSpec>,
+    ) -> CrateResult<Self>;
+
+    /// Run every invocation in the chain once per substep, in order, and wait for it to
+    /// finish.
+    fn run_chain(&self) -> CrateResult<()>;
+
+    /// Run the whole chain `substeps` times in a row on every [`Self::run_chain`]
+    /// (1 by default).
+    fn set_substeps(&mut self, substeps: u32) -> CrateResult<()>;
+
+    /// Copy the current contents of the buffer `name` back to the host.
+    fn read_buffer<T: Pod + Send + Sync>(&self, name: &str) -> CrateResult<Vec<T>>;
+
+    /// Overwrite the buffer `name` with `data`, which must have the buffer's length and
+    /// element type.
+    fn write_buffer<T: Pod + Send + Sync>(&self, name: &str, data: &[T]) -> CrateResult<()>;
+
+    /// Overwrite the elements of the buffer `name` from `offset` on with `data`, which
+    /// must fit in the buffer and have its element type.
+    fn write_range<T: Pod + Send + Sync>(
+        &self,
+        name: &str,
+        offset: usize,
+        data: &[T],
+    ) -> CrateResult<()>;
+
+    /// Grow or shrink the buffer `name` to `new_len` elements, keeping its contents up to
+    /// the new length and filling any new elements with `value`. The invocations that
+    /// dispatch over the buffer get new workgroup counts, and the ones with push constants
+    /// over it new push constants.
+    ///
+    /// Fails, leaving the chain as it was, if an invocation that uses the buffer has
+    /// fixed push constants or a grid dispatch size, which can't follow the new length.
+    fn resize_buffer<T: Pod + Send + Sync>(
+        &mut self,
+        name: &str,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<()>;
+}
+
+/// The elements of buffer `name` that writing `data_len` elements at `offset` overwrites
+pub(crate) fn write_range_bounds(
+    name: &str,
+    buffer_len: usize,
+    offset: usize,
+    data_len: usize,
+) -> CrateResult<Range<usize>> {
+    match offset.checked_add(data_len) {
+        Some(end) if end <= buffer_len => Ok(offset..end),
+        _ => Err(ChimeraError::BufferRangeOutOfBounds {
+            name: name.to_string(),
+            offset,
+            data_len,
+            buffer_len,
+        }),
+    }
+}
@@ -0,0 +1,250 @@
+//! CPU executor for `invoc_spec` chains
+//!
+//! Runs host implementations of the shader entry points over copies of the
+//! `buf_spec` data, so chains can be run and tested on machines without a GPU.
+
+pub mod host_buffers;
+pub mod kernels;
+
+use std::marker::PhantomData;
+
+use bytemuck::Pod;
+use shared::DispatchIndirectCommand;
+
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::{
+        compute_backend::{write_range_bounds, ComputeBackend},
+        cpu_compute_chain::{
+            host_buffers::{HostBuffers, IntoHostBuffers},
+            kernels::{host_kernel, HostKernelArgs, HostKernelFn},
+        },
+        vulkano::{
+            reflection::shaders_reflection,
+            shader_pipeline_builder::{PushConstantSource, ShaderPipelineSpec},
+        },
+    },
+};
+
+/// An invocation's dispatch and push constants, resolved against the buffer lengths
+struct Dispatch {
+    local_size: [u32; 3],
+    /// `None` for indirect dispatches, which read it from their buffer on every execution
+    num_workgroups: Option<[u32; 3]>,
+    push_constants: Option<PushConstantSource>,
+}
+
+pub struct CpuComputeChain<BS: IntoHostBuffers> {
+    buffers: HostBuffers,
+    pipeline_specs: Vec<ShaderPipelineSpec>,
+    kernels: Vec<HostKernelFn>,
+    dispatches: Vec<Dispatch>,
+    /// How many times [`Self::execute`] runs the chain
+    substeps: u32,
+    _buffer_specs: PhantomData<fn(&BS)>,
+}
+
+impl<BS: IntoHostBuffers> CpuComputeChain<BS> {
+    /// Create a new CPU compute chain
+    pub fn new(buffer_specs: &BS, pipeline_specs: Vec<ShaderPipelineSpec>) -> CrateResult<Self> {
+        let mut buffers = HostBuffers::default();
+        buffer_specs.with_host_buffer(&mut buffers)?;
+
+        validate(&pipeline_specs, &buffers)?;
+
+        let kernels = pipeline_specs
+            .iter()
+            .map(|spec| {
+                let entry_point_name = spec.kernel_config().entry_point_name();
+                host_kernel(entry_point_name)
+                    .ok_or_else(|| ChimeraError::KernelNotFound(entry_point_name.to_string()))
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        let dispatches = resolve_dispatches(&pipeline_specs, |buf_name| buffers.len(buf_name))?;
+
+        Ok(Self {
+            buffers,
+            pipeline_specs,
+            kernels,
+            dispatches,
+            substeps: 1,
+            _buffer_specs: PhantomData,
+        })
+    }
+
+    /// Run the chain `substeps` times in a row on every execution
+    pub fn with_substeps(mut self, substeps: u32) -> CrateResult<Self> {
+        self.set_substeps(substeps)?;
+        Ok(self)
+    }
+
+    pub fn set_substeps(&mut self, substeps: u32) -> CrateResult<()> {
+        if substeps == 0 {
+            return Err(ChimeraError::ZeroSubsteps);
+        }
+        self.substeps = substeps;
+        Ok(())
+    }
+
+    pub fn substeps(&self) -> u32 {
+        self.substeps
+    }
+
+    pub fn execute(&self) -> CrateResult<()> {
+        for _ in 0..self.substeps {
+            self.execute_once()?;
+        }
+        Ok(())
+    }
+
+    fn execute_once(&self) -> CrateResult<()> {
+        for ((spec, kernel), dispatch) in self
+            .pipeline_specs
+            .iter()
+            .zip(self.kernels.iter())
+            .zip(self.dispatches.iter())
+        {
+            let num_workgroups = match (dispatch.num_workgroups, spec.indirect_buf_name()) {
+                (Some(num_workgroups), _) => num_workgroups,
+                // checked to hold a command when the chain was built
+                (None, Some(buf_name)) => {
+                    self.buffers.read::<DispatchIndirectCommand>(buf_name)?[0].num_workgroups()
+                }
+                (None, None) => unreachable!("only indirect dispatches have no workgroup count"),
+            };
+            kernel(&HostKernelArgs {
+                buffers: &self.buffers,
+                spec,
+                local_size: dispatch.local_size,
+                num_workgroups,
+                push_constants: dispatch.push_constants.as_ref(),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn buffers(&self) -> &HostBuffers {
+        &self.buffers
+    }
+}
+
+/// Check every invocation against the names, element sizes and binding numbers of
+/// `buffers`
+fn validate(pipeline_specs: &[ShaderPipelineSpec], buffers: &HostBuffers) -> CrateResult<()> {
+    pipeline_specs.iter().try_for_each(|spec| {
+        spec.validate_with(|buf_name| {
+            if buffers.contains(buf_name) {
+                Ok(())
+            } else {
+                Err(ChimeraError::DescriptorSetNameNotFound(
+                    buf_name.to_string(),
+                ))
+            }
+        })?;
+        // the host kernels take their buffers by the shader's binding numbers too
+        spec.validate_against_reflection(shaders_reflection()?, |buf_name| {
+            buffers.element_size(buf_name)
+        })
+    })
+}
+
+/// The dispatch of each invocation, with `buffer_len` returning the number of elements
+/// of a buffer
+fn resolve_dispatches(
+    pipeline_specs: &[ShaderPipelineSpec],
+    buffer_len: impl Fn(&str) -> CrateResult<usize>,
+) -> CrateResult<Vec<Dispatch>> {
+    // the emulated dispatches use the shader's workgroup size too
+    let reflection = shaders_reflection()?;
+    pipeline_specs
+        .iter()
+        .map(|spec| {
+            let local_size = reflection
+                .entry_point(spec.kernel_config().entry_point_name())?
+                .local_size()?;
+            let num_workgroups = spec.num_workgroups(reflection, &buffer_len)?;
+            let push_constants = spec
+                .kernel_config()
+                .push_constants()
+                .map(|push_constants| push_constants.resolved(&buffer_len))
+                .transpose()?;
+            Ok(Dispatch {
+                local_size,
+                num_workgroups,
+                push_constants,
+            })
+        })
+        .collect()
+}
+
+impl<BS: IntoHostBuffers> ComputeBackend for CpuComputeChain<BS> {
+    type BufferSpecs = BS;
+
+    fn create(buffer_specs: &BS, pipeline_specs: Vec<ShaderPipelineSpec>) -> CrateResult<Self> {
+        Self::new(buffer_specs, pipeline_specs)
+    }
+
+    fn run_chain(&self) -> CrateResult<()> {
+        self.execute()
+    }
+
+    fn set_substeps(&mut self, substeps: u32) -> CrateResult<()> {
+        CpuComputeChain::set_substeps(self, substeps)
+    }
+
+    fn read_buffer<T: Pod + Send + Sync>(&self, name: &str) -> CrateResult<Vec<T>> {
+        Ok(self.buffers.read::<T>(name)?.clone())
+    }
+
+    fn write_buffer<T: Pod + Send + Sync>(&self, name: &str, data: &[T]) -> CrateResult<()> {
+        let mut buffer = self.buffers.write::<T>(name)?;
+        if buffer.len() != data.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: buffer.len(),
+                data_len: data.len(),
+            });
+        }
+        buffer.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn write_range<T: Pod + Send + Sync>(
+        &self,
+        name: &str,
+        offset: usize,
+        data: &[T],
+    ) -> CrateResult<()> {
+        let mut buffer = self.buffers.write::<T>(name)?;
+        let range = write_range_bounds(name, buffer.len(), offset, data.len())?;
+        buffer[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn resize_buffer<T: Pod + Send + Sync>(
+        &mut self,
+        name: &str,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<()> {
+        if new_len == 0 {
+            return Err(ChimeraError::ZeroLengthBuffer(name.to_string()));
+        }
+        self.pipeline_specs
+            .iter()
+            .try_for_each(|spec| spec.validate_resize(name))?;
+        validate(&self.pipeline_specs, &self.buffers)?;
+        // resolved against the new length before anything changes, so that a failure
+        // leaves the chain as it was
+        let dispatches = resolve_dispatches(&self.pipeline_specs, |buf_name| {
+            if buf_name == name {
+                Ok(new_len)
+            } else {
+                self.buffers.len(buf_name)
+            }
+        })?;
+        self.buffers.resize(name, new_len, value)?;
+        self.dispatches = dispatches;
+        Ok(())
+    }
+}
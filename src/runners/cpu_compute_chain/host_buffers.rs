@@ -0,0 +1,146 @@
+use std::{any::Any, collections::HashMap};
+
+use parking_lot::{
+    MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+use variadics_please::all_tuples_enumerated;
+
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano::buffer_specs::DataAndBindingSpec,
+};
+
+/// Host-side storage for the buffers of a [`CpuComputeChain`](super::CpuComputeChain).
+///
+/// Each buffer is a type-erased `Vec<T>` behind its own lock, so a kernel can
+/// borrow several buffers mutably at the same time.
+#[derive(Default)]
+pub struct HostBuffers {
+    buffers: HashMap<&'static str, HostBuffer>,
+}
+
+struct HostBuffer {
+    element_size: usize,
+    len: usize,
+    type_name: &'static str,
+    data: RwLock<Box<dyn Any + Send + Sync>>,
+}
+
+impl HostBuffers {
+    /// Add `data` as the buffer named `name`, or [`ChimeraError::DuplicateBufferName`]
+    /// if there already is a buffer with that name
+    pub fn insert<T: Send + Sync + 'static>(
+        &mut self,
+        name: &'static str,
+        data: Vec<T>,
+    ) -> CrateResult<()> {
+        if self.contains(name) {
+            return Err(ChimeraError::DuplicateBufferName(name.to_string()));
+        }
+        self.buffers.insert(
+            name,
+            HostBuffer {
+                element_size: std::mem::size_of::<T>(),
+                len: data.len(),
+                type_name: std::any::type_name::<T>(),
+                data: RwLock::new(Box::new(data)),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.buffers.contains_key(name)
+    }
+
+    /// Size in bytes of one element of the buffer named `name`
+    pub fn element_size(&self, name: &str) -> CrateResult<usize> {
+        Ok(self.buffer(name)?.element_size)
+    }
+
+    /// Number of elements of the buffer named `name`
+    pub fn len(&self, name: &str) -> CrateResult<usize> {
+        Ok(self.buffer(name)?.len)
+    }
+
+    /// Grow or shrink the buffer named `name` to `new_len` elements, filling any new
+    /// ones with `value`
+    pub fn resize<T: Clone + 'static>(
+        &mut self,
+        name: &str,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<()> {
+        self.write::<T>(name)?.resize(new_len, value);
+        if let Some(buffer) = self.buffers.get_mut(name) {
+            buffer.len = new_len;
+        }
+        Ok(())
+    }
+
+    fn buffer(&self, name: &str) -> CrateResult<&HostBuffer> {
+        self.buffers
+            .get(name)
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(name.to_string()))
+    }
+
+    fn lock(&self, name: &str) -> CrateResult<&RwLock<Box<dyn Any + Send + Sync>>> {
+        Ok(&self.buffer(name)?.data)
+    }
+
+    fn type_mismatch<T>(&self, name: &str) -> ChimeraError {
+        ChimeraError::BufferTypeMismatch {
+            name: name.to_string(),
+            stored: self.buffers[name].type_name.to_string(),
+            requested: std::any::type_name::<T>().to_string(),
+        }
+    }
+
+    pub fn read<T: 'static>(&self, name: &str) -> CrateResult<MappedRwLockReadGuard<'_, Vec<T>>> {
+        let guard = self
+            .lock(name)?
+            .try_read()
+            .ok_or_else(|| ChimeraError::HostBufferAliased(name.to_string()))?;
+        RwLockReadGuard::try_map(guard, |data| data.downcast_ref::<Vec<T>>())
+            .map_err(|_| self.type_mismatch::<T>(name))
+    }
+
+    pub fn write<T: 'static>(&self, name: &str) -> CrateResult<MappedRwLockWriteGuard<'_, Vec<T>>> {
+        let guard = self
+            .lock(name)?
+            .try_write()
+            .ok_or_else(|| ChimeraError::HostBufferAliased(name.to_string()))?;
+        RwLockWriteGuard::try_map(guard, |data| data.downcast_mut::<Vec<T>>())
+            .map_err(|_| self.type_mismatch::<T>(name))
+    }
+}
+
+/// Copy the data of `buf_spec`s into [`HostBuffers`], the CPU counterpart of
+/// [`IntoDescriptorSetByName`](crate::runners::vulkano::buffer_specs::IntoDescriptorSetByName).
+pub trait IntoHostBuffers {
+    fn with_host_buffer(&self, buffers: &mut HostBuffers) -> CrateResult<()>;
+}
+
+impl<'a, T> IntoHostBuffers for DataAndBindingSpec<'a, T>
+where
+    T: Copy + Send + Sync + 'static,
+{
+    fn with_host_buffer(&self, buffers: &mut HostBuffers) -> CrateResult<()> {
+        buffers.insert(self.name, self.data.to_vec())
+    }
+}
+
+macro_rules! impl_into_host_buffers_for_tuple {
+    ($(($n:tt, $T:ident)),*) => {
+        impl<$($T: IntoHostBuffers),*> IntoHostBuffers for ($($T,)*) {
+            fn with_host_buffer(&self, buffers: &mut HostBuffers) -> CrateResult<()> {
+                $(
+                    self.$n.with_host_buffer(buffers)?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+all_tuples_enumerated!(impl_into_host_buffers_for_tuple, 1, 15, T);
@@ -0,0 +1,291 @@
+//! Host kernels for the shader entry points, keyed by entry point name.
+//!
+//! Each kernel binds the invocation's buffers and runs the per-invocation body of the
+//! entry point through the [`dispatch`] emulator. The bodies are built from the same
+//! functions of the shaders and shared crates that the entry points call, but access
+//! the buffers one element at a time through [`SharedSlice`], since parallel
+//! invocations can't each hold a `&mut` to the whole buffer.
+
+use std::ptr::addr_of_mut;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard};
+use shaders::{g2p::G2pUpdate, p2g::P2gTransfer};
+use shared::{
+    grid::{linear_grid_index, update_grid_cell, GridCell, GridUpdateParams},
+    particles::{MaterialPod, ParticleDeformation, ParticleMatrices},
+    DispatchIndirectCommand, ScalarPushConstants, N_GRID_X,
+};
+
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::{
+        cpu_compute_chain::host_buffers::HostBuffers,
+        cpu_dispatch::{atomic_f_add, dispatch, SharedSlice},
+        vulkano::shader_pipeline_builder::{PushConstantSource, ShaderPipelineSpec},
+    },
+};
+
+/// A host implementation of a compute shader entry point.
+pub type HostKernelFn = fn(&HostKernelArgs) -> CrateResult<()>;
+
+/// Look up the host implementation of the shader entry point `entry_point_name`.
+pub fn host_kernel(entry_point_name: &str) -> Option<HostKernelFn> {
+    let f: HostKernelFn = match entry_point_name {
+        "adder" => adder,
+        "add_scalar" => add_scalar,
+        "step_particles" => step_particles,
+        "wrap_particles" => wrap_particles,
+        "indirect_args_1d" => indirect_args_1d,
+        "fill_grid_random" => fill_grid_random,
+        "clear_grid" => clear_grid,
+        "p2g::p2g" => p2g,
+        "grid_update::grid_update" => grid_update,
+        "g2p::g2p" => g2p,
+        _ => return None,
+    };
+    Some(f)
+}
+
+/// The buffers of one invocation, addressed by the binding numbers used in the shader.
+pub struct HostKernelArgs<'a> {
+    pub(crate) buffers: &'a HostBuffers,
+    pub(crate) spec: &'a ShaderPipelineSpec,
+    pub(crate) local_size: [u32; 3],
+    pub(crate) num_workgroups: [u32; 3],
+    /// The invocation's push constants, resolved against the buffer lengths
+    pub(crate) push_constants: Option<&'a PushConstantSource>,
+}
+
+impl<'a> HostKernelArgs<'a> {
+    fn buf_name(&self, binding: u32) -> CrateResult<&'static str> {
+        self.spec.buf_name_for_binding(binding).ok_or_else(|| {
+            ChimeraError::BindingNotInInvocation {
+                invocation_name: self.spec.invocation_name().to_string(),
+                binding,
+            }
+        })
+    }
+
+    pub fn read<T: 'static>(&self, binding: u32) -> CrateResult<MappedRwLockReadGuard<'a, Vec<T>>> {
+        self.buffers.read::<T>(self.buf_name(binding)?)
+    }
+
+    pub fn write<T: 'static>(
+        &self,
+        binding: u32,
+    ) -> CrateResult<MappedRwLockWriteGuard<'a, Vec<T>>> {
+        self.buffers.write::<T>(self.buf_name(binding)?)
+    }
+
+    /// The workgroup size of the entry point, reflected from the shader
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    pub fn num_workgroups(&self) -> [u32; 3] {
+        self.num_workgroups
+    }
+
+    /// The invocation's push constants, evaluated for this execution
+    ///
+    /// Fails if the invocation has no push constants, or if they are smaller than the
+    /// `T` the entry point reads.
+    pub fn push_constants<T: Pod>(&self) -> CrateResult<T> {
+        let words = self
+            .push_constants
+            .ok_or_else(|| {
+                ChimeraError::MissingPushConstants(self.spec.invocation_name().to_string())
+            })?
+            .words();
+        let bytes = bytemuck::cast_slice::<u32, u8>(&words);
+        let bytes = bytes.get(..std::mem::size_of::<T>()).ok_or_else(|| {
+            ChimeraError::PushConstantsMismatch {
+                invocation_name: self.spec.invocation_name().to_string(),
+                entry_point: self.spec.kernel_config().entry_point_name().to_string(),
+                shader_has_push_constants: true,
+            }
+        })?;
+        Ok(bytemuck::pod_read_unaligned(bytes))
+    }
+}
+
+// SAFETY (all kernels): like the entry points they mirror, invocation `i` of a 1d
+// kernel and invocation `(x, y)` of a 2d kernel only access element `i` or cell
+// `(x, y)` of the buffers they write, except for p2g, which scatters to the grid
+// through `atomic_f_add`.
+
+fn adder(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut a = args.write::<u32>(0)?;
+    let b = args.read::<u32>(1)?;
+    let (a, b) = (SharedSlice::new(&mut a), &b[..]);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let i = id.x as usize;
+        unsafe { a.update(i, |a| shared::add_update(a, b[i])) };
+    });
+    Ok(())
+}
+
+fn add_scalar(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut a = args.write::<u32>(0)?;
+    let push_constants = args.push_constants::<ScalarPushConstants>()?;
+    let a = SharedSlice::new(&mut a);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let i = id.x as usize;
+        if i >= push_constants.num_elements as usize {
+            return;
+        }
+        unsafe { a.update(i, |a| shared::add_update(a, push_constants.scalar)) };
+    });
+    Ok(())
+}
+
+fn step_particles(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut x = args.write::<Vec2>(2)?;
+    let v = args.read::<Vec2>(3)?;
+    let (x, v) = (SharedSlice::new(&mut x), &v[..]);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let i = id.x as usize;
+        unsafe { x.update(i, |x| shared::add_update(x, v[i])) };
+    });
+    Ok(())
+}
+
+fn wrap_particles(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut x = args.write::<Vec2>(2)?;
+    let x = SharedSlice::new(&mut x);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let i = id.x as usize;
+        unsafe { x.update(i, |x| *x = shaders::wrap_position(*x)) };
+    });
+    Ok(())
+}
+
+fn indirect_args_1d(args: &HostKernelArgs) -> CrateResult<()> {
+    let count = args.read::<u32>(9)?;
+    let mut indirect_args = args.write::<DispatchIndirectCommand>(10)?;
+    let (count, indirect_args) = (&count[..], SharedSlice::new(&mut indirect_args));
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        if id.x == 0 {
+            unsafe { indirect_args.write(0, DispatchIndirectCommand::over_1d(count[0])) };
+        }
+    });
+    Ok(())
+}
+
+fn fill_grid_random(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut grid = args.write::<GridCell>(4)?;
+    let grid = SharedSlice::new(&mut grid);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let index = linear_grid_index(id.x, id.y);
+        unsafe { grid.write(index, shaders::random_grid_cell(id.x, id.y)) };
+    });
+    Ok(())
+}
+
+fn clear_grid(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut grid = args.write::<GridCell>(4)?;
+    let grid = SharedSlice::new(&mut grid);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let index = linear_grid_index(id.x, id.y);
+        unsafe { grid.write(index, GridCell::zeroed()) };
+    });
+    Ok(())
+}
+
+fn p2g(args: &HostKernelArgs) -> CrateResult<()> {
+    let x = args.read::<Vec2>(2)?;
+    let v = args.read::<Vec2>(3)?;
+    let mut grid = args.write::<GridCell>(4)?;
+    let mut particle_matrices = args.write::<ParticleMatrices>(5)?;
+    let mut particle_deformation = args.write::<ParticleDeformation>(6)?;
+    let particle_material = args.read::<MaterialPod>(7)?;
+
+    let (x, v, particle_material) = (&x[..], &v[..], &particle_material[..]);
+    let grid = SharedSlice::new(&mut grid);
+    let particle_matrices = SharedSlice::new(&mut particle_matrices);
+    let particle_deformation = SharedSlice::new(&mut particle_deformation);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let p = id.x as usize;
+        if p >= x.len() {
+            return;
+        }
+        let (mut matrices, mut deformation) =
+            unsafe { (particle_matrices.read(p), particle_deformation.read(p)) };
+        let transfer = P2gTransfer::new(
+            x[p],
+            v[p],
+            matrices.C,
+            matrices.F,
+            deformation.J,
+            particle_material[p].to_material(),
+        );
+
+        // save updated particle data
+        matrices.F = transfer.F;
+        deformation.J = transfer.Jp;
+        unsafe {
+            particle_matrices.write(p, matrices);
+            particle_deformation.write(p, deformation);
+        }
+
+        // do the actual P2G transfer
+        for o in 0..9 {
+            let node = transfer.node(o);
+            if node.index == usize::MAX {
+                continue;
+            }
+            let cell = grid.element_ptr(node.index);
+            unsafe {
+                atomic_f_add(addr_of_mut!((*cell).mass), node.mass);
+                atomic_f_add(addr_of_mut!((*cell).v.x), node.momentum.x);
+                atomic_f_add(addr_of_mut!((*cell).v.y), node.momentum.y);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn grid_update(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut grid = args.write::<GridCell>(4)?;
+    let params = args.read::<GridUpdateParams>(8)?;
+    let (grid, params) = (SharedSlice::new(&mut grid), &params[..]);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        if id.x >= N_GRID_X || id.y >= N_GRID_X {
+            return;
+        }
+        let index = linear_grid_index(id.x, id.y);
+        unsafe {
+            grid.update(index, |cell| {
+                *cell = update_grid_cell(*cell, id.truncate(), &params[0])
+            })
+        };
+    });
+    Ok(())
+}
+
+fn g2p(args: &HostKernelArgs) -> CrateResult<()> {
+    let mut x = args.write::<Vec2>(2)?;
+    let mut v = args.write::<Vec2>(3)?;
+    let grid = args.read::<GridCell>(4)?;
+    let mut particle_matrices = args.write::<ParticleMatrices>(5)?;
+
+    let x = SharedSlice::new(&mut x);
+    let v = SharedSlice::new(&mut v);
+    let grid = &grid[..];
+    let particle_matrices = SharedSlice::new(&mut particle_matrices);
+    dispatch(args.num_workgroups(), args.local_size(), |id| {
+        let p = id.x as usize;
+        if p >= x.len() {
+            return;
+        }
+        unsafe {
+            let update = G2pUpdate::new(x.read(p), grid);
+            v.write(p, update.v);
+            particle_matrices.update(p, |matrices| matrices.C = update.C);
+            x.write(p, update.x);
+        }
+    });
+    Ok(())
+}
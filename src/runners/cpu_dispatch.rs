@@ -0,0 +1,159 @@
+//! Host-side emulation of compute shader dispatches
+//!
+//! The shaders crate also builds for the host target, where its entry points are
+//! plain Rust functions taking a `global_invocation_id` and slices. [`dispatch`]
+//! calls a function once per invocation of a workgroup grid, in parallel with rayon;
+//! the invocations share the buffers they write through [`SharedSlice`], one element
+//! at a time. [`dispatch_serial`] runs the invocations one after the other instead,
+//! so an entry point can be called as is, with `&mut` slices, and unit-tested on the
+//! CPU.
+
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use glam::UVec3;
+use rayon::prelude::*;
+use shared::{GRID_WORKGROUP_SIZE, WORKGROUP_SIZE};
+
+/// Local size of the 1d particle kernels, `#[spirv(compute(threads(64)))]`
+pub const LOCAL_SIZE_1D: [u32; 3] = [WORKGROUP_SIZE, 1, 1];
+
+/// Local size of the 2d grid kernels, `#[spirv(compute(threads(8, 8)))]`
+pub const LOCAL_SIZE_2D: [u32; 3] = [GRID_WORKGROUP_SIZE.0, GRID_WORKGROUP_SIZE.1, 1];
+
+/// Call `f` once for every `global_invocation_id` of a dispatch of `num_workgroups`
+/// workgroups with `local_size` invocations each.
+///
+/// Workgroups run in parallel; the invocations of one workgroup run in order on a
+/// single thread. A panic in `f`, e.g. from out of bounds indexing in the shader,
+/// propagates to the caller.
+///
+/// Invocations share the buffers they write through [`SharedSlice`]s.
+pub fn dispatch(num_workgroups: [u32; 3], local_size: [u32; 3], f: impl Fn(UVec3) + Sync) {
+    let [wg_x, wg_y, wg_z] = num_workgroups;
+    let n_workgroups = wg_x as usize * wg_y as usize * wg_z as usize;
+
+    (0..n_workgroups)
+        .into_par_iter()
+        .for_each(|w| workgroup_invocations(num_workgroups, local_size, w).for_each(&f));
+}
+
+/// Like [`dispatch`], but every invocation runs in order on the calling thread.
+///
+/// `f` gets exclusive access to whatever it captures, so it can call an entry point
+/// of the shaders crate itself, passing the same `&mut` slices to every invocation.
+pub fn dispatch_serial(num_workgroups: [u32; 3], local_size: [u32; 3], f: impl FnMut(UVec3)) {
+    let [wg_x, wg_y, wg_z] = num_workgroups;
+    let n_workgroups = wg_x as usize * wg_y as usize * wg_z as usize;
+
+    (0..n_workgroups)
+        .flat_map(|w| workgroup_invocations(num_workgroups, local_size, w))
+        .for_each(f);
+}
+
+/// The `global_invocation_id`s of workgroup `w` (in linear order) of a dispatch
+fn workgroup_invocations(
+    num_workgroups: [u32; 3],
+    local_size: [u32; 3],
+    w: usize,
+) -> impl Iterator<Item = UVec3> {
+    let [wg_x, wg_y, _] = num_workgroups;
+    let local_size = UVec3::from(local_size);
+    let w = w as u32;
+    let workgroup_id = UVec3::new(w % wg_x, (w / wg_x) % wg_y, w / (wg_x * wg_y));
+    let base = workgroup_id * local_size;
+    (0..local_size.z).flat_map(move |z| {
+        (0..local_size.y)
+            .flat_map(move |y| (0..local_size.x).map(move |x| base + UVec3::new(x, y, z)))
+    })
+}
+
+/// A mutable slice that every invocation of a [`dispatch`] can access at once, like
+/// a storage buffer bound to a shader.
+///
+/// Invocations read and write single elements; none of them ever gets a reference
+/// to the whole slice, which another invocation would alias.
+pub struct SharedSlice<'a, T> {
+    ptr: *mut T,
+    len: usize,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+unsafe impl<T: Send> Send for SharedSlice<'_, T> {}
+unsafe impl<T: Send + Sync> Sync for SharedSlice<'_, T> {}
+
+impl<'a, T: Copy> SharedSlice<'a, T> {
+    pub fn new(data: &'a mut [T]) -> Self {
+        Self {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pointer to element `i`, e.g. for atomic access to one of its fields.
+    ///
+    /// Panics if `i` is out of bounds, like indexing the buffer in the shader.
+    pub fn element_ptr(&self, i: usize) -> *mut T {
+        assert!(
+            i < self.len,
+            "index out of bounds: the len is {} but the index is {i}",
+            self.len
+        );
+        // SAFETY: in bounds of the slice `ptr` was taken from
+        unsafe { self.ptr.add(i) }
+    }
+
+    /// Read element `i`.
+    ///
+    /// # Safety
+    /// No other invocation of the dispatch may write element `i`, unless all of
+    /// those accesses are atomic.
+    pub unsafe fn read(&self, i: usize) -> T {
+        self.element_ptr(i).read()
+    }
+
+    /// Overwrite element `i` with `value`.
+    ///
+    /// # Safety
+    /// No other invocation of the dispatch may access element `i`.
+    pub unsafe fn write(&self, i: usize, value: T) {
+        self.element_ptr(i).write(value)
+    }
+
+    /// Update element `i` in place with `f`, as the shader does through `&mut x[i]`.
+    ///
+    /// # Safety
+    /// Same as [`Self::write`].
+    pub unsafe fn update(&self, i: usize, f: impl FnOnce(&mut T)) {
+        let mut value = self.read(i);
+        f(&mut value);
+        self.write(i, value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Atomically add `val` to the `f32` at `dst`, returning the previous value, like
+/// `atomic_f_add` does in the shaders.
+///
+/// # Safety
+/// `dst` must be valid and aligned, and invocations running concurrently must only
+/// access `*dst` atomically.
+pub unsafe fn atomic_f_add(dst: *mut f32, val: f32) -> f32 {
+    let atomic = AtomicU32::from_ptr(dst.cast::<u32>());
+    let prev = atomic
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f32::from_bits(bits) + val).to_bits())
+        })
+        .unwrap_or_else(|bits| bits);
+    f32::from_bits(prev)
+}
@@ -1,9 +1,19 @@
 //! Runner implementations for different compute backends
+pub mod compute_backend;
+pub mod cpu_compute_chain;
+pub mod cpu_dispatch;
 pub mod vulkano;
 pub mod vulkano_compute_chain;
 
 pub mod vulkano_bindless;
+pub mod vulkano_descriptor_array;
+pub mod vulkano_device_address;
+pub mod vulkano_separate_buffers;
 // Re-export runners at module level for convenience
+pub use self::compute_backend::ComputeBackend;
+pub use self::cpu_compute_chain::CpuComputeChain;
 pub use self::vulkano_bindless::VulkanoBindlessRunner;
+pub use self::vulkano_descriptor_array::VulkanoDescriptorArrayRunner;
+pub use self::vulkano_device_address::VulkanoDeviceAddressRunner;
 
 // mod vulkano_tutorial;
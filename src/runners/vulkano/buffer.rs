@@ -1,133 +1,119 @@
-use crate::error::CrateResult;
-use std::{collections::HashMap, ops::Index, sync::Arc};
+use crate::error::{ChimeraError, CrateResult};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::Index,
+    sync::Arc,
+};
 
-use glam::Vec2;
-use shared::grid::GridCell;
+use bytemuck::Pod;
 use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
     descriptor_set::WriteDescriptorSet,
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
 };
 
-#[allow(non_camel_case_types)]
-pub enum BufferKindsEnum {
-    u32(Subbuffer<[u32]>),
-    f32(Subbuffer<[f32]>),
-    Vec2(Subbuffer<[Vec2]>),
-    GridCell(Subbuffer<[GridCell]>),
+/// A storage buffer of any `BufferContents + Pod` element type, with the type erased.
+///
+/// Runners can keep buffers of different element types in one collection and
+/// recover the typed `Subbuffer<[T]>` with [`AnyBuffer::downcast`], which fails
+/// instead of panicking if `T` is not the stored element type.
+#[derive(Clone)]
+pub struct AnyBuffer {
+    type_id: TypeId,
+    type_name: &'static str,
+    element_size: usize,
+    /// The buffer as `Subbuffer<[T]>`, for typed access
+    typed: Arc<dyn Any + Send + Sync>,
+    /// The same buffer as bytes, for descriptor writes
+    bytes: Subbuffer<[u8]>,
 }
 
-impl From<Subbuffer<[u32]>> for BufferKindsEnum {
-    fn from(buf: Subbuffer<[u32]>) -> Self {
-        BufferKindsEnum::u32(buf)
+impl<T: BufferContents + Pod> From<Subbuffer<[T]>> for AnyBuffer {
+    fn from(buf: Subbuffer<[T]>) -> Self {
+        AnyBuffer {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            element_size: std::mem::size_of::<T>(),
+            bytes: buf.clone().into_bytes(),
+            typed: Arc::new(buf),
+        }
     }
 }
-impl From<Subbuffer<[f32]>> for BufferKindsEnum {
-    fn from(buf: Subbuffer<[f32]>) -> Self {
-        BufferKindsEnum::f32(buf)
+
+impl AnyBuffer {
+    /// Upload `data` into a new host-visible storage buffer.
+    pub fn from_data<T: BufferContents + Pod>(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: &[T],
+    ) -> CrateResult<Self> {
+        Ok(build_and_fill_buffer(memory_allocator, data)?.into())
     }
-}
-impl From<Subbuffer<[Vec2]>> for BufferKindsEnum {
-    fn from(buf: Subbuffer<[Vec2]>) -> Self {
-        BufferKindsEnum::Vec2(buf)
+
+    /// Whether the buffer holds `T` elements
+    pub fn is<T: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
     }
-}
-impl From<Subbuffer<[GridCell]>> for BufferKindsEnum {
-    fn from(buf: Subbuffer<[GridCell]>) -> Self {
-        BufferKindsEnum::GridCell(buf)
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
     }
-}
 
-impl TryInto<Subbuffer<[u32]>> for BufferKindsEnum {
-    type Error = String;
+    pub fn element_size(&self) -> usize {
+        self.element_size
+    }
 
-    fn try_into(self) -> std::result::Result<Subbuffer<[u32]>, Self::Error> {
-        match self {
-            BufferKindsEnum::u32(buf) => Ok(buf),
-            _ => Err("BufferAny is not of type u32".to_string()),
-        }
+    /// Number of elements in the buffer
+    pub fn len(&self) -> usize {
+        self.bytes.len() as usize / self.element_size.max(1)
     }
-}
-impl TryInto<Subbuffer<[f32]>> for BufferKindsEnum {
-    type Error = String;
 
-    fn try_into(self) -> std::result::Result<Subbuffer<[f32]>, Self::Error> {
-        match self {
-            BufferKindsEnum::f32(buf) => Ok(buf),
-            _ => Err("BufferAny is not of type f32".to_string()),
-        }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-}
-impl TryInto<Subbuffer<[Vec2]>> for BufferKindsEnum {
-    type Error = String;
 
-    fn try_into(self) -> std::result::Result<Subbuffer<[Vec2]>, Self::Error> {
-        match self {
-            BufferKindsEnum::Vec2(buf) => Ok(buf),
-            _ => Err("BufferAny is not of type Vec2".to_string()),
-        }
+    /// The buffer as raw bytes
+    pub fn bytes(&self) -> &Subbuffer<[u8]> {
+        &self.bytes
     }
-}
-impl TryInto<Subbuffer<[GridCell]>> for BufferKindsEnum {
-    type Error = String;
 
-    fn try_into(self) -> std::result::Result<Subbuffer<[GridCell]>, Self::Error> {
-        match self {
-            BufferKindsEnum::GridCell(buf) => Ok(buf),
-            _ => Err("BufferAny is not of type GridCell".to_string()),
-        }
+    /// The buffer as `Subbuffer<[T]>`, or [`ChimeraError::AnyBufferTypeMismatch`]
+    /// if it holds another element type.
+    pub fn downcast<T: 'static>(&self) -> CrateResult<Subbuffer<[T]>> {
+        self.typed
+            .downcast_ref::<Subbuffer<[T]>>()
+            .cloned()
+            .ok_or_else(|| ChimeraError::AnyBufferTypeMismatch {
+                stored: self.type_name.to_string(),
+                requested: std::any::type_name::<T>().to_string(),
+            })
     }
-}
 
-impl BufferKindsEnum {
     pub fn write_descriptor_set_for_binding(&self, binding: u32) -> WriteDescriptorSet {
-        match self {
-            BufferKindsEnum::u32(buf) => WriteDescriptorSet::buffer(binding, buf.clone()),
-            BufferKindsEnum::f32(buf) => WriteDescriptorSet::buffer(binding, buf.clone()),
-            BufferKindsEnum::Vec2(buf) => WriteDescriptorSet::buffer(binding, buf.clone()),
-            BufferKindsEnum::GridCell(buf) => WriteDescriptorSet::buffer(binding, buf.clone()),
-        }
+        WriteDescriptorSet::buffer(binding, self.bytes.clone())
     }
 
-    pub fn read_u32(&self) -> Vec<u32> {
-        match self {
-            BufferKindsEnum::u32(buf) => {
-                let content = buf.read().expect("failed to read buffer");
-                content.to_vec()
-            }
-            _ => panic!("BufferAny is not of type u32"),
-        }
+    /// Copy the buffer contents back to the host.
+    pub fn read<T: BufferContents + Pod>(&self) -> CrateResult<Vec<T>> {
+        Ok(self.downcast::<T>()?.read()?.to_vec())
     }
-    pub fn read_f32(&self) -> Vec<f32> {
-        match self {
-            BufferKindsEnum::f32(buf) => {
-                let content = buf.read().expect("failed to read buffer");
-                content.to_vec()
-            }
-            _ => panic!("BufferAny is not of type f32"),
-        }
-    }
-    pub fn read_vec2(&self) -> Vec<Vec2> {
-        match self {
-            BufferKindsEnum::Vec2(buf) => {
-                let content = buf.read().expect("failed to read buffer");
-                content.to_vec()
-            }
-            _ => panic!("BufferAny is not of type Vec2"),
-        }
-    }
-    pub fn read_grid_cell(&self) -> Vec<GridCell> {
-        match self {
-            BufferKindsEnum::GridCell(buf) => {
-                let content = buf.read().expect("failed to read buffer");
-                content.to_vec()
-            }
-            _ => panic!("BufferAny is not of type GridCell"),
+
+    /// Overwrite the buffer contents with `data`, which must have the buffer's length.
+    pub fn write<T: BufferContents + Pod>(&self, data: &[T]) -> CrateResult<()> {
+        let buf = self.downcast::<T>()?;
+        let mut content = buf.write()?;
+        if content.len() != data.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: content.len(),
+                data_len: data.len(),
+            });
         }
+        content.copy_from_slice(data);
+        Ok(())
     }
 }
 
-pub struct BufNameToBufferAny(pub HashMap<String, BufferKindsEnum>);
+pub struct BufNameToBufferAny(pub HashMap<String, AnyBuffer>);
 
 #[derive(Clone)]
 pub struct BufNameToBinding(pub HashMap<String, u32>);
@@ -155,7 +141,11 @@ pub fn build_and_fill_buffer<T: BufferContents + Copy>(
     memory_allocator: Arc<StandardMemoryAllocator>,
     data: &[T],
 ) -> CrateResult<Subbuffer<[T]>> {
-    let usage = BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
+    // registry buffers can also hold the workgroup count of an indirect dispatch
+    let usage = BufferUsage::STORAGE_BUFFER
+        | BufferUsage::INDIRECT_BUFFER
+        | BufferUsage::TRANSFER_SRC
+        | BufferUsage::TRANSFER_DST;
 
     let buffer: Subbuffer<[T]> = Buffer::from_iter(
         memory_allocator,
@@ -0,0 +1,247 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bytemuck::Pod;
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    descriptor_set::WriteDescriptorSet,
+    memory::allocator::StandardMemoryAllocator,
+};
+
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::{
+        cpu_compute_chain::host_buffers::{HostBuffers, IntoHostBuffers},
+        vulkano::{
+            buffer::AnyBuffer,
+            buffer_specs::{
+                DataAndBindingSpec, DescriptorSetByName, IntoDescriptorSetByName, Residency,
+            },
+            staging::Staging,
+            typed_subbuffer_by_name::TypedSubbufferByName,
+        },
+    },
+};
+
+/// A GPU buffer in a [`BufferRegistry`] and the binding it is bound at
+#[derive(Clone)]
+struct RegisteredBuffer {
+    binding: u32,
+    buffer: AnyBuffer,
+    residency: Residency,
+}
+
+/// GPU buffers keyed by name, with no limit on their number or element types.
+///
+/// Replaces tuples of `SubbufferAndBindingSpec`s: lookups are a hash map access instead
+/// of a scan over the tuple, and buffers can be added at any time.
+#[derive(Clone, Default)]
+pub struct BufferRegistry {
+    buffers: HashMap<String, RegisteredBuffer>,
+}
+
+impl BufferRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the host-visible `buffer` as `name`, bound at `binding`, or
+    /// [`ChimeraError::DuplicateBufferName`] if the registry already has a buffer with
+    /// that name.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        binding: u32,
+        buffer: impl Into<AnyBuffer>,
+    ) -> CrateResult<()> {
+        self.insert_with_residency(name, binding, buffer, Residency::HostVisible)
+    }
+
+    /// Register `buffer`, allocated with `residency`, as `name`, bound at `binding`
+    pub fn insert_with_residency(
+        &mut self,
+        name: impl Into<String>,
+        binding: u32,
+        buffer: impl Into<AnyBuffer>,
+        residency: Residency,
+    ) -> CrateResult<()> {
+        let name = self.new_name(name)?;
+        let buffer = RegisteredBuffer {
+            binding,
+            buffer: buffer.into(),
+            residency,
+        };
+        self.buffers.insert(name, buffer);
+        Ok(())
+    }
+
+    /// Upload `data` into a new host-visible storage buffer and register it as `name`.
+    pub fn insert_data<T: BufferContents + Pod>(
+        &mut self,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        name: impl Into<String>,
+        binding: u32,
+        data: &[T],
+    ) -> CrateResult<()> {
+        let name = self.new_name(name)?;
+        let buffer = AnyBuffer::from_data(memory_allocator, data)?;
+        self.insert(name, binding, buffer)
+    }
+
+    /// `name`, if no buffer is registered under it yet
+    fn new_name(&self, name: impl Into<String>) -> CrateResult<String> {
+        let name = name.into();
+        if self.contains(&name) {
+            return Err(ChimeraError::DuplicateBufferName(name));
+        }
+        Ok(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.buffers.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.buffers.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    fn get(&self, name: &str) -> CrateResult<&RegisteredBuffer> {
+        self.buffers
+            .get(name)
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(name.to_string()))
+    }
+
+    /// The buffer named `name`, with its element type erased
+    pub fn buffer(&self, name: &str) -> CrateResult<&AnyBuffer> {
+        Ok(&self.get(name)?.buffer)
+    }
+
+    /// The buffer named `name` as `Subbuffer<[T]>`, or
+    /// [`ChimeraError::BufferTypeMismatch`] if it holds another element type.
+    pub fn subbuffer<T: 'static>(&self, name: &str) -> CrateResult<Subbuffer<[T]>> {
+        let buffer = self.buffer(name)?;
+        if !buffer.is::<T>() {
+            return Err(ChimeraError::BufferTypeMismatch {
+                name: name.to_string(),
+                stored: buffer.type_name().to_string(),
+                requested: std::any::type_name::<T>().to_string(),
+            });
+        }
+        buffer.downcast()
+    }
+}
+
+impl DescriptorSetByName for BufferRegistry {
+    fn descriptor_set_by_name(&self, name: &str) -> CrateResult<WriteDescriptorSet> {
+        let registered = self.get(name)?;
+        Ok(registered
+            .buffer
+            .write_descriptor_set_for_binding(registered.binding))
+    }
+
+    fn element_size_by_name(&self, name: &str) -> CrateResult<usize> {
+        Ok(self.buffer(name)?.element_size())
+    }
+
+    fn len_by_name(&self, name: &str) -> CrateResult<usize> {
+        Ok(self.buffer(name)?.len())
+    }
+
+    fn bytes_by_name(&self, name: &str) -> CrateResult<Subbuffer<[u8]>> {
+        Ok(self.buffer(name)?.bytes().clone())
+    }
+
+    fn residency_by_name(&self, name: &str) -> CrateResult<Residency> {
+        Ok(self.get(name)?.residency)
+    }
+}
+
+impl TypedSubbufferByName for BufferRegistry {
+    fn subbuffer<T: 'static>(&self, name: &str) -> CrateResult<Subbuffer<[T]>> {
+        BufferRegistry::subbuffer(self, name)
+    }
+
+    fn replace_subbuffer<T: BufferContents + Pod>(
+        &mut self,
+        name: &str,
+        sub_buf: Subbuffer<[T]>,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        let replaced = BufferRegistry::subbuffer::<T>(self, name)?;
+        if let Some(registered) = self.buffers.get_mut(name) {
+            registered.buffer = sub_buf.into();
+        }
+        Ok(replaced)
+    }
+}
+
+/// A `buf_spec` that can be uploaded into a [`BufferRegistry`] (or [`HostBuffers`])
+/// without naming its element type.
+pub trait RegistryBufferSpec: IntoHostBuffers {
+    fn register(&self, registry: &mut BufferRegistry, staging: &Staging) -> CrateResult<()>;
+}
+
+impl<'a, T> RegistryBufferSpec for DataAndBindingSpec<'a, T>
+where
+    T: BufferContents + Pod,
+{
+    fn register(&self, registry: &mut BufferRegistry, staging: &Staging) -> CrateResult<()> {
+        if registry.contains(self.name) {
+            return Err(ChimeraError::DuplicateBufferName(self.name.to_string()));
+        }
+        let buffer = staging.buffer_from_data(self.data, self.residency)?;
+        registry.insert_with_residency(self.name, self.binding, buffer, self.residency)
+    }
+}
+
+/// Any number of `buf_spec`s, for compute chains backed by a [`BufferRegistry`].
+///
+/// The names of the specs must be unique: building a chain from specs that repeat a
+/// name fails with [`ChimeraError::DuplicateBufferName`].
+///
+/// ```ignore
+/// let buf_specs = RegistryBufferSpecs::new()
+///     .with(buf_spec("x", 2, &mut x))
+///     .with(buf_spec("grid", 4, &mut grid));
+/// ```
+#[derive(Default)]
+pub struct RegistryBufferSpecs<'a> {
+    specs: Vec<Box<dyn RegistryBufferSpec + 'a>>,
+}
+
+impl<'a> RegistryBufferSpecs<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, spec: impl RegistryBufferSpec + 'a) -> Self {
+        self.specs.push(Box::new(spec));
+        self
+    }
+}
+
+impl IntoDescriptorSetByName for RegistryBufferSpecs<'_> {
+    type Out = BufferRegistry;
+
+    fn with_gpu_buffer(&self, staging: &Staging) -> CrateResult<BufferRegistry> {
+        let mut registry = BufferRegistry::new();
+        for spec in self.specs.iter() {
+            spec.register(&mut registry, staging)?;
+        }
+        Ok(registry)
+    }
+}
+
+impl IntoHostBuffers for RegistryBufferSpecs<'_> {
+    fn with_host_buffer(&self, buffers: &mut HostBuffers) -> CrateResult<()> {
+        self.specs
+            .iter()
+            .try_for_each(|spec| spec.with_host_buffer(buffers))
+    }
+}
@@ -1,18 +1,17 @@
-use std::sync::Arc;
-
 use variadics_please::all_tuples_enumerated;
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    buffer::{BufferContents, Subbuffer},
     descriptor_set::WriteDescriptorSet,
-    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    memory::allocator::MemoryTypeFilter,
 };
 
-use crate::error::CrateResult;
+use crate::{error::CrateResult, runners::vulkano::staging::Staging};
 
 pub struct DataAndBindingSpec<'a, T> {
     pub name: &'static str,
     pub binding: u32,
     pub data: &'a mut [T],
+    pub residency: Residency,
 }
 
 pub fn buf_spec<'a, T>(
@@ -24,6 +23,49 @@ pub fn buf_spec<'a, T>(
         name,
         binding,
         data,
+        residency: Residency::default(),
+    }
+}
+
+impl<T> DataAndBindingSpec<'_, T> {
+    /// Keep the buffer in the memory `residency` chooses instead of host-visible memory
+    pub fn with_residency(mut self, residency: Residency) -> Self {
+        self.residency = residency;
+        self
+    }
+}
+
+/// Where a buffer of a chain lives, and how the host reaches it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Residency {
+    /// Device-local memory, the fastest for kernels; the host only reaches it through
+    /// staging buffers
+    DeviceLocal,
+    /// Host memory the host maps, slower for kernels
+    #[default]
+    HostVisible,
+    /// Device-local memory the host can map where the device has some, else host
+    /// memory; for results the host reads back often
+    Readback,
+}
+
+impl Residency {
+    /// Whether the host maps the buffer instead of going through a staging buffer
+    pub fn is_host_visible(self) -> bool {
+        self != Residency::DeviceLocal
+    }
+
+    /// The memory a buffer with this residency is allocated from
+    pub fn memory_type_filter(self) -> MemoryTypeFilter {
+        match self {
+            Residency::DeviceLocal => MemoryTypeFilter::PREFER_DEVICE,
+            Residency::HostVisible => {
+                MemoryTypeFilter::PREFER_HOST | MemoryTypeFilter::HOST_RANDOM_ACCESS
+            }
+            Residency::Readback => {
+                MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_RANDOM_ACCESS
+            }
+        }
     }
 }
 
@@ -31,14 +73,14 @@ pub struct SubbufferAndBindingSpec<T> {
     pub name: &'static str,
     pub binding: u32,
     pub sub_buf: Subbuffer<[T]>,
+    pub residency: Residency,
 }
 
 pub trait IntoDescriptorSetByName {
     type Out: DescriptorSetByName;
-    fn with_gpu_buffer(
-        &self,
-        memory_allocator: Arc<StandardMemoryAllocator>,
-    ) -> CrateResult<Self::Out>;
+    /// Allocate and fill the buffers, uploading through `staging` where the host can't
+    /// map them
+    fn with_gpu_buffer(&self, staging: &Staging) -> CrateResult<Self::Out>;
 }
 
 impl<'a, T> IntoDescriptorSetByName for DataAndBindingSpec<'a, T>
@@ -47,31 +89,14 @@ where
     SubbufferAndBindingSpec<T>: DescriptorSetByName,
 {
     type Out = SubbufferAndBindingSpec<T>;
-    fn with_gpu_buffer(
-        &self,
-        memory_allocator: Arc<StandardMemoryAllocator>,
-    ) -> CrateResult<SubbufferAndBindingSpec<T>> {
-        let usage =
-            BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
-
-        let sub_buf: Subbuffer<[T]> = Buffer::from_iter(
-            memory_allocator,
-            BufferCreateInfo {
-                usage,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
-                ..Default::default()
-            },
-            self.data.iter().copied(),
-        )?;
+    fn with_gpu_buffer(&self, staging: &Staging) -> CrateResult<SubbufferAndBindingSpec<T>> {
+        let sub_buf = staging.buffer_from_data(self.data, self.residency)?;
 
         Ok(SubbufferAndBindingSpec {
             name: self.name,
             binding: self.binding,
             sub_buf,
+            residency: self.residency,
         })
     }
 }
@@ -81,11 +106,10 @@ macro_rules! impl_into_descriptor_set_by_name_for_tuple {
         impl<$($T: IntoDescriptorSetByName),*> IntoDescriptorSetByName for ($($T,)*) {
 
             type Out = ($($T::Out,)*);
-            fn with_gpu_buffer(&self,
-                memory_allocator: Arc<StandardMemoryAllocator>) -> CrateResult<Self::Out> {
+            fn with_gpu_buffer(&self, staging: &Staging) -> CrateResult<Self::Out> {
                 Ok((
                     $(
-                        self.$n.with_gpu_buffer(memory_allocator.clone())?,
+                        self.$n.with_gpu_buffer(staging)?,
                     )*
                 ))
             }
@@ -102,6 +126,18 @@ all_tuples_enumerated!(impl_into_descriptor_set_by_name_for_tuple, 1, 15, T);
 
 pub trait DescriptorSetByName {
     fn descriptor_set_by_name(&self, name: &str) -> CrateResult<WriteDescriptorSet>;
+
+    /// Size in bytes of one element of the buffer named `name`
+    fn element_size_by_name(&self, name: &str) -> CrateResult<usize>;
+
+    /// Number of elements of the buffer named `name`
+    fn len_by_name(&self, name: &str) -> CrateResult<usize>;
+
+    /// The buffer named `name` as raw bytes, e.g. to copy it
+    fn bytes_by_name(&self, name: &str) -> CrateResult<Subbuffer<[u8]>>;
+
+    /// Where the buffer named `name` lives
+    fn residency_by_name(&self, name: &str) -> CrateResult<Residency>;
 }
 
 impl<S> DescriptorSetByName for SubbufferAndBindingSpec<S> {
@@ -117,6 +153,46 @@ impl<S> DescriptorSetByName for SubbufferAndBindingSpec<S> {
             ))
         }
     }
+
+    fn element_size_by_name(&self, name: &str) -> CrateResult<usize> {
+        if name == self.name {
+            Ok(std::mem::size_of::<S>())
+        } else {
+            Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                name.to_string(),
+            ))
+        }
+    }
+
+    fn len_by_name(&self, name: &str) -> CrateResult<usize> {
+        if name == self.name {
+            Ok(self.sub_buf.len() as usize)
+        } else {
+            Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                name.to_string(),
+            ))
+        }
+    }
+
+    fn bytes_by_name(&self, name: &str) -> CrateResult<Subbuffer<[u8]>> {
+        if name == self.name {
+            Ok(self.sub_buf.clone().into_bytes())
+        } else {
+            Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                name.to_string(),
+            ))
+        }
+    }
+
+    fn residency_by_name(&self, name: &str) -> CrateResult<Residency> {
+        if name == self.name {
+            Ok(self.residency)
+        } else {
+            Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                name.to_string(),
+            ))
+        }
+    }
 }
 
 macro_rules! impl_descriptor_set_by_name_for_tuple {
@@ -133,6 +209,50 @@ macro_rules! impl_descriptor_set_by_name_for_tuple {
                      name.to_string()),
                 )
             }
+
+            fn element_size_by_name(&self, name: &str) -> CrateResult<usize> {
+                $(
+                    if let Ok(size) = self.$n.element_size_by_name(name) {
+                        return Ok(size);
+                    }
+                )*
+                Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                    name.to_string(),
+                ))
+            }
+
+            fn len_by_name(&self, name: &str) -> CrateResult<usize> {
+                $(
+                    if let Ok(len) = self.$n.len_by_name(name) {
+                        return Ok(len);
+                    }
+                )*
+                Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                    name.to_string(),
+                ))
+            }
+
+            fn bytes_by_name(&self, name: &str) -> CrateResult<Subbuffer<[u8]>> {
+                $(
+                    if let Ok(bytes) = self.$n.bytes_by_name(name) {
+                        return Ok(bytes);
+                    }
+                )*
+                Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                    name.to_string(),
+                ))
+            }
+
+            fn residency_by_name(&self, name: &str) -> CrateResult<Residency> {
+                $(
+                    if let Ok(residency) = self.$n.residency_by_name(name) {
+                        return Ok(residency);
+                    }
+                )*
+                Err(crate::error::ChimeraError::DescriptorSetNameNotFound(
+                    name.to_string(),
+                ))
+            }
         }
     };
 }
@@ -0,0 +1,280 @@
+//! Record a chain of invocations with explicit barriers
+//!
+//! An `AutoCommandBufferBuilder` infers the hazards between dispatches from the
+//! resources they bind, and has no way to know that two atomic scatters into the same
+//! buffer do not need ordering. The [`ChainRecorder`] records into a plain
+//! `RecordingCommandBuffer` instead, with exactly the barriers the chain's
+//! [`DependencyGraph`] asks for, and a final barrier making the results visible to
+//! the host.
+//!
+//! With profiling on, a timestamp is also written before and after every dispatch
+//! (see [`profiling`](crate::runners::vulkano::profiling)).
+//!
+//! With substeps, the chain is recorded several times in a row, with one barrier
+//! between the runs if the next run depends on the previous one.
+//!
+//! Submissions of a chain may overlap (see
+//! [`frames_in_flight`](crate::runners::vulkano::frames_in_flight)), so the recording
+//! also starts with a barrier waiting for everything submitted to the queue before it.
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        sys::{CommandBuffer, CommandBufferBeginInfo, RecordingCommandBuffer},
+        CommandBufferLevel, CommandBufferSubmitInfo, CommandBufferUsage, CopyBufferInfo,
+        SubmitInfo,
+    },
+    device::{Device, Queue},
+    query::QueryPool,
+    sync::{
+        fence::{Fence, FenceCreateInfo},
+        AccessFlags, DependencyInfo, MemoryBarrier, PipelineStage, PipelineStages,
+    },
+};
+
+use crate::{
+    error::CrateResult,
+    runners::vulkano::{
+        dependency_graph::DependencyGraph,
+        shader_pipeline_builder::{Ready, ShaderPipelineBuilder},
+    },
+};
+
+/// Records the dispatches of a chain, in order, with the barriers of its dependency graph
+pub struct ChainRecorder<'a> {
+    pipelines: &'a [ShaderPipelineBuilder<Ready>],
+    dependency_graph: &'a DependencyGraph,
+    /// How many times the chain is recorded
+    substeps: u32,
+    /// (source, destination) buffers copied once every dispatch finished
+    copies: Vec<(Subbuffer<[u8]>, Subbuffer<[u8]>)>,
+    /// Where the timestamps around each dispatch go, if profiling
+    timestamps: Option<Arc<QueryPool>>,
+}
+
+impl<'a> ChainRecorder<'a> {
+    /// `dependency_graph` must be the graph of the specs of `pipelines`
+    pub fn new(
+        pipelines: &'a [ShaderPipelineBuilder<Ready>],
+        dependency_graph: &'a DependencyGraph,
+    ) -> Self {
+        Self {
+            pipelines,
+            dependency_graph,
+            substeps: 1,
+            copies: Vec::new(),
+            timestamps: None,
+        }
+    }
+
+    /// Record the chain `substeps` times in a row
+    pub fn with_substeps(self, substeps: u32) -> Self {
+        Self { substeps, ..self }
+    }
+
+    /// Copy each source into its destination after the last dispatch, e.g. to keep a
+    /// snapshot of the chain's results while later submissions update them
+    pub fn with_copies(self, copies: Vec<(Subbuffer<[u8]>, Subbuffer<[u8]>)>) -> Self {
+        Self { copies, ..self }
+    }
+
+    /// Write a timestamp before and after every dispatch into `query_pool`, which needs
+    /// two queries per invocation and substep
+    pub fn with_timestamps(self, query_pool: Option<Arc<QueryPool>>) -> Self {
+        Self {
+            timestamps: query_pool,
+            ..self
+        }
+    }
+
+    pub fn record(
+        &self,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        queue: &Queue,
+        usage: CommandBufferUsage,
+    ) -> CrateResult<Arc<CommandBuffer>> {
+        let mut recorder = unsafe {
+            RecordingCommandBuffer::new(
+                command_buffer_allocator,
+                queue.queue_family_index(),
+                CommandBufferLevel::Primary,
+                CommandBufferBeginInfo {
+                    usage,
+                    ..Default::default()
+                },
+            )?
+        };
+
+        // earlier submissions of the chain update the same buffers, and others (e.g. a
+        // renderer) may read them or the copy destinations
+        let after_earlier_submissions = barrier(
+            PipelineStages::ALL_COMMANDS,
+            AccessFlags::SHADER_WRITE | AccessFlags::TRANSFER_WRITE,
+            PipelineStages::COMPUTE_SHADER
+                | PipelineStages::DRAW_INDIRECT
+                | PipelineStages::ALL_TRANSFER,
+            AccessFlags::SHADER_READ
+                | AccessFlags::SHADER_WRITE
+                | AccessFlags::INDIRECT_COMMAND_READ
+                | AccessFlags::TRANSFER_READ
+                | AccessFlags::TRANSFER_WRITE,
+        );
+        unsafe { recorder.pipeline_barrier(&after_earlier_submissions)? };
+
+        if let Some(query_pool) = &self.timestamps {
+            unsafe { recorder.reset_query_pool(query_pool, 0..query_pool.query_count())? };
+        }
+
+        for substep in 0..self.substeps {
+            let graph = self.dependency_graph;
+            if substep > 0 && graph.needs_barrier_between_repeats() {
+                let barrier = chain_barrier(
+                    graph.repeat_barrier_needs_indirect_command_read(),
+                    graph.repeat_barrier_needs_memory_dependency(),
+                );
+                unsafe { recorder.pipeline_barrier(&barrier)? };
+            }
+            for (index, pipeline) in self.pipelines.iter().enumerate() {
+                if graph.needs_barrier_before(index) {
+                    let barrier = chain_barrier(
+                        graph.barrier_needs_indirect_command_read(index),
+                        graph.barrier_needs_memory_dependency(index),
+                    );
+                    unsafe { recorder.pipeline_barrier(&barrier)? };
+                }
+                let query = 2 * (substep * self.pipelines.len() as u32 + index as u32);
+                if let Some(query_pool) = &self.timestamps {
+                    unsafe {
+                        recorder.write_timestamp(query_pool, query, PipelineStage::TopOfPipe)?
+                    };
+                }
+                pipeline.record_dispatch(&mut recorder)?;
+                if let Some(query_pool) = &self.timestamps {
+                    unsafe {
+                        recorder.write_timestamp(
+                            query_pool,
+                            query + 1,
+                            PipelineStage::BottomOfPipe,
+                        )?
+                    };
+                }
+            }
+        }
+
+        if !self.copies.is_empty() {
+            let to_copies = memory_barrier(
+                AccessFlags::SHADER_WRITE,
+                PipelineStages::ALL_TRANSFER,
+                AccessFlags::TRANSFER_READ,
+            );
+            unsafe { recorder.pipeline_barrier(&to_copies)? };
+            for (src, dst) in &self.copies {
+                unsafe {
+                    recorder.copy_buffer(&CopyBufferInfo::buffers(src.clone(), dst.clone()))?
+                };
+            }
+        }
+
+        // the host reads the buffers back once the fence is signaled, and later
+        // submissions (e.g. rendering) may read the copies
+        let to_host = barrier(
+            PipelineStages::COMPUTE_SHADER | PipelineStages::ALL_TRANSFER,
+            AccessFlags::SHADER_WRITE | AccessFlags::TRANSFER_WRITE,
+            PipelineStages::HOST | PipelineStages::ALL_COMMANDS,
+            AccessFlags::HOST_READ | AccessFlags::MEMORY_READ,
+        );
+        unsafe { recorder.pipeline_barrier(&to_host)? };
+
+        Ok(Arc::new(unsafe { recorder.end()? }))
+    }
+}
+
+/// The barrier between two dispatches of the chain, as weak as the dependencies it
+/// serves allow
+fn chain_barrier(
+    needs_indirect_command_read: bool,
+    needs_memory_dependency: bool,
+) -> DependencyInfo {
+    if needs_indirect_command_read {
+        // the workgroup count is read in the draw indirect stage
+        memory_barrier(
+            AccessFlags::SHADER_WRITE,
+            PipelineStages::COMPUTE_SHADER | PipelineStages::DRAW_INDIRECT,
+            AccessFlags::SHADER_READ
+                | AccessFlags::SHADER_WRITE
+                | AccessFlags::INDIRECT_COMMAND_READ,
+        )
+    } else if needs_memory_dependency {
+        memory_barrier(
+            AccessFlags::SHADER_WRITE,
+            PipelineStages::COMPUTE_SHADER,
+            AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+        )
+    } else {
+        // write-after-read only: the reads just have to finish first
+        memory_barrier(
+            AccessFlags::empty(),
+            PipelineStages::COMPUTE_SHADER,
+            AccessFlags::empty(),
+        )
+    }
+}
+
+/// A global barrier from the compute shader writes of earlier dispatches to later
+/// `dst_stages`
+fn memory_barrier(
+    src_access: AccessFlags,
+    dst_stages: PipelineStages,
+    dst_access: AccessFlags,
+) -> DependencyInfo {
+    barrier(
+        PipelineStages::COMPUTE_SHADER,
+        src_access,
+        dst_stages,
+        dst_access,
+    )
+}
+
+/// A global barrier from `src_stages` to `dst_stages`
+fn barrier(
+    src_stages: PipelineStages,
+    src_access: AccessFlags,
+    dst_stages: PipelineStages,
+    dst_access: AccessFlags,
+) -> DependencyInfo {
+    DependencyInfo {
+        memory_barriers: vec![MemoryBarrier {
+            src_stages,
+            src_access,
+            dst_stages,
+            dst_access,
+            ..Default::default()
+        }]
+        .into(),
+        ..Default::default()
+    }
+}
+
+/// Submit a command buffer recorded by a [`ChainRecorder`], without waiting for it
+///
+/// The returned fence is signaled once it finished; `command_buffer` must be kept
+/// alive until then.
+pub fn submit(
+    device: Arc<Device>,
+    queue: &Queue,
+    command_buffer: Arc<CommandBuffer>,
+) -> CrateResult<Arc<Fence>> {
+    let fence = Arc::new(Fence::new(device, FenceCreateInfo::default())?);
+    queue.with(|mut queue| unsafe {
+        queue.submit(
+            &[SubmitInfo {
+                command_buffers: vec![CommandBufferSubmitInfo::new(command_buffer)],
+                ..Default::default()
+            }],
+            Some(&fence),
+        )
+    })?;
+    Ok(fence)
+}
@@ -0,0 +1,343 @@
+//! Dependencies between the invocations of a chain
+//!
+//! From the [`BufferAccess`] annotations of each invocation, find which earlier
+//! invocations it has to wait for, and where the chain needs a barrier: invocations
+//! are recorded in order, and a barrier goes before an invocation only if it depends
+//! on an invocation recorded since the previous barrier. One barrier orders
+//! everything before it with everything after it, so independent invocations
+//! (e.g. `adder` and `clear_grid`) share a batch.
+//!
+//! Invocations in the same batch run unordered. That is only fine for buffers they
+//! both update atomically, and [`DependencyGraph::unordered_writes`] reports them.
+//!
+//! An indirect dispatch reads its workgroup count from a buffer like any other read,
+//! but the device reads it before the shader runs, so a barrier in front of it must
+//! also make earlier writes visible to indirect command reads.
+//!
+//! A chain recorded several times in a row (substeps) also depends on its previous
+//! run; [`DependencyGraph::repeat_dependencies`] finds those dependencies from the
+//! chain followed by itself, and one barrier between the runs serves them all.
+use std::fmt;
+
+use crate::runners::vulkano::shader_pipeline_builder::{BufferAccess, ShaderPipelineSpec};
+
+/// The kind of conflict between two accesses to the same buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hazard {
+    ReadAfterWrite,
+    WriteAfterRead,
+    WriteAfterWrite,
+}
+
+impl Hazard {
+    /// Whether the later invocation must see the earlier one's writes, i.e. the
+    /// barrier must make memory available and not only order execution
+    pub fn needs_memory_dependency(self) -> bool {
+        !matches!(self, Hazard::WriteAfterRead)
+    }
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Hazard::ReadAfterWrite => "read after write",
+            Hazard::WriteAfterRead => "write after read",
+            Hazard::WriteAfterWrite => "write after write",
+        })
+    }
+}
+
+/// Invocation `to` has to wait for invocation `from` (indices in the chain)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    pub from: usize,
+    pub to: usize,
+    pub buf_name: &'static str,
+    pub hazard: Hazard,
+}
+
+/// Invocations `first` and `second` both write `buf_name` with nothing ordering them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnorderedWrite {
+    pub first: usize,
+    pub second: usize,
+    pub buf_name: &'static str,
+}
+
+/// Earlier accesses to a buffer that later invocations may conflict with
+#[derive(Default)]
+struct BufferState {
+    /// The last writer, or the atomic writers since the last non-atomic write
+    writers: Vec<(usize, BufferAccess)>,
+    /// Readers since the last write
+    readers: Vec<usize>,
+}
+
+/// The dependency graph of a chain, and the barriers it needs
+#[derive(Clone, Debug)]
+pub struct DependencyGraph {
+    invocation_names: Vec<&'static str>,
+    /// The buffer each indirect dispatch reads its workgroup count from
+    indirect_buf_names: Vec<Option<&'static str>>,
+    dependencies: Vec<Dependency>,
+    /// Dependencies on the previous run of the chain; `to` indexes the later run
+    repeat_dependencies: Vec<Dependency>,
+    /// Whether a barrier is recorded before each invocation
+    barrier_before: Vec<bool>,
+    unordered_writes: Vec<UnorderedWrite>,
+}
+
+impl DependencyGraph {
+    pub fn new(pipeline_specs: &[ShaderPipelineSpec]) -> Self {
+        let dependencies = find_dependencies(pipeline_specs.iter());
+
+        // the chain followed by itself: the dependencies of the second run on the first
+        let n = pipeline_specs.len();
+        let repeat_dependencies = find_dependencies(pipeline_specs.iter().chain(pipeline_specs))
+            .into_iter()
+            .filter(|d| d.from < n && d.to >= n)
+            .map(|d| Dependency { to: d.to - n, ..d })
+            .collect();
+
+        // greedy batches: a barrier before an invocation that depends on the current batch
+        let mut barrier_before = vec![false; pipeline_specs.len()];
+        let mut batch_start = 0;
+        for (to, barrier) in barrier_before.iter_mut().enumerate() {
+            if dependencies
+                .iter()
+                .any(|d| d.to == to && d.from >= batch_start)
+            {
+                *barrier = true;
+                batch_start = to;
+            }
+        }
+
+        // writes to the same buffer within a batch are unordered
+        let batch_of = |i: usize| barrier_before[..=i].iter().filter(|b| **b).count();
+        let mut unordered_writes = Vec::new();
+        for (second, spec) in pipeline_specs.iter().enumerate() {
+            for (first, earlier) in pipeline_specs[..second].iter().enumerate() {
+                if batch_of(first) != batch_of(second) {
+                    continue;
+                }
+                for (buf_name, access) in spec.buffer_accesses() {
+                    if access.writes()
+                        && earlier.access(buf_name).writes()
+                        && earlier.buf_names().contains(&buf_name)
+                    {
+                        unordered_writes.push(UnorderedWrite {
+                            first,
+                            second,
+                            buf_name,
+                        });
+                    }
+                }
+            }
+        }
+
+        Self {
+            invocation_names: pipeline_specs
+                .iter()
+                .map(ShaderPipelineSpec::invocation_name)
+                .collect(),
+            indirect_buf_names: pipeline_specs
+                .iter()
+                .map(ShaderPipelineSpec::indirect_buf_name)
+                .collect(),
+            dependencies,
+            repeat_dependencies,
+            barrier_before,
+            unordered_writes,
+        }
+    }
+
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+
+    /// The dependencies of invocation `index` on earlier invocations
+    pub fn dependencies_of(&self, index: usize) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.iter().filter(move |d| d.to == index)
+    }
+
+    /// Whether a barrier must be recorded before invocation `index`
+    pub fn needs_barrier_before(&self, index: usize) -> bool {
+        self.barrier_before.get(index).copied().unwrap_or(false)
+    }
+
+    /// Whether the barrier before invocation `index` must make earlier writes visible,
+    /// or only has to order execution (write-after-read hazards only)
+    ///
+    /// The barrier serves every invocation of the batch it starts, not only `index`.
+    pub fn barrier_needs_memory_dependency(&self, index: usize) -> bool {
+        self.dependencies_across_barrier(index)
+            .any(|d| d.hazard.needs_memory_dependency())
+    }
+
+    /// Whether an indirect dispatch after the barrier before invocation `index` reads
+    /// its workgroup count from a buffer written before the barrier
+    pub fn barrier_needs_indirect_command_read(&self, index: usize) -> bool {
+        self.dependencies_across_barrier(index)
+            .any(|d| self.reads_indirect_command(d))
+    }
+
+    /// The dependencies of the chain's invocations on its previous run, when it runs
+    /// several times in a row; `from` indexes the earlier run and `to` the later one
+    pub fn repeat_dependencies(&self) -> &[Dependency] {
+        &self.repeat_dependencies
+    }
+
+    /// Whether a barrier must be recorded between two runs of the chain
+    pub fn needs_barrier_between_repeats(&self) -> bool {
+        !self.repeat_dependencies.is_empty()
+    }
+
+    /// Like [`Self::barrier_needs_memory_dependency`], for the barrier between two
+    /// runs of the chain
+    pub fn repeat_barrier_needs_memory_dependency(&self) -> bool {
+        self.repeat_dependencies
+            .iter()
+            .any(|d| d.hazard.needs_memory_dependency())
+    }
+
+    /// Like [`Self::barrier_needs_indirect_command_read`], for the barrier between two
+    /// runs of the chain
+    pub fn repeat_barrier_needs_indirect_command_read(&self) -> bool {
+        self.repeat_dependencies
+            .iter()
+            .any(|d| self.reads_indirect_command(d))
+    }
+
+    /// Whether the later invocation of `dependency` reads its workgroup count from
+    /// what the earlier one wrote
+    fn reads_indirect_command(&self, dependency: &Dependency) -> bool {
+        dependency.hazard == Hazard::ReadAfterWrite
+            && self.indirect_buf_names[dependency.to] == Some(dependency.buf_name)
+    }
+
+    /// The dependencies the barrier before invocation `index` serves: those of the
+    /// invocations of its batch on invocations before it
+    fn dependencies_across_barrier(&self, index: usize) -> impl Iterator<Item = &Dependency> {
+        let batch_end = (index + 1..self.barrier_before.len())
+            .find(|&i| self.barrier_before[i])
+            .unwrap_or(self.barrier_before.len());
+        self.dependencies
+            .iter()
+            .filter(move |d| (index..batch_end).contains(&d.to) && d.from < index)
+    }
+
+    pub fn barrier_count(&self) -> usize {
+        self.barrier_before.iter().filter(|b| **b).count()
+    }
+
+    pub fn unordered_writes(&self) -> &[UnorderedWrite] {
+        &self.unordered_writes
+    }
+
+    /// One warning per pair of invocations writing the same buffer unordered
+    pub fn warnings(&self) -> Vec<String> {
+        self.unordered_writes
+            .iter()
+            .map(|w| {
+                format!(
+                    "`{}` and `{}` both write buffer `{}` with no ordering between them; \
+                     this is only correct if every write is atomic",
+                    self.invocation_names[w.first], self.invocation_names[w.second], w.buf_name
+                )
+            })
+            .collect()
+    }
+}
+
+/// The dependencies of each invocation on earlier ones, in chain order
+fn find_dependencies<'a>(
+    pipeline_specs: impl Iterator<Item = &'a ShaderPipelineSpec>,
+) -> Vec<Dependency> {
+    let mut states: Vec<(&'static str, BufferState)> = Vec::new();
+    let mut dependencies = Vec::new();
+
+    for (to, spec) in pipeline_specs.enumerate() {
+        for (buf_name, access) in spec.buffer_accesses() {
+            let state = match states.iter().position(|(name, _)| *name == buf_name) {
+                Some(i) => &mut states[i].1,
+                None => {
+                    states.push((buf_name, BufferState::default()));
+                    &mut states.last_mut().unwrap().1
+                }
+            };
+            let mut depend_on = |from: usize, hazard: Hazard| {
+                dependencies.push(Dependency {
+                    from,
+                    to,
+                    buf_name,
+                    hazard,
+                })
+            };
+
+            // atomic updates commute with each other
+            let commutes = |other: BufferAccess| {
+                access == BufferAccess::Atomic && other == BufferAccess::Atomic
+            };
+            for &(from, writer_access) in &state.writers {
+                if commutes(writer_access) {
+                    continue;
+                }
+                if access.reads() {
+                    depend_on(from, Hazard::ReadAfterWrite);
+                } else {
+                    depend_on(from, Hazard::WriteAfterWrite);
+                }
+            }
+            if access.writes() {
+                for &from in &state.readers {
+                    depend_on(from, Hazard::WriteAfterRead);
+                }
+            }
+
+            if !access.writes() {
+                state.readers.push(to);
+            } else if access == BufferAccess::Atomic
+                && state.readers.is_empty()
+                && state
+                    .writers
+                    .iter()
+                    .all(|&(_, a)| a == BufferAccess::Atomic)
+            {
+                state.writers.push((to, access));
+            } else {
+                state.writers = vec![(to, access)];
+                state.readers.clear();
+            }
+        }
+    }
+
+    dependencies
+}
+
+/// The invocations in order, with their dependencies and the barriers between them
+impl fmt::Display for DependencyGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} invocations, {} barriers:",
+            self.invocation_names.len(),
+            self.barrier_count()
+        )?;
+        for (index, name) in self.invocation_names.iter().enumerate() {
+            if self.needs_barrier_before(index) {
+                writeln!(f, "  -- barrier --")?;
+            }
+            write!(f, "  {name}")?;
+            for (i, d) in self.dependencies_of(index).enumerate() {
+                let separator = if i == 0 { " after " } else { ", " };
+                write!(
+                    f,
+                    "{separator}{} ({}: {})",
+                    self.invocation_names[d.from], d.buf_name, d.hazard
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -8,6 +8,21 @@ use vulkano::{
 };
 
 pub fn compute_capable_device_and_queue(
+) -> CrateResult<(Arc<Instance>, String, Arc<Device>, Arc<Queue>)> {
+    compute_capable_device_and_queue_with_features(
+        "the shared shader module",
+        DeviceFeatures::empty(),
+    )
+}
+
+/// Like [`compute_capable_device_and_queue`], also enabling `extra_features`: those a
+/// runner's own shader module needs beyond what the shared one does
+///
+/// Fails with [`ChimeraError::UnsupportedDeviceFeatures`] naming `needed_by` if the
+/// device doesn't support all of them.
+pub fn compute_capable_device_and_queue_with_features(
+    needed_by: &str,
+    extra_features: DeviceFeatures,
 ) -> CrateResult<(Arc<Instance>, String, Arc<Device>, Arc<Queue>)> {
     // 1. Load the Vulkan library
     let library = VulkanLibrary::new()?;
@@ -32,6 +47,15 @@ pub fn compute_capable_device_and_queue(
 
     let device_name = physical.properties().device_name.clone();
 
+    let missing_features = extra_features.difference(physical.supported_features());
+    if !missing_features.is_empty() {
+        return Err(ChimeraError::UnsupportedDeviceFeatures {
+            device_name,
+            needed_by: needed_by.to_string(),
+            missing: format!("{missing_features:?}"),
+        });
+    }
+
     // 4. Select a queue family that supports compute and graphics
     // This allows both compute and graphics operations on the same queue
     let (queue_family_index, _q_props) = physical
@@ -76,6 +100,8 @@ pub fn compute_capable_device_and_queue(
     required_capabilities.shader_buffer_int64_atomics = true;
     required_capabilities.shader_int8 = true;
 
+    let required_capabilities = required_capabilities.union(&extra_features);
+
     // dbg!(physical.supported_features());
     // // Verify support before requesting so we can provide a clearer error.
 
@@ -1,23 +1,50 @@
 use std::sync::Arc;
 
 use vulkano::{
-    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    buffer::Subbuffer,
+    command_buffer::{
+        sys::RecordingCommandBuffer, AutoCommandBufferBuilder, DispatchIndirectCommand,
+        PrimaryAutoCommandBuffer,
+    },
     descriptor_set::DescriptorSet,
     pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
 };
 
 use crate::error::CrateResult;
 
-/// Bind the descriptor set and dispatch the compute shader
+/// The workgroup count of a dispatch
+#[derive(Clone)]
+pub enum Workgroups {
+    Direct([u32; 3]),
+    /// Read by the device from the first command in the buffer when the dispatch runs
+    Indirect(Subbuffer<[DispatchIndirectCommand]>),
+}
+
+impl From<[u32; 3]> for Workgroups {
+    fn from(num_workgroups: [u32; 3]) -> Self {
+        Workgroups::Direct(num_workgroups)
+    }
+}
+
+impl Workgroups {
+    /// The indirect command buffer of a [`shared::DispatchIndirectCommand`] buffer,
+    /// which has the layout of vulkano's
+    pub fn indirect(buffer: Subbuffer<[shared::DispatchIndirectCommand]>) -> Self {
+        Workgroups::Indirect(buffer.reinterpret::<[DispatchIndirectCommand]>())
+    }
+}
+
+/// Bind the descriptor set, push the push constants and dispatch the compute shader
 /// This is basically like:
-/// * providing arguments (the descriptor set)
+/// * providing arguments (the descriptor set and push constants)
 /// * to a function/function pointer (the pipeline)
 /// * then calling it (dispatch)
 pub fn bind_and_dispatch(
     builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
     pipeline: Arc<ComputePipeline>,
     descriptor_set: Arc<DescriptorSet>,
-    num_wg: [u32; 3],
+    push_constants: Option<&[u32]>,
+    workgroups: impl Into<Workgroups>,
 ) -> CrateResult<()> {
     builder.bind_descriptor_sets(
         PipelineBindPoint::Compute,
@@ -25,8 +52,49 @@ pub fn bind_and_dispatch(
         0,
         descriptor_set,
     )?;
+    // pushed word by word, since `push_constants` takes a sized value
+    for (i, word) in push_constants.unwrap_or_default().iter().enumerate() {
+        builder.push_constants(pipeline.layout().clone(), 4 * i as u32, *word)?;
+    }
+    match workgroups.into() {
+        Workgroups::Direct(num_wg) => unsafe {
+            builder.dispatch(num_wg)?;
+        },
+        Workgroups::Indirect(indirect_buffer) => unsafe {
+            builder.dispatch_indirect(indirect_buffer)?;
+        },
+    }
+    Ok(())
+}
+
+/// Same as [`bind_and_dispatch`], into a command buffer that vulkano does not
+/// synchronize: the caller records the barriers between dispatches (see
+/// [`ChainRecorder`](crate::runners::vulkano::chain_recorder::ChainRecorder))
+pub fn record_dispatch(
+    recorder: &mut RecordingCommandBuffer,
+    pipeline: &ComputePipeline,
+    descriptor_set: &DescriptorSet,
+    push_constants: Option<&[u32]>,
+    workgroups: impl Into<Workgroups>,
+) -> CrateResult<()> {
     unsafe {
-        builder.dispatch(num_wg)?;
+        recorder.bind_pipeline_compute(pipeline)?;
+        recorder.bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout(),
+            0,
+            &[descriptor_set.as_raw()],
+            &[],
+        )?;
+        if let Some(words) = push_constants {
+            recorder.push_constants(pipeline.layout(), 0, words)?;
+        }
+        match workgroups.into() {
+            Workgroups::Direct(num_wg) => recorder.dispatch(num_wg)?,
+            Workgroups::Indirect(indirect_buffer) => {
+                recorder.dispatch_indirect(&indirect_buffer)?
+            }
+        };
     }
     Ok(())
 }
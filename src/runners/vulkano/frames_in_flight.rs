@@ -0,0 +1,257 @@
+//! Submissions of a chain that the host has not waited for yet
+//!
+//! A chain with N frames in flight has N slots, used in turn by its submissions. A
+//! submission only waits for the one made N submissions earlier in the same slot, so
+//! the host can record, render and read back earlier frames while the GPU simulates.
+//!
+//! Each slot can also hold snapshots of some of the chain's buffers, copied at the end
+//! of every submission made in the slot. The chain updates its buffers in place, so
+//! these copies are what the renderer and the host read while later frames run.
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::sys::CommandBuffer,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::fence::Fence,
+};
+
+use crate::error::{ChimeraError, CrateResult};
+
+/// Frames a chain keeps in flight unless told otherwise
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Handle of one submission of a chain, to wait for it or check whether it finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Submission {
+    serial: u64,
+}
+
+impl Submission {
+    /// Submissions are numbered from 0 in the order they were made
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+}
+
+/// The last submission made in a slot
+struct InFlight {
+    submission: Submission,
+    fence: Arc<Fence>,
+    /// Nothing else keeps the resources of a `sys` command buffer alive until it ran
+    _command_buffer: Arc<CommandBuffer>,
+}
+
+/// The slots of a chain's frames in flight, with the last submission made in each
+pub struct FramesInFlight {
+    slots: Vec<Option<InFlight>>,
+    next_serial: u64,
+}
+
+impl FramesInFlight {
+    pub fn new(frames_in_flight: usize) -> CrateResult<Self> {
+        if frames_in_flight == 0 {
+            return Err(ChimeraError::ZeroFramesInFlight);
+        }
+        Ok(Self {
+            slots: (0..frames_in_flight).map(|_| None).collect(),
+            next_serial: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// The slot of the next submission, once the submission made in it
+    /// [`Self::len`] submissions ago has finished
+    pub fn acquire(&mut self) -> CrateResult<usize> {
+        let slot = (self.next_serial % self.slots.len() as u64) as usize;
+        if let Some(in_flight) = self.slots[slot].take() {
+            in_flight.fence.wait(None)?;
+        }
+        Ok(slot)
+    }
+
+    /// Record that `command_buffer` was submitted in `slot`, signaling `fence`
+    pub fn submitted(
+        &mut self,
+        slot: usize,
+        fence: Arc<Fence>,
+        command_buffer: Arc<CommandBuffer>,
+    ) -> Submission {
+        let submission = Submission {
+            serial: self.next_serial,
+        };
+        self.next_serial += 1;
+        self.slots[slot] = Some(InFlight {
+            submission,
+            fence,
+            _command_buffer: command_buffer,
+        });
+        submission
+    }
+
+    /// Whether `submission` finished; a submission whose slot was reused has
+    pub fn is_complete(&self, submission: Submission) -> CrateResult<bool> {
+        match self.in_flight(submission) {
+            Some(fence) => Ok(fence.is_signaled()?),
+            None => Ok(submission.serial < self.next_serial),
+        }
+    }
+
+    /// Whether the last submission made in `slot`, if any, finished
+    pub fn is_slot_complete(&self, slot: usize) -> CrateResult<bool> {
+        match &self.slots[slot] {
+            Some(in_flight) => Ok(in_flight.fence.is_signaled()?),
+            None => Ok(true),
+        }
+    }
+
+    /// Block until `submission` finished
+    pub fn wait(&self, submission: Submission) -> CrateResult<()> {
+        if let Some(fence) = self.in_flight(submission) {
+            fence.wait(None)?;
+        }
+        Ok(())
+    }
+
+    /// Block until every submission finished
+    pub fn wait_all(&self) -> CrateResult<()> {
+        for in_flight in self.slots.iter().flatten() {
+            in_flight.fence.wait(None)?;
+        }
+        Ok(())
+    }
+
+    /// Block until every submission finished, and stop tracking them, e.g. once the
+    /// snapshots they made are gone
+    pub fn retire_all(&mut self) -> CrateResult<()> {
+        self.wait_all()?;
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        Ok(())
+    }
+
+    /// The slot of the newest submission that finished, if any is still tracked
+    pub fn latest_complete(&self) -> CrateResult<Option<(Submission, usize)>> {
+        let mut latest: Option<(Submission, usize)> = None;
+        for (slot, in_flight) in self.in_flight_slots() {
+            if latest.is_some_and(|(newest, _)| newest > in_flight.submission) {
+                continue;
+            }
+            if in_flight.fence.is_signaled()? {
+                latest = Some((in_flight.submission, slot));
+            }
+        }
+        Ok(latest)
+    }
+
+    /// The slot of the newest submission that finished, waiting for the oldest one
+    /// still running if none did
+    pub fn wait_latest_complete(&self) -> CrateResult<Option<(Submission, usize)>> {
+        if let Some(latest) = self.latest_complete()? {
+            return Ok(Some(latest));
+        }
+        let oldest = self
+            .in_flight_slots()
+            .min_by_key(|(_, in_flight)| in_flight.submission);
+        match oldest {
+            Some((slot, in_flight)) => {
+                in_flight.fence.wait(None)?;
+                Ok(Some((in_flight.submission, slot)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn in_flight_slots(&self) -> impl Iterator<Item = (usize, &InFlight)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, in_flight)| in_flight.as_ref().map(|f| (slot, f)))
+    }
+
+    fn in_flight(&self, submission: Submission) -> Option<&Arc<Fence>> {
+        self.slots
+            .iter()
+            .flatten()
+            .find(|in_flight| in_flight.submission == submission)
+            .map(|in_flight| &in_flight.fence)
+    }
+}
+
+/// The command buffers and buffers of a chain must outlive its submissions
+impl Drop for FramesInFlight {
+    fn drop(&mut self) {
+        if let Err(e) = self.wait_all() {
+            eprintln!("Failed to wait for the frames in flight: {e}");
+        }
+    }
+}
+
+/// Copies of some of the chain's buffers, one set per frame in flight
+pub struct Snapshots {
+    /// By slot, then by buffer name
+    slots: Vec<HashMap<&'static str, Subbuffer<[u8]>>>,
+}
+
+impl Snapshots {
+    /// Allocate a copy of each of `buffers` (name and contents as bytes) for every slot
+    pub fn new(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        frames_in_flight: usize,
+        buffers: &[(&'static str, Subbuffer<[u8]>)],
+    ) -> CrateResult<Self> {
+        let slots = (0..frames_in_flight)
+            .map(|_| {
+                buffers
+                    .iter()
+                    .map(|(name, source)| {
+                        let snapshot = Buffer::new_slice::<u8>(
+                            memory_allocator.clone(),
+                            BufferCreateInfo {
+                                // read by the renderer as storage buffers
+                                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST,
+                                ..Default::default()
+                            },
+                            AllocationCreateInfo {
+                                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                                ..Default::default()
+                            },
+                            source.len(),
+                        )?;
+                        Ok((*name, snapshot))
+                    })
+                    .collect::<CrateResult<HashMap<_, _>>>()
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+        Ok(Self { slots })
+    }
+
+    /// The copies a submission in `slot` makes, as (source name, destination)
+    pub fn slot(&self, slot: usize) -> impl Iterator<Item = (&'static str, &Subbuffer<[u8]>)> {
+        self.slots[slot]
+            .iter()
+            .map(|(name, buffer)| (*name, buffer))
+    }
+
+    /// Names of the buffers it copies
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.slots
+            .first()
+            .into_iter()
+            .flat_map(|buffers| buffers.keys().copied())
+    }
+
+    /// The snapshot of buffer `name` in `slot`
+    pub fn get(&self, slot: usize, name: &str) -> CrateResult<&Subbuffer<[u8]>> {
+        self.slots[slot]
+            .get(name)
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(name.to_string()))
+    }
+}
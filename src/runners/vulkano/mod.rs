@@ -1,10 +1,17 @@
 pub mod buffer;
+pub mod buffer_registry;
 pub mod buffer_specs;
+pub mod chain_recorder;
+pub mod dependency_graph;
 pub mod descriptor_sets;
 pub mod device;
 pub mod dispatch;
+pub mod frames_in_flight;
 pub mod pipeline;
+pub mod profiling;
+pub mod reflection;
 pub mod shader;
 pub mod shader_buffer_mapping;
 pub mod shader_pipeline_builder;
+pub mod staging;
 pub mod typed_subbuffer_by_name;
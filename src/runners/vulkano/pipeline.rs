@@ -6,29 +6,34 @@ use vulkano::{
     device::Device,
     pipeline::{
         compute::{ComputePipeline, ComputePipelineCreateInfo},
-        layout::{PipelineLayout, PipelineLayoutCreateInfo},
+        layout::{PipelineLayout, PipelineLayoutCreateInfo, PushConstantRange},
         PipelineShaderStageCreateInfo,
     },
-    shader::EntryPoint,
+    shader::{EntryPoint, ShaderStages},
 };
 
 /// Build a compute pipeline for the given entry point and descriptor set layout
 /// A "pipeline" is for only one shader (not a sequence of shaders)
+/// `push_constant_size` is the size in bytes of the shader's push constants, if it has any.
 pub fn build_pipeline(
     device: Arc<Device>,
     descriptor_set_layout: Arc<DescriptorSetLayout>,
     entry_point: EntryPoint,
+    push_constant_size: Option<u32>,
 ) -> CrateResult<Arc<ComputePipeline>> {
     // Pipeline layout + push constants
     let pipeline_layout = PipelineLayout::new(
         device.clone(),
         PipelineLayoutCreateInfo {
             set_layouts: vec![descriptor_set_layout],
-            // push_constant_ranges: vec![PushConstantRange {
-            //     stages: ShaderStages::COMPUTE,
-            //     offset: 0,
-            //     size: std::mem::size_of::<BitonicPushConstants>() as u32,
-            // }],
+            push_constant_ranges: push_constant_size
+                .map(|size| PushConstantRange {
+                    stages: ShaderStages::COMPUTE,
+                    offset: 0,
+                    size,
+                })
+                .into_iter()
+                .collect(),
             ..Default::default()
         },
     )
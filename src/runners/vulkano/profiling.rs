@@ -0,0 +1,262 @@
+//! GPU time spent in each invocation of a chain
+//!
+//! With profiling on, the [`ChainRecorder`] writes a timestamp before and after every
+//! dispatch, into a query pool per frame in flight. Once a submission finished, the
+//! [`ChainProfiler`] reads them back and adds one sample per invocation (summed over
+//! the substeps) to a [`ChainProfile`], which keeps the last `window` samples.
+//!
+//! Invocations of the same batch (see
+//! [`DependencyGraph`](crate::runners::vulkano::dependency_graph::DependencyGraph)) run
+//! concurrently, so their timings overlap.
+//!
+//! [`ChainRecorder`]: crate::runners::vulkano::chain_recorder::ChainRecorder
+use std::{collections::VecDeque, fmt, sync::Arc, time::Duration};
+
+use vulkano::{
+    device::{Device, Queue},
+    query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+};
+
+use crate::error::CrateResult;
+
+/// Timings of one invocation over the samples of a [`ChainProfile`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvocationTimings {
+    pub samples: usize,
+    pub last: Duration,
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// GPU time spent in each invocation of a chain, over its last `window` submissions
+#[derive(Clone, Debug)]
+pub struct ChainProfile {
+    window: usize,
+    /// Invocation name and samples, oldest first, in chain order
+    invocations: Vec<(&'static str, VecDeque<Duration>)>,
+}
+
+impl ChainProfile {
+    pub fn new(invocation_names: impl IntoIterator<Item = &'static str>, window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            invocations: invocation_names
+                .into_iter()
+                .map(|name| (name, VecDeque::new()))
+                .collect(),
+        }
+    }
+
+    /// Add a sample for the invocation at `index` in the chain, dropping the oldest
+    /// one if the window is full
+    pub fn record(&mut self, index: usize, duration: Duration) {
+        let samples = &mut self.invocations[index].1;
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(duration);
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Timings of the first invocation named `invocation_name`, if it has samples
+    pub fn timings(&self, invocation_name: &str) -> Option<InvocationTimings> {
+        self.invocations
+            .iter()
+            .find(|(name, _)| *name == invocation_name)
+            .and_then(|(_, samples)| timings(samples))
+    }
+
+    /// Timings of every invocation with samples, in chain order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, InvocationTimings)> + '_ {
+        self.invocations
+            .iter()
+            .filter_map(|(name, samples)| Some((*name, timings(samples)?)))
+    }
+
+    /// One line per invocation, times in microseconds
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("invocation,samples,last_us,mean_us,min_us,max_us\n");
+        for (name, t) in self.iter() {
+            csv.push_str(&format!(
+                "{name},{},{:.3},{:.3},{:.3},{:.3}\n",
+                t.samples,
+                micros(t.last),
+                micros(t.mean),
+                micros(t.min),
+                micros(t.max)
+            ));
+        }
+        csv
+    }
+}
+
+fn timings(samples: &VecDeque<Duration>) -> Option<InvocationTimings> {
+    let last = *samples.back()?;
+    let total: Duration = samples.iter().sum();
+    Some(InvocationTimings {
+        samples: samples.len(),
+        last,
+        mean: total / samples.len() as u32,
+        min: *samples.iter().min()?,
+        max: *samples.iter().max()?,
+    })
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
+
+/// A table of the timings, times in microseconds
+impl fmt::Display for ChainProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .invocations
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("invocation".len());
+        writeln!(
+            f,
+            "{:name_width$}  {:>7}  {:>10}  {:>10}  {:>10}  {:>10}",
+            "invocation", "samples", "last µs", "mean µs", "min µs", "max µs"
+        )?;
+        for (name, t) in self.iter() {
+            writeln!(
+                f,
+                "{name:name_width$}  {:>7}  {:>10.3}  {:>10.3}  {:>10.3}  {:>10.3}",
+                t.samples,
+                micros(t.last),
+                micros(t.mean),
+                micros(t.min),
+                micros(t.max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The timestamp query pools of a chain, one per frame in flight, and the profile
+/// their results go to
+pub struct ChainProfiler {
+    device: Arc<Device>,
+    pools: Vec<Arc<QueryPool>>,
+    /// Whether the last submission in each slot wrote timestamps not read yet
+    pending: Vec<bool>,
+    substeps: u32,
+    /// Nanoseconds per timestamp tick
+    period: f64,
+    /// Timestamps only have this many valid bits, and wrap around
+    valid_bits_mask: u64,
+    profile: ChainProfile,
+}
+
+impl ChainProfiler {
+    /// A profiler for `profile`'s invocations, or `None` if `queue` can't write
+    /// timestamps
+    pub fn new(
+        device: Arc<Device>,
+        queue: &Queue,
+        frames_in_flight: usize,
+        substeps: u32,
+        profile: ChainProfile,
+    ) -> CrateResult<Option<Self>> {
+        let physical_device = device.physical_device();
+        let valid_bits = physical_device.queue_family_properties()
+            [queue.queue_family_index() as usize]
+            .timestamp_valid_bits;
+        let valid_bits_mask = match valid_bits {
+            Some(bits) if bits >= 64 => u64::MAX,
+            Some(bits) if bits > 0 => (1u64 << bits) - 1,
+            _ => return Ok(None),
+        };
+        let period = physical_device.properties().timestamp_period as f64;
+
+        let mut profiler = Self {
+            device,
+            pools: Vec::new(),
+            pending: Vec::new(),
+            substeps: 0,
+            period,
+            valid_bits_mask,
+            profile,
+        };
+        profiler.reallocate(frames_in_flight, substeps)?;
+        Ok(Some(profiler))
+    }
+
+    /// Query pools for `frames_in_flight` slots of `substeps` runs of the chain,
+    /// unless the current ones already fit; pending results are lost
+    pub fn reallocate(&mut self, frames_in_flight: usize, substeps: u32) -> CrateResult<()> {
+        if self.pools.len() == frames_in_flight && self.substeps == substeps {
+            return Ok(());
+        }
+        let query_count = 2 * self.profile.invocations.len() as u32 * substeps;
+        self.pools = (0..frames_in_flight)
+            .map(|_| {
+                QueryPool::new(
+                    self.device.clone(),
+                    QueryPoolCreateInfo {
+                        query_count,
+                        ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                    },
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        self.pending = vec![false; frames_in_flight];
+        self.substeps = substeps;
+        Ok(())
+    }
+
+    /// Number of frames in flight it has query pools for
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// The pool the submission in `slot` writes its timestamps to: a start and an end
+    /// query per invocation, for every substep in order
+    pub fn pool(&self, slot: usize) -> &Arc<QueryPool> {
+        &self.pools[slot]
+    }
+
+    /// Record that a submission writing timestamps was made in `slot`
+    pub fn submitted(&mut self, slot: usize) {
+        self.pending[slot] = true;
+    }
+
+    /// Add the timings of the submission in `slot` to the profile; it must have
+    /// finished
+    pub fn collect(&mut self, slot: usize) -> CrateResult<()> {
+        if !std::mem::take(&mut self.pending[slot]) {
+            return Ok(());
+        }
+        let pool = &self.pools[slot];
+        let mut ticks = vec![0u64; pool.query_count() as usize];
+        pool.get_results(0..pool.query_count(), &mut ticks, QueryResultFlags::WAIT)?;
+
+        let n_invocations = self.profile.invocations.len();
+        for index in 0..n_invocations {
+            let total_ticks: u64 = (0..self.substeps as usize)
+                .map(|substep| {
+                    let start = 2 * (substep * n_invocations + index);
+                    ticks[start + 1].wrapping_sub(ticks[start]) & self.valid_bits_mask
+                })
+                .sum();
+            let nanos = (total_ticks as f64 * self.period) as u64;
+            self.profile.record(index, Duration::from_nanos(nanos));
+        }
+        Ok(())
+    }
+
+    pub fn profile(&self) -> &ChainProfile {
+        &self.profile
+    }
+}
@@ -0,0 +1,439 @@
+//! Minimal SPIR-V reflection for the compute entry points in `OTHER_SHADERS_SPIRV`
+//!
+//! Only what is needed to check `kernel(...)` configs and `buf_spec`s against the
+//! shader: the storage buffers each entry point uses, with their descriptor set,
+//! binding and element stride, and the workgroup size it is compiled with.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
+
+use crate::error::{ChimeraError, CrateResult};
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+// opcodes
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+
+// decorations
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+
+// execution modes
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+/// A storage buffer used by an entry point
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageBufferBinding {
+    pub descriptor_set: u32,
+    pub binding: u32,
+    /// Name of the shader parameter, if the module has debug names
+    pub name: Option<String>,
+    /// `ArrayStride` of the elements for slice buffers (`&[T]`, `&mut [T]`), which is
+    /// `size_of::<T>()` with scalar block layout. `None` for buffers that hold a single value.
+    pub element_stride: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntryPointReflection {
+    pub name: String,
+    /// Sorted by descriptor set, then binding
+    pub storage_buffers: Vec<StorageBufferBinding>,
+    /// Whether the entry point has a `#[spirv(push_constant)]` parameter
+    pub has_push_constants: bool,
+    /// Workgroup size from `#[spirv(compute(threads(...)))]`
+    pub local_size: Option<[u32; 3]>,
+}
+
+impl EntryPointReflection {
+    /// The storage buffer at `binding` in descriptor set 0, the only set the compute chains bind.
+    pub fn storage_buffer(&self, binding: u32) -> Option<&StorageBufferBinding> {
+        self.storage_buffers
+            .iter()
+            .find(|b| b.descriptor_set == 0 && b.binding == binding)
+    }
+
+    /// The storage buffer declared by the shader parameter `name`
+    pub fn storage_buffer_by_name(&self, name: &str) -> Option<&StorageBufferBinding> {
+        self.storage_buffers
+            .iter()
+            .find(|b| b.name.as_deref() == Some(name))
+    }
+
+    pub fn binding_nums(&self) -> Vec<u32> {
+        self.storage_buffers.iter().map(|b| b.binding).collect()
+    }
+
+    /// The workgroup size, or [`ChimeraError::MissingLocalSize`]
+    pub fn local_size(&self) -> CrateResult<[u32; 3]> {
+        self.local_size
+            .ok_or_else(|| ChimeraError::MissingLocalSize(self.name.clone()))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    entry_points: Vec<EntryPointReflection>,
+}
+
+impl ShaderReflection {
+    pub fn from_bytes(bytes: &[u8]) -> CrateResult<Self> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(ChimeraError::SpirvReflection(format!(
+                "module is {} bytes, not a multiple of 4",
+                bytes.len()
+            )));
+        }
+        let words = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect::<Vec<_>>();
+        Self::from_words(&words)
+    }
+
+    pub fn from_words(words: &[u32]) -> CrateResult<Self> {
+        if words.len() < HEADER_WORDS || words[0] != MAGIC {
+            return Err(ChimeraError::SpirvReflection(
+                "missing SPIR-V header".to_string(),
+            ));
+        }
+
+        let mut entry_points = Vec::new();
+        let mut names = HashMap::new();
+        let mut decorations: HashMap<u32, HashMap<u32, u32>> = HashMap::new();
+        let mut runtime_arrays = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut pointers = HashMap::new();
+        let mut storage_buffer_vars = HashMap::new();
+        let mut push_constant_vars = HashSet::new();
+        let mut local_sizes = HashMap::new();
+
+        let mut i = HEADER_WORDS;
+        while i < words.len() {
+            let word_count = (words[i] >> 16) as usize;
+            let opcode = words[i] & 0xffff;
+            if word_count == 0 || i + word_count > words.len() {
+                return Err(ChimeraError::SpirvReflection(format!(
+                    "truncated instruction at word {i}"
+                )));
+            }
+            let operands = &words[i + 1..i + word_count];
+            i += word_count;
+
+            match opcode {
+                OP_ENTRY_POINT if operands.len() >= 2 => {
+                    let (name, name_words) = parse_string(&operands[2..]);
+                    entry_points.push((operands[1], name, operands[2 + name_words..].to_vec()));
+                }
+                OP_EXECUTION_MODE
+                    if operands.len() >= 5 && operands[1] == EXECUTION_MODE_LOCAL_SIZE =>
+                {
+                    local_sizes.insert(operands[0], [operands[2], operands[3], operands[4]]);
+                }
+                OP_NAME if !operands.is_empty() => {
+                    names.insert(operands[0], parse_string(&operands[1..]).0);
+                }
+                OP_DECORATE if operands.len() >= 3 => {
+                    decorations
+                        .entry(operands[0])
+                        .or_default()
+                        .insert(operands[1], operands[2]);
+                }
+                OP_TYPE_RUNTIME_ARRAY if operands.len() >= 2 => {
+                    runtime_arrays.insert(operands[0], operands[1]);
+                }
+                OP_TYPE_STRUCT if !operands.is_empty() => {
+                    structs.insert(operands[0], operands[1..].to_vec());
+                }
+                OP_TYPE_POINTER if operands.len() >= 3 => {
+                    pointers.insert(operands[0], operands[2]);
+                }
+                OP_VARIABLE
+                    if operands.len() >= 3 && operands[2] == STORAGE_CLASS_STORAGE_BUFFER =>
+                {
+                    storage_buffer_vars.insert(operands[1], operands[0]);
+                }
+                OP_VARIABLE
+                    if operands.len() >= 3 && operands[2] == STORAGE_CLASS_PUSH_CONSTANT =>
+                {
+                    push_constant_vars.insert(operands[1]);
+                }
+                _ => {}
+            }
+        }
+
+        let decoration = |id: u32, decoration: u32| {
+            decorations
+                .get(&id)
+                .and_then(|d| d.get(&decoration))
+                .copied()
+        };
+
+        // stride of a runtime array, either directly or as the only member of a block struct
+        let element_stride = |pointee: u32| {
+            let array = match structs.get(&pointee) {
+                Some(members) if members.len() == 1 => members[0],
+                Some(_) => return None,
+                None => pointee,
+            };
+            runtime_arrays.get(&array)?;
+            decoration(array, DECORATION_ARRAY_STRIDE)
+        };
+
+        let entry_points = entry_points
+            .into_iter()
+            .map(|(id, name, interface)| {
+                // Since SPIR-V 1.4 the interface lists every global variable the entry
+                // point uses, storage buffers included.
+                let mut storage_buffers = interface
+                    .iter()
+                    .filter_map(|var| {
+                        let pointer_type = storage_buffer_vars.get(var)?;
+                        let binding = decoration(*var, DECORATION_BINDING);
+                        let descriptor_set = decoration(*var, DECORATION_DESCRIPTOR_SET);
+                        let element_stride = pointers
+                            .get(pointer_type)
+                            .and_then(|pointee| element_stride(*pointee));
+                        Some(
+                            binding
+                                .zip(descriptor_set)
+                                .map(|(binding, descriptor_set)| StorageBufferBinding {
+                                    descriptor_set,
+                                    binding,
+                                    name: names.get(var).cloned(),
+                                    element_stride,
+                                })
+                                .ok_or_else(|| {
+                                    ChimeraError::SpirvReflection(format!(
+                                        "storage buffer %{var} of entry point `{name}` has no binding"
+                                    ))
+                                }),
+                        )
+                    })
+                    .collect::<CrateResult<Vec<_>>>()?;
+                storage_buffers.sort_by_key(|b| (b.descriptor_set, b.binding));
+                let has_push_constants =
+                    interface.iter().any(|var| push_constant_vars.contains(var));
+                Ok(EntryPointReflection {
+                    name,
+                    storage_buffers,
+                    has_push_constants,
+                    local_size: local_sizes.get(&id).copied(),
+                })
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        Ok(Self { entry_points })
+    }
+
+    pub fn entry_points(&self) -> &[EntryPointReflection] {
+        &self.entry_points
+    }
+
+    pub fn entry_point(&self, name: &str) -> CrateResult<&EntryPointReflection> {
+        self.entry_points
+            .iter()
+            .find(|e| e.name == name)
+            .ok_or_else(|| ChimeraError::KernelNotFound(name.to_string()))
+    }
+}
+
+/// Reflection of the embedded `OTHER_SHADERS_SPIRV` module, parsed on first use
+pub fn shaders_reflection() -> CrateResult<&'static ShaderReflection> {
+    static REFLECTION: OnceLock<ShaderReflection> = OnceLock::new();
+    REFLECTION.get_or_try_init(|| ShaderReflection::from_bytes(crate::OTHER_SHADERS_SPIRV))
+}
+
+/// Parse a nul-terminated SPIR-V literal string, returning it and the number of words it used.
+fn parse_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(s.len() / 4 * 4 + 4, 0);
+        bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect()
+    }
+
+    fn inst(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    /// `adder`-like module: `a: &mut [u32]` at binding 0, `b: &[Vec2]` at binding 3 and
+    /// push constants
+    fn module() -> Vec<u32> {
+        let (u32_ty, vec2_ty) = (1, 2);
+        let (pc_block, pc_ptr, pc_var) = (31, 32, 33);
+        let (arr_a, block_a, ptr_a, var_a) = (10, 11, 12, 13);
+        let (arr_b, block_b, ptr_b, var_b) = (20, 21, 22, 23);
+        let unused_var = 30;
+        let main = 40;
+
+        let mut words = vec![MAGIC, 0x0001_0600, 0, 100, 0];
+        words.extend(inst(
+            OP_ENTRY_POINT,
+            &[
+                [5].as_slice(),
+                &[main],
+                &string("adder"),
+                &[var_a, var_b, pc_var],
+            ]
+            .concat(),
+        ));
+        words.extend(inst(
+            OP_EXECUTION_MODE,
+            &[main, EXECUTION_MODE_LOCAL_SIZE, 64, 1, 1],
+        ));
+        words.extend(inst(OP_NAME, &[[var_a].as_slice(), &string("a")].concat()));
+        words.extend(inst(OP_DECORATE, &[arr_a, DECORATION_ARRAY_STRIDE, 4]));
+        words.extend(inst(OP_DECORATE, &[var_a, DECORATION_DESCRIPTOR_SET, 0]));
+        words.extend(inst(OP_DECORATE, &[var_a, DECORATION_BINDING, 0]));
+        words.extend(inst(OP_DECORATE, &[arr_b, DECORATION_ARRAY_STRIDE, 8]));
+        words.extend(inst(OP_DECORATE, &[var_b, DECORATION_DESCRIPTOR_SET, 0]));
+        words.extend(inst(OP_DECORATE, &[var_b, DECORATION_BINDING, 3]));
+        words.extend(inst(
+            OP_DECORATE,
+            &[unused_var, DECORATION_DESCRIPTOR_SET, 0],
+        ));
+        words.extend(inst(OP_DECORATE, &[unused_var, DECORATION_BINDING, 7]));
+        words.extend(inst(OP_TYPE_RUNTIME_ARRAY, &[arr_a, u32_ty]));
+        words.extend(inst(OP_TYPE_STRUCT, &[block_a, arr_a]));
+        words.extend(inst(
+            OP_TYPE_POINTER,
+            &[ptr_a, STORAGE_CLASS_STORAGE_BUFFER, block_a],
+        ));
+        words.extend(inst(OP_TYPE_RUNTIME_ARRAY, &[arr_b, vec2_ty]));
+        words.extend(inst(OP_TYPE_STRUCT, &[block_b, arr_b]));
+        words.extend(inst(
+            OP_TYPE_POINTER,
+            &[ptr_b, STORAGE_CLASS_STORAGE_BUFFER, block_b],
+        ));
+        words.extend(inst(
+            OP_VARIABLE,
+            &[ptr_b, var_b, STORAGE_CLASS_STORAGE_BUFFER],
+        ));
+        words.extend(inst(
+            OP_VARIABLE,
+            &[ptr_a, var_a, STORAGE_CLASS_STORAGE_BUFFER],
+        ));
+        words.extend(inst(
+            OP_VARIABLE,
+            &[ptr_a, unused_var, STORAGE_CLASS_STORAGE_BUFFER],
+        ));
+        words.extend(inst(OP_TYPE_STRUCT, &[pc_block, u32_ty]));
+        words.extend(inst(
+            OP_TYPE_POINTER,
+            &[pc_ptr, STORAGE_CLASS_PUSH_CONSTANT, pc_block],
+        ));
+        words.extend(inst(
+            OP_VARIABLE,
+            &[pc_ptr, pc_var, STORAGE_CLASS_PUSH_CONSTANT],
+        ));
+        words
+    }
+
+    #[test]
+    fn test_reflects_storage_buffers_of_entry_point() {
+        let reflection = ShaderReflection::from_words(&module()).unwrap();
+        let adder = reflection.entry_point("adder").unwrap();
+
+        assert_eq!(adder.binding_nums(), vec![0, 3]);
+        assert_eq!(
+            adder.storage_buffer(0),
+            Some(&StorageBufferBinding {
+                descriptor_set: 0,
+                binding: 0,
+                name: Some("a".to_string()),
+                element_stride: Some(4),
+            })
+        );
+        assert_eq!(adder.storage_buffer(3).unwrap().element_stride, Some(8));
+        assert_eq!(adder.storage_buffer(3).unwrap().name, None);
+        assert!(adder.storage_buffer(7).is_none());
+        assert!(adder.has_push_constants);
+        assert_eq!(adder.local_size, Some([64, 1, 1]));
+    }
+
+    #[test]
+    fn test_missing_local_size() {
+        let mut words = vec![MAGIC, 0x0001_0600, 0, 100, 0];
+        words.extend(inst(
+            OP_ENTRY_POINT,
+            &[[5, 1].as_slice(), &string("no_threads")].concat(),
+        ));
+        let reflection = ShaderReflection::from_words(&words).unwrap();
+        let entry_point = reflection.entry_point("no_threads").unwrap();
+
+        assert_eq!(entry_point.local_size, None);
+        assert!(matches!(
+            entry_point.local_size(),
+            Err(ChimeraError::MissingLocalSize(name)) if name == "no_threads"
+        ));
+    }
+
+    #[test]
+    fn test_from_bytes_matches_from_words() {
+        let bytes = module()
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect::<Vec<_>>();
+        let reflection = ShaderReflection::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            reflection.entry_point("adder").unwrap().binding_nums(),
+            vec![0, 3]
+        );
+    }
+
+    #[test]
+    fn test_unknown_entry_point() {
+        let reflection = ShaderReflection::from_words(&module()).unwrap();
+        assert!(matches!(
+            reflection.entry_point("nope"),
+            Err(ChimeraError::KernelNotFound(name)) if name == "nope"
+        ));
+    }
+
+    #[test]
+    fn test_rejects_malformed_modules() {
+        assert!(ShaderReflection::from_words(&[1, 2, 3]).is_err());
+        assert!(ShaderReflection::from_bytes(&[0; 7]).is_err());
+
+        let mut truncated = module();
+        truncated.truncate(truncated.len() - 1);
+        assert!(matches!(
+            ShaderReflection::from_words(&truncated),
+            Err(ChimeraError::SpirvReflection(_))
+        ));
+    }
+}
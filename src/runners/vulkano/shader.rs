@@ -9,7 +9,14 @@ use vulkano::{
 
 pub fn shader_module(device: Arc<Device>) -> CrateResult<Arc<ShaderModule>> {
     // Create shader module from embedded SPIR-V
-    let kernel_bytes = crate::OTHER_SHADERS_SPIRV;
+    spirv_shader_module(device, crate::OTHER_SHADERS_SPIRV)
+}
+
+/// Create a shader module from the SPIR-V binary `kernel_bytes`
+pub fn spirv_shader_module(
+    device: Arc<Device>,
+    kernel_bytes: &[u8],
+) -> CrateResult<Arc<ShaderModule>> {
     // Convert SPIR-V bytes to words then create shader module
     let words = vulkano::shader::spirv::bytes_to_words(kernel_bytes)?;
     let shader_module = unsafe {
@@ -270,6 +270,7 @@ impl ShaderPipelineInfosWithDescriptorSetLayouts {
                     device.clone(),
                     pipeline_info.descriptor_set_layout.clone(),
                     pipeline_info.entry_point.clone(),
+                    None,
                 )
                 .inspect_err(|e| {
                     println!(
@@ -348,6 +349,7 @@ impl ShaderPipelineInfosWithDescriptorSets {
                 builder,
                 pipeline_info.pipeline.clone(),
                 pipeline_info.descriptor_set.clone(),
+                None,
                 [num_wg, 1, 1],
             )?;
         }
@@ -1,7 +1,11 @@
 use std::{collections::BTreeMap, sync::Arc};
 
+use bytemuck::Pod;
+
 use vulkano::{
-    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    command_buffer::{
+        sys::RecordingCommandBuffer, AutoCommandBufferBuilder, PrimaryAutoCommandBuffer,
+    },
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator,
         layout::{
@@ -16,28 +20,267 @@ use vulkano::{
 };
 
 use crate::{
-    error::CrateResult,
+    error::{ChimeraError, CrateResult},
     runners::vulkano::{
-        buffer_specs::DescriptorSetByName, dispatch::bind_and_dispatch, pipeline::build_pipeline,
+        buffer_specs::DescriptorSetByName,
+        dispatch::{bind_and_dispatch, record_dispatch, Workgroups},
+        pipeline::build_pipeline,
+        reflection::{shaders_reflection, ShaderReflection},
         shader::shader_entry_point,
+        typed_subbuffer_by_name::TypedSubbufferByName,
     },
 };
 
+/// Push constants of a kernel, as the 32-bit words pushed to the shader
+#[derive(Clone)]
+pub enum PushConstantSource {
+    /// The same value on every execution of the chain
+    Value(Vec<u32>),
+    /// A value computed from the number of elements of the buffer `buf_name`, which the
+    /// invocation must bind, when the chain is built and whenever the buffer is resized
+    OverBuffer {
+        buf_name: &'static str,
+        size: u32,
+        f: Arc<dyn Fn(u32) -> Vec<u32> + Send + Sync>,
+    },
+    /// A value produced anew for every execution of the chain
+    PerExecution {
+        size: u32,
+        f: Arc<dyn Fn() -> Vec<u32> + Send + Sync>,
+    },
+}
+
+impl PushConstantSource {
+    /// Size in bytes of the push constant range
+    pub fn size(&self) -> u32 {
+        match self {
+            PushConstantSource::Value(words) => 4 * words.len() as u32,
+            PushConstantSource::OverBuffer { size, .. }
+            | PushConstantSource::PerExecution { size, .. } => *size,
+        }
+    }
+
+    /// The words pushed on this execution; [`PushConstantSource::OverBuffer`] must be
+    /// [resolved](Self::resolved) first
+    pub fn words(&self) -> Vec<u32> {
+        match self {
+            PushConstantSource::Value(words) => words.clone(),
+            PushConstantSource::OverBuffer { .. } => {
+                unreachable!("push constants over a buffer are resolved when the chain is built")
+            }
+            PushConstantSource::PerExecution { f, .. } => f(),
+        }
+    }
+
+    /// The same push constants, with [`PushConstantSource::OverBuffer`] evaluated for
+    /// the length `buffer_len` returns
+    pub fn resolved(
+        &self,
+        buffer_len: impl Fn(&str) -> CrateResult<usize>,
+    ) -> CrateResult<PushConstantSource> {
+        Ok(match self {
+            PushConstantSource::OverBuffer { buf_name, f, .. } => {
+                PushConstantSource::Value(f(buffer_len(buf_name)? as u32))
+            }
+            _ => self.clone(),
+        })
+    }
+
+    pub fn is_per_execution(&self) -> bool {
+        matches!(self, PushConstantSource::PerExecution { .. })
+    }
+}
+
+/// `value` as 32-bit words, zero padded to a multiple of 4 bytes as Vulkan requires
+pub(crate) fn pod_to_words<T: Pod>(value: &T) -> Vec<u32> {
+    let bytes = bytemuck::bytes_of(value);
+    let mut words = vec![0u32; bytes.len().div_ceil(4)];
+    bytemuck::cast_slice_mut::<u32, u8>(&mut words)[..bytes.len()].copy_from_slice(bytes);
+    words
+}
+
+/// How an invocation accesses one of its buffers
+///
+/// Chains order invocations by these annotations: an invocation only waits for the
+/// earlier ones it conflicts with (see
+/// [`DependencyGraph`](crate::runners::vulkano::dependency_graph::DependencyGraph)).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BufferAccess {
+    /// Only read
+    Read,
+    /// Only written, without reading the previous contents (e.g. `clear_grid`)
+    Write,
+    /// Read and written; the default for buffers without an annotation
+    #[default]
+    ReadWrite,
+    /// Only updated with atomic read-modify-writes (e.g. `p2g` scattering into `grid`).
+    /// Atomic accesses by different invocations commute, so they are not ordered.
+    Atomic,
+}
+
+impl BufferAccess {
+    pub fn reads(self) -> bool {
+        !matches!(self, BufferAccess::Write)
+    }
+
+    pub fn writes(self) -> bool {
+        !matches!(self, BufferAccess::Read)
+    }
+}
+
+/// How many workgroups a kernel dispatches
+///
+/// Only [`DispatchSize::Workgroups`] is a workgroup count. `OverBuffer` and `Grid2d`
+/// count invocations, and are divided by the entry point's reflected `LocalSize` when
+/// the chain is built, so they follow the actual lengths of the chain's buffers.
+/// `Indirect` is only known when the dispatch runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DispatchSize {
+    /// Exactly this many workgroups
+    Workgroups([u32; 3]),
+    /// One invocation per element of the buffer named `buf_name`, which the invocation
+    /// must bind
+    OverBuffer(&'static str),
+    /// One invocation per cell of a `width` × `height` grid
+    Grid2d { width: u32, height: u32 },
+    /// The workgroup count in the first [`shared::DispatchIndirectCommand`] of the
+    /// buffer `buf_name`, as an earlier invocation of the chain left it
+    Indirect(&'static str),
+}
+
+impl From<[u32; 3]> for DispatchSize {
+    fn from(num_workgroups: [u32; 3]) -> Self {
+        DispatchSize::Workgroups(num_workgroups)
+    }
+}
+
+impl DispatchSize {
+    /// The workgroup count for an entry point with `local_size`, with `buffer_len`
+    /// returning the number of elements of a chain buffer; `None` for indirect dispatches
+    pub fn num_workgroups(
+        &self,
+        local_size: [u32; 3],
+        buffer_len: impl Fn(&str) -> CrateResult<usize>,
+    ) -> CrateResult<Option<[u32; 3]>> {
+        Ok(match *self {
+            DispatchSize::Workgroups(num_workgroups) => Some(num_workgroups),
+            DispatchSize::OverBuffer(buf_name) => {
+                let len = buffer_len(buf_name)?;
+                Some([len.div_ceil(local_size[0] as usize) as u32, 1, 1])
+            }
+            DispatchSize::Grid2d { width, height } => Some([
+                width.div_ceil(local_size[0]),
+                height.div_ceil(local_size[1]),
+                1,
+            ]),
+            DispatchSize::Indirect(_) => None,
+        })
+    }
+}
+
+/// Dispatch one invocation per element of the buffer `buf_name`
+pub fn over_buffer(buf_name: &'static str) -> DispatchSize {
+    DispatchSize::OverBuffer(buf_name)
+}
+
+/// Dispatch one invocation per cell of a `width` × `height` grid
+pub fn over_grid_2d(width: u32, height: u32) -> DispatchSize {
+    DispatchSize::Grid2d { width, height }
+}
+
 #[derive(Clone)]
 pub struct KernelConfig {
     entry_point_name: &'static str,
     binding_nums_in_shader: Vec<u32>,
-    num_workgroups: [u32; 3],
+    dispatch_size: DispatchSize,
+    push_constants: Option<PushConstantSource>,
 }
+
+/// `dispatch_size` is either a workgroup count (`[u32; 3]`), or one of
+/// [`over_buffer`] and [`over_grid_2d`].
 pub fn kernel(
     entry_point_name: &'static str,
     binding_nums_in_shader: Vec<u32>,
-    num_workgroups: [u32; 3],
+    dispatch_size: impl Into<DispatchSize>,
 ) -> KernelConfig {
     KernelConfig {
         entry_point_name,
         binding_nums_in_shader,
-        num_workgroups,
+        dispatch_size: dispatch_size.into(),
+        push_constants: None,
+    }
+}
+
+/// Same as [`kernel`], with the binding numbers read from the entry point's storage
+/// buffers in the compiled shader, in ascending order.
+pub fn inferred_kernel(
+    entry_point_name: &'static str,
+    dispatch_size: impl Into<DispatchSize>,
+) -> CrateResult<KernelConfig> {
+    let binding_nums_in_shader = shaders_reflection()?
+        .entry_point(entry_point_name)?
+        .binding_nums();
+    Ok(kernel(
+        entry_point_name,
+        binding_nums_in_shader,
+        dispatch_size,
+    ))
+}
+
+impl KernelConfig {
+    pub fn entry_point_name(&self) -> &'static str {
+        self.entry_point_name
+    }
+
+    pub fn binding_nums_in_shader(&self) -> &Vec<u32> {
+        &self.binding_nums_in_shader
+    }
+
+    pub fn dispatch_size(&self) -> &DispatchSize {
+        &self.dispatch_size
+    }
+
+    /// Push `value` to the shader's `#[spirv(push_constant)]` parameter on every execution.
+    pub fn with_push_constants<T: Pod>(self, value: T) -> Self {
+        KernelConfig {
+            push_constants: Some(PushConstantSource::Value(pod_to_words(&value))),
+            ..self
+        }
+    }
+
+    /// Push the value `f` returns for the number of elements of the buffer `buf_name`,
+    /// which the invocation must bind, e.g. a `num_elements` field; called again when
+    /// the buffer is resized.
+    pub fn with_push_constants_over_buffer<T: Pod>(
+        self,
+        buf_name: &'static str,
+        f: impl Fn(u32) -> T + Send + Sync + 'static,
+    ) -> Self {
+        let size = 4 * pod_to_words(&T::zeroed()).len() as u32;
+        KernelConfig {
+            push_constants: Some(PushConstantSource::OverBuffer {
+                buf_name,
+                size,
+                f: Arc::new(move |len| pod_to_words(&f(len))),
+            }),
+            ..self
+        }
+    }
+
+    /// Push the value returned by `f`, called again on every execution of the chain.
+    pub fn with_push_constants_fn<T: Pod>(self, f: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        let size = 4 * pod_to_words(&T::zeroed()).len() as u32;
+        KernelConfig {
+            push_constants: Some(PushConstantSource::PerExecution {
+                size,
+                f: Arc::new(move || pod_to_words(&f())),
+            }),
+            ..self
+        }
+    }
+
+    pub fn push_constants(&self) -> Option<&PushConstantSource> {
+        self.push_constants.as_ref()
     }
 }
 
@@ -47,6 +290,8 @@ pub struct ShaderPipelineSpec {
     invocation_name: &'static str,
     buf_names: Vec<&'static str>,
     kernel_config: KernelConfig,
+    /// Access annotations, by buffer name; unannotated buffers are `ReadWrite`
+    accesses: Vec<(&'static str, BufferAccess)>,
 }
 
 impl ShaderPipelineSpec {
@@ -65,31 +310,281 @@ impl ShaderPipelineSpec {
         &self.buf_names
     }
 
-    /// Validate that all buffer names in this pipeline spec exist in the given buffer specs.
-    /// NOTE: this does not check that the invocation lists all the buffers it needs, only that the ones it
-    /// lists do exist.
+    pub fn kernel_config(&self) -> &KernelConfig {
+        &self.kernel_config
+    }
+
+    /// Annotate how the invocation accesses the buffer `buf_name`
+    ///
+    /// ```ignore
+    /// invoc_spec("p2g", vec!["x", "v", "grid"], p2g_kernel)
+    ///     .with_access("x", BufferAccess::Read)
+    ///     .with_access("v", BufferAccess::Read)
+    ///     .with_access("grid", BufferAccess::Atomic)
+    /// ```
+    pub fn with_access(mut self, buf_name: &'static str, access: BufferAccess) -> Self {
+        self.accesses.retain(|(name, _)| *name != buf_name);
+        self.accesses.push((buf_name, access));
+        self
+    }
+
+    /// How the invocation accesses the buffer `buf_name`
+    pub fn access(&self, buf_name: &str) -> BufferAccess {
+        self.accesses
+            .iter()
+            .find(|(name, _)| *name == buf_name)
+            .map(|(_, access)| *access)
+            .unwrap_or_default()
+    }
+
+    /// The distinct buffers of the invocation, in order, with their access; last the
+    /// buffer an indirect dispatch reads its workgroup count from, if it is not bound
+    pub fn buffer_accesses(&self) -> Vec<(&'static str, BufferAccess)> {
+        let mut accesses: Vec<(&'static str, BufferAccess)> = Vec::new();
+        for &buf_name in &self.buf_names {
+            if !accesses.iter().any(|(name, _)| *name == buf_name) {
+                accesses.push((buf_name, self.access(buf_name)));
+            }
+        }
+        if let Some(buf_name) = self.indirect_buf_name() {
+            if !self.buf_names.contains(&buf_name) {
+                accesses.push((buf_name, BufferAccess::Read));
+            }
+        }
+        accesses
+    }
+
+    /// The buffer the invocation reads its workgroup count from, for indirect dispatches
+    pub fn indirect_buf_name(&self) -> Option<&'static str> {
+        match self.kernel_config.dispatch_size {
+            DispatchSize::Indirect(buf_name) => Some(buf_name),
+            _ => None,
+        }
+    }
+
+    /// Name of the buffer bound to `binding` in this invocation, if the kernel uses that binding.
+    pub fn buf_name_for_binding(&self, binding: u32) -> Option<&'static str> {
+        self.kernel_config
+            .binding_nums_in_shader
+            .iter()
+            .position(|&b| b == binding)
+            .and_then(|i| self.buf_names.get(i).copied())
+    }
+
+    /// Validate that all buffer names in this pipeline spec exist in the given buffer specs,
+    /// and that the bindings and buffer element sizes match the shader entry point.
     pub fn validate_against_buffer_specs<S: DescriptorSetByName>(
         &self,
         buffer_specs: &S,
+    ) -> CrateResult<()> {
+        self.validate_with(|buf_name| buffer_specs.descriptor_set_by_name(buf_name).map(|_| ()))?;
+        self.validate_against_reflection(shaders_reflection()?, |buf_name| {
+            buffer_specs.element_size_by_name(buf_name)
+        })
+    }
+
+    /// Same as [`Self::validate_against_buffer_specs`], with `buffer_exists` returning an error
+    /// for names that are not available in the backend's buffers.
+    pub fn validate_with(
+        &self,
+        buffer_exists: impl Fn(&str) -> CrateResult<()>,
     ) -> CrateResult<()> {
         //check that the invocation lists all the buffers it needs
-        for buf_name in self.buf_names.iter() {
+        for buf_name in self
+            .buf_names
+            .iter()
+            .chain(self.indirect_buf_name().as_ref())
+        {
             // just check it exists
-            buffer_specs.descriptor_set_by_name(buf_name)?;
+            buffer_exists(buf_name)?;
+        }
+
+        // access annotations must name buffers the invocation binds
+        if let Some((buf_name, _)) = self
+            .accesses
+            .iter()
+            .find(|(name, _)| !self.buf_names.contains(name))
+        {
+            return Err(ChimeraError::AccessAnnotationNotBound {
+                invocation_name: self.invocation_name.to_string(),
+                buf_name: buf_name.to_string(),
+            });
+        }
+
+        if let DispatchSize::OverBuffer(buf_name) = self.kernel_config.dispatch_size {
+            if !self.buf_names.contains(&buf_name) {
+                return Err(ChimeraError::DispatchBufferNotBound {
+                    invocation_name: self.invocation_name.to_string(),
+                    buf_name: buf_name.to_string(),
+                });
+            }
+        }
+        if let Some(PushConstantSource::OverBuffer { buf_name, .. }) =
+            self.kernel_config.push_constants
+        {
+            if !self.buf_names.contains(&buf_name) {
+                return Err(ChimeraError::PushConstantBufferNotBound {
+                    invocation_name: self.invocation_name.to_string(),
+                    buf_name: buf_name.to_string(),
+                });
+            }
         }
 
         // check that the number of buffer names matches the the number of bindings in the kernel config
         if self.buf_names.len() != self.kernel_config.binding_nums_in_shader.len() {
-            return Err(
-                crate::error::ChimeraError::PipelineSpecBufferNameCountMismatch {
+            return Err(ChimeraError::PipelineSpecBufferNameCountMismatch {
+                invocation_name: self.invocation_name.to_string(),
+                expected: self.kernel_config.binding_nums_in_shader.len(),
+                found: self.buf_names.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that the invocation can follow a resize of the buffer `buf_name`: if it
+    /// uses the buffer, neither its push constants nor its dispatch size may be fixed
+    /// values, which would still hold the old length
+    ///
+    /// Push constants from [`KernelConfig::with_push_constants_over_buffer`] and
+    /// [`over_buffer`] dispatches follow the new length, and per-execution push
+    /// constants are left to their closure.
+    pub fn validate_resize(&self, buf_name: &str) -> CrateResult<()> {
+        if !self
+            .buffer_accesses()
+            .iter()
+            .any(|(name, _)| *name == buf_name)
+        {
+            return Ok(());
+        }
+        let fixed = if matches!(
+            self.kernel_config.push_constants,
+            Some(PushConstantSource::Value(_))
+        ) {
+            "fixed push constants"
+        } else if matches!(
+            self.kernel_config.dispatch_size,
+            DispatchSize::Grid2d { .. }
+        ) {
+            "fixed grid dispatch size"
+        } else {
+            return Ok(());
+        };
+        Err(ChimeraError::FixedInvocationOverResizedBuffer {
+            invocation_name: self.invocation_name.to_string(),
+            buf_name: buf_name.to_string(),
+            fixed: fixed.to_string(),
+        })
+    }
+
+    /// Check the kernel's binding numbers against the storage buffers its entry point uses
+    /// in `reflection`, and the element size of each bound buffer (from `element_size`)
+    /// against the shader's array stride.
+    pub fn validate_against_reflection(
+        &self,
+        reflection: &ShaderReflection,
+        element_size: impl Fn(&str) -> CrateResult<usize>,
+    ) -> CrateResult<()> {
+        let entry_point_name = self.kernel_config.entry_point_name;
+        let entry_point = reflection.entry_point(entry_point_name)?;
+
+        if let Some(b) = entry_point
+            .storage_buffers
+            .iter()
+            .find(|b| b.descriptor_set != 0)
+        {
+            return Err(ChimeraError::UnsupportedDescriptorSet {
+                entry_point: entry_point_name.to_string(),
+                descriptor_set: b.descriptor_set,
+                binding: b.binding,
+            });
+        }
+
+        if let Some(buf_name) = self.indirect_buf_name() {
+            let buffer_element_size = element_size(buf_name)?;
+            if buffer_element_size != std::mem::size_of::<shared::DispatchIndirectCommand>() {
+                return Err(ChimeraError::IndirectBufferElementSizeMismatch {
+                    invocation_name: self.invocation_name.to_string(),
+                    buf_name: buf_name.to_string(),
+                    buffer_element_size,
+                });
+            }
+        }
+
+        let kernel_has_push_constants = self.kernel_config.push_constants.is_some();
+        if kernel_has_push_constants != entry_point.has_push_constants {
+            return Err(ChimeraError::PushConstantsMismatch {
+                invocation_name: self.invocation_name.to_string(),
+                entry_point: entry_point_name.to_string(),
+                shader_has_push_constants: entry_point.has_push_constants,
+            });
+        }
+
+        let mut kernel_bindings = self.kernel_config.binding_nums_in_shader.clone();
+        kernel_bindings.sort_unstable();
+        let shader_bindings = entry_point.binding_nums();
+        if kernel_bindings != shader_bindings {
+            return Err(ChimeraError::KernelBindingMismatch {
+                invocation_name: self.invocation_name.to_string(),
+                entry_point: entry_point_name.to_string(),
+                kernel_bindings: self.kernel_config.binding_nums_in_shader.clone(),
+                shader_bindings,
+            });
+        }
+
+        for (&binding, &buf_name) in self
+            .kernel_config
+            .binding_nums_in_shader
+            .iter()
+            .zip(self.buf_names.iter())
+        {
+            let Some(shader_stride) = entry_point
+                .storage_buffer(binding)
+                .and_then(|b| b.element_stride)
+            else {
+                continue;
+            };
+            let buffer_element_size = element_size(buf_name)?;
+            if buffer_element_size != shader_stride as usize {
+                return Err(ChimeraError::BufferElementSizeMismatch {
                     invocation_name: self.invocation_name.to_string(),
-                    expected: self.kernel_config.binding_nums_in_shader.len(),
-                    found: self.buf_names.len(),
-                },
-            );
+                    buf_name: buf_name.to_string(),
+                    binding,
+                    shader_stride,
+                    buffer_element_size,
+                });
+            }
         }
         Ok(())
     }
+
+    /// Resolve the kernel's [`DispatchSize`] against the entry point's `LocalSize` in
+    /// `reflection`, with `buffer_len` returning the number of elements of a buffer.
+    ///
+    /// `None` for indirect dispatches, once their buffer is known to hold a command.
+    pub fn num_workgroups(
+        &self,
+        reflection: &ShaderReflection,
+        buffer_len: impl Fn(&str) -> CrateResult<usize>,
+    ) -> CrateResult<Option<[u32; 3]>> {
+        let dispatch_size = &self.kernel_config.dispatch_size;
+        match dispatch_size {
+            DispatchSize::Workgroups(num_workgroups) => return Ok(Some(*num_workgroups)),
+            DispatchSize::Indirect(buf_name) => {
+                if buffer_len(buf_name)? == 0 {
+                    return Err(ChimeraError::IndirectBufferEmpty {
+                        invocation_name: self.invocation_name.to_string(),
+                        buf_name: buf_name.to_string(),
+                    });
+                }
+                return Ok(None);
+            }
+            DispatchSize::OverBuffer(_) | DispatchSize::Grid2d { .. } => {}
+        }
+        let local_size = reflection
+            .entry_point(self.kernel_config.entry_point_name)?
+            .local_size()?;
+        dispatch_size.num_workgroups(local_size, buffer_len)
+    }
 }
 
 /// spec for a shader pipeline invocation
@@ -102,9 +597,71 @@ pub fn invoc_spec(
         invocation_name,
         buf_names,
         kernel_config,
+        accesses: Vec::new(),
     }
 }
 
+/// spec for an invocation dispatched indirectly: its workgroup count is read from the
+/// first [`shared::DispatchIndirectCommand`] in the buffer `indirect_buf_name` when it
+/// runs, so an earlier invocation of the chain can decide it (e.g. from the number of
+/// active particles).
+///
+/// Replaces the dispatch size of `kernel_config`. The invocation need not bind the
+/// buffer; it is ordered after the invocations that write it like any read.
+pub fn invoc_spec_indirect(
+    invocation_name: &'static str,
+    buf_names: Vec<&'static str>,
+    kernel_config: KernelConfig,
+    indirect_buf_name: &'static str,
+) -> ShaderPipelineSpec {
+    invoc_spec(
+        invocation_name,
+        buf_names,
+        KernelConfig {
+            dispatch_size: DispatchSize::Indirect(indirect_buf_name),
+            ..kernel_config
+        },
+    )
+}
+
+/// spec for a shader pipeline invocation, with each buffer given as a
+/// `(shader parameter name, buffer name)` pair instead of by position.
+///
+/// The bindings of `kernel_config` are replaced by the ones of the named parameters.
+pub fn invoc_spec_by_param(
+    invocation_name: &'static str,
+    params: Vec<(&'static str, &'static str)>,
+    kernel_config: KernelConfig,
+) -> CrateResult<ShaderPipelineSpec> {
+    let entry_point_name = kernel_config.entry_point_name;
+    let entry_point = shaders_reflection()?.entry_point(entry_point_name)?;
+
+    let (binding_nums_in_shader, buf_names) = params
+        .into_iter()
+        .map(|(param, buf_name)| {
+            entry_point
+                .storage_buffer_by_name(param)
+                .map(|b| (b.binding, buf_name))
+                .ok_or_else(|| ChimeraError::ShaderParameterNotFound {
+                    entry_point: entry_point_name.to_string(),
+                    param: param.to_string(),
+                })
+        })
+        .collect::<CrateResult<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    Ok(ShaderPipelineSpec {
+        invocation_name,
+        buf_names,
+        kernel_config: KernelConfig {
+            binding_nums_in_shader,
+            ..kernel_config
+        },
+        accesses: Vec::new(),
+    })
+}
+
 /// Builder for a shader pipeline.
 /// Note that a Vulkan shader "pipeline" does not know the types of the buffers it will use. It only knows about the bindings. We only need actual buffer types when we create the descriptor sets Since a given pipeline can use multiple buffers that might be used by several pipelines, we track buffers by name. E
 #[derive(Clone)]
@@ -141,6 +698,10 @@ pub struct Ready {
     descriptor_set_layout: Arc<DescriptorSetLayout>,
     pipeline: Arc<ComputePipeline>,
     descriptor_set: Arc<DescriptorSet>,
+    /// The kernel's dispatch size, resolved against the bound buffers
+    workgroups: Workgroups,
+    /// The kernel's push constants, resolved against the bound buffers
+    push_constants: Option<PushConstantSource>,
 }
 
 // transition methods
@@ -217,6 +778,11 @@ impl ShaderPipelineBuilder<HasDescriptorSetLayout> {
             device,
             self.builder_state.descriptor_set_layout.clone(),
             self.builder_state.entry_point.clone(),
+            self.spec
+                .kernel_config
+                .push_constants
+                .as_ref()
+                .map(PushConstantSource::size),
         )?;
         Ok(ShaderPipelineBuilder {
             spec: self.spec,
@@ -231,7 +797,7 @@ impl ShaderPipelineBuilder<HasDescriptorSetLayout> {
 }
 
 impl ShaderPipelineBuilder<HasPipeline> {
-    pub fn with_descriptor_set<S: DescriptorSetByName>(
+    pub fn with_descriptor_set<S: DescriptorSetByName + TypedSubbufferByName>(
         self,
         buffer_specs: &S,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
@@ -250,6 +816,28 @@ impl ShaderPipelineBuilder<HasPipeline> {
                 write_descriptor_sets.clone(),
             )?;
 
+        let num_workgroups = self
+            .spec
+            .num_workgroups(shaders_reflection()?, |buf_name| {
+                buffer_specs.len_by_name(buf_name)
+            })?;
+        let workgroups = match (num_workgroups, self.spec.indirect_buf_name()) {
+            (Some(num_workgroups), _) => Workgroups::Direct(num_workgroups),
+            (None, Some(buf_name)) => Workgroups::indirect(
+                buffer_specs.subbuffer::<shared::DispatchIndirectCommand>(buf_name)?,
+            ),
+            (None, None) => unreachable!("only indirect dispatches have no workgroup count"),
+        };
+        let push_constants = self
+            .spec
+            .kernel_config
+            .push_constants
+            .as_ref()
+            .map(|push_constants| {
+                push_constants.resolved(|buf_name| buffer_specs.len_by_name(buf_name))
+            })
+            .transpose()?;
+
         Ok(ShaderPipelineBuilder {
             spec: self.spec,
             builder_state: Ready {
@@ -257,6 +845,8 @@ impl ShaderPipelineBuilder<HasPipeline> {
                 descriptor_set_layout: self.builder_state.descriptor_set_layout,
                 pipeline: self.builder_state.pipeline,
                 descriptor_set,
+                workgroups,
+                push_constants,
             },
         })
     }
@@ -269,13 +859,71 @@ impl ShaderPipelineBuilder<Ready> {
     ) -> CrateResult<()> {
         builder.bind_pipeline_compute(self.builder_state.pipeline.clone())?;
 
+        let push_constants = self
+            .builder_state
+            .push_constants
+            .as_ref()
+            .map(PushConstantSource::words);
+
         bind_and_dispatch(
             builder,
             self.builder_state.pipeline.clone(),
             self.builder_state.descriptor_set.clone(),
-            self.spec.kernel_config.num_workgroups,
+            push_constants.as_deref(),
+            self.builder_state.workgroups.clone(),
         )?;
 
         Ok(())
     }
+
+    /// Record the dispatch without automatic synchronization, for [`ChainRecorder`]
+    ///
+    /// [`ChainRecorder`]: crate::runners::vulkano::chain_recorder::ChainRecorder
+    pub fn record_dispatch(&self, recorder: &mut RecordingCommandBuffer) -> CrateResult<()> {
+        let push_constants = self
+            .builder_state
+            .push_constants
+            .as_ref()
+            .map(PushConstantSource::words);
+
+        record_dispatch(
+            recorder,
+            &self.builder_state.pipeline,
+            &self.builder_state.descriptor_set,
+            push_constants.as_deref(),
+            self.builder_state.workgroups.clone(),
+        )
+    }
+
+    /// The same pipeline with its descriptor set built again from `buffer_specs`, and its
+    /// workgroup count resolved again against their lengths, e.g. after a buffer was
+    /// replaced
+    pub fn with_descriptor_set_rebuilt<S: DescriptorSetByName + TypedSubbufferByName>(
+        &self,
+        buffer_specs: &S,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> CrateResult<ShaderPipelineBuilder<Ready>> {
+        ShaderPipelineBuilder {
+            spec: self.spec.clone(),
+            builder_state: HasPipeline {
+                entry_point: self.builder_state.entry_point.clone(),
+                descriptor_set_layout: self.builder_state.descriptor_set_layout.clone(),
+                pipeline: self.builder_state.pipeline.clone(),
+            },
+        }
+        .with_descriptor_set(buffer_specs, descriptor_set_allocator)
+    }
+
+    pub fn spec(&self) -> &ShaderPipelineSpec {
+        &self.spec
+    }
+
+    /// The workgroup count the kernel's dispatch size resolved to; `None` for indirect
+    /// dispatches
+    pub fn num_workgroups(&self) -> Option<[u32; 3]> {
+        match self.builder_state.workgroups {
+            Workgroups::Direct(num_workgroups) => Some(num_workgroups),
+            Workgroups::Indirect(_) => None,
+        }
+    }
 }
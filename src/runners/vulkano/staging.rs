@@ -0,0 +1,196 @@
+//! Transfers between the host and buffers it can't map
+//!
+//! A [`Residency::DeviceLocal`] buffer lives where kernels reach it fastest, but the
+//! host can't map it. Its contents go through a host-visible staging buffer instead,
+//! copied to or from it on the GPU. Each transfer waits for its copy to finish.
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        CopyBufferInfo,
+    },
+    device::Queue,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::{self, GpuFuture},
+};
+
+use crate::{error::CrateResult, runners::vulkano::buffer_specs::Residency};
+
+/// What a chain needs to allocate its buffers and move their contents to and from
+/// the host
+#[derive(Clone)]
+pub struct Staging {
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    queue: Arc<Queue>,
+}
+
+impl Staging {
+    pub fn new(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        queue: Arc<Queue>,
+    ) -> Self {
+        Self {
+            memory_allocator,
+            command_buffer_allocator,
+            queue,
+        }
+    }
+
+    pub fn memory_allocator(&self) -> &Arc<StandardMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    /// A new buffer of a chain holding `data`, in the memory `residency` chooses
+    pub fn buffer_from_data<T: BufferContents + Copy>(
+        &self,
+        data: &[T],
+        residency: Residency,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        // any buffer of a chain can hold the workgroup count of an indirect dispatch
+        let usage = BufferUsage::STORAGE_BUFFER
+            | BufferUsage::INDIRECT_BUFFER
+            | BufferUsage::TRANSFER_SRC
+            | BufferUsage::TRANSFER_DST;
+        let create_info = BufferCreateInfo {
+            usage,
+            ..Default::default()
+        };
+        let allocation_info = AllocationCreateInfo {
+            memory_type_filter: residency.memory_type_filter(),
+            ..Default::default()
+        };
+
+        if residency.is_host_visible() {
+            return Ok(Buffer::from_iter(
+                self.memory_allocator.clone(),
+                create_info,
+                allocation_info,
+                data.iter().copied(),
+            )?);
+        }
+        let buffer = Buffer::new_slice::<T>(
+            self.memory_allocator.clone(),
+            create_info,
+            allocation_info,
+            data.len() as u64,
+        )?;
+        self.upload(&buffer, data)?;
+        Ok(buffer)
+    }
+
+    /// The contents of `buffer`, mapped if `residency` is host-visible, else copied
+    /// through a staging buffer
+    pub fn read<T: BufferContents + Copy>(
+        &self,
+        buffer: &Subbuffer<[T]>,
+        residency: Residency,
+    ) -> CrateResult<Vec<T>> {
+        if residency.is_host_visible() {
+            Ok(buffer.read()?.to_vec())
+        } else {
+            self.download(buffer)
+        }
+    }
+
+    /// Overwrite `buffer` with `data`, of the same length, mapped if `residency` is
+    /// host-visible, else copied through a staging buffer
+    pub fn write<T: BufferContents + Copy>(
+        &self,
+        buffer: &Subbuffer<[T]>,
+        residency: Residency,
+        data: &[T],
+    ) -> CrateResult<()> {
+        if residency.is_host_visible() {
+            buffer.write()?.copy_from_slice(data);
+            Ok(())
+        } else {
+            self.upload(buffer, data)
+        }
+    }
+
+    /// A copy of `buffer` with `new_len` elements, in the memory `residency` chooses: the
+    /// elements both have are copied on the GPU, and any new ones are `value`
+    pub fn resized<T: BufferContents + Copy>(
+        &self,
+        buffer: &Subbuffer<[T]>,
+        residency: Residency,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        let resized = self.buffer_from_data(&vec![value; new_len], residency)?;
+        let kept = buffer.len().min(new_len as u64);
+        if kept > 0 {
+            self.copy(buffer.clone().slice(..kept), resized.clone().slice(..kept))?;
+        }
+        Ok(resized)
+    }
+
+    /// Copy `data` into `buffer` through a staging buffer
+    pub fn upload<T: BufferContents + Copy>(
+        &self,
+        buffer: &Subbuffer<[T]>,
+        data: &[T],
+    ) -> CrateResult<()> {
+        let staging_buffer = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )?;
+        self.copy(staging_buffer, buffer.clone())
+    }
+
+    /// Copy the contents of `buffer` back to the host through a staging buffer
+    pub fn download<T: BufferContents + Copy>(
+        &self,
+        buffer: &Subbuffer<[T]>,
+    ) -> CrateResult<Vec<T>> {
+        let staging_buffer = Buffer::new_slice::<T>(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            buffer.len(),
+        )?;
+        self.copy(buffer.clone(), staging_buffer.clone())?;
+        let contents = staging_buffer.read()?.to_vec();
+        Ok(contents)
+    }
+
+    /// Copy `src` into `dst` on the chain's queue, and wait for the copy to finish
+    ///
+    /// Nothing orders it after the chain's submissions on the GPU, so callers wait for
+    /// those first.
+    fn copy<T: BufferContents>(&self, src: Subbuffer<[T]>, dst: Subbuffer<[T]>) -> CrateResult<()> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.command_buffer_allocator.clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_buffer(CopyBufferInfo::buffers(src, dst))?;
+        let command_buffer = builder.build()?;
+
+        let future = sync::now(self.queue.device().clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?;
+        future.wait(None)?;
+        Ok(())
+    }
+}
@@ -1,10 +1,19 @@
+use bytemuck::Pod;
 use variadics_please::all_tuples_enumerated;
-use vulkano::buffer::Subbuffer;
+use vulkano::buffer::{BufferContents, Subbuffer};
 
 use crate::{error::CrateResult, runners::vulkano::buffer_specs::SubbufferAndBindingSpec};
 
 pub trait TypedSubbufferByName {
     fn subbuffer<T: 'static>(&self, name: &str) -> CrateResult<Subbuffer<[T]>>;
+
+    /// Put `sub_buf` in place of the buffer named `name`, which must hold `T` elements
+    /// too, and return the buffer it replaced
+    fn replace_subbuffer<T: BufferContents + Pod>(
+        &mut self,
+        name: &str,
+        sub_buf: Subbuffer<[T]>,
+    ) -> CrateResult<Subbuffer<[T]>>;
 }
 
 use std::any::Any;
@@ -23,6 +32,23 @@ impl<S: 'static> TypedSubbufferByName for SubbufferAndBindingSpec<S> {
             std::any::type_name::<T>().to_string(),
         ))
     }
+
+    fn replace_subbuffer<T: BufferContents + Pod>(
+        &mut self,
+        name: &str,
+        sub_buf: Subbuffer<[T]>,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        if self.name == name {
+            let any: &mut dyn Any = &mut self.sub_buf;
+            if let Some(buf) = any.downcast_mut::<Subbuffer<[T]>>() {
+                return Ok(std::mem::replace(buf, sub_buf));
+            }
+        }
+        Err(crate::error::ChimeraError::TypedDescriptorSetNameNotFound(
+            name.to_string(),
+            std::any::type_name::<T>().to_string(),
+        ))
+    }
 }
 
 macro_rules! impl_typed_subbuffer_by_name_for_tuple {
@@ -40,6 +66,22 @@ macro_rules! impl_typed_subbuffer_by_name_for_tuple {
                      std::any::type_name::<U>().to_string(),
                 ))
             }
+
+            fn replace_subbuffer<U: BufferContents + Pod>(
+                &mut self,
+                name: &str,
+                sub_buf: Subbuffer<[U]>,
+            ) -> CrateResult<Subbuffer<[U]>> {
+                $(
+                    if let Ok(replaced) = self.$n.replace_subbuffer::<U>(name, sub_buf.clone()) {
+                        return Ok(replaced);
+                    }
+                )*
+                Err(crate::error::ChimeraError::TypedDescriptorSetNameNotFound (
+                     name.to_string(),
+                     std::any::type_name::<U>().to_string(),
+                ))
+            }
         }
     };
 }
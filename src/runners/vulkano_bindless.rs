@@ -1,13 +1,17 @@
 pub mod buffer;
+pub mod compute_chain;
 pub mod descriptor_sets;
 pub mod device;
 pub mod dispatch;
+pub mod heap;
 pub mod pipeline;
 pub mod shader;
 pub mod shader_buffer_mapping;
 pub mod unified_buffer;
 
-use self::shader_buffer_mapping::BindlessComputePass;
+use self::compute_chain::BindlessComputeChain;
+use self::heap::{BindlessHeap, HeapKernel};
+use self::shader_buffer_mapping::{BindlessComputePass, BindlessDispatchSpec};
 use self::unified_buffer::UnifiedBufferTracker;
 use crate::{
     error::CrateResult,
@@ -22,7 +26,7 @@ use shared::WORKGROUP_SIZE;
 use std::sync::Arc;
 
 use vulkano::{
-    buffer::Subbuffer,
+    buffer::{BufferContents, Subbuffer},
     command_buffer::{
         allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
     },
@@ -38,9 +42,9 @@ use vulkano::{
 /// Unlike the traditional "bindfull" approach where each logical buffer gets its own
 /// descriptor binding, this runner uses a bindless approach where:
 /// - All u32 data is packed into one unified buffer
-/// - All Vec2 data is packed into another unified buffer  
+/// - All Vec2 data is packed into another unified buffer (and so on per element type)
 /// - Shaders use push constants to know where each logical buffer starts
-/// - Only 2 descriptor bindings are needed total (vs 6+ in the bindfull approach)
+/// - Only one descriptor binding per element type is needed (vs 6+ in the bindfull approach)
 pub struct VulkanoBindlessRunner {
     instance: Arc<Instance>,
     device: Arc<Device>,
@@ -102,6 +106,9 @@ impl VulkanoBindlessRunner {
     /// The key difference from the bindfull approach is that we only bind 2 descriptor
     /// sets total (one for u32 buffer, one for Vec2 buffer), and use push constants
     /// to specify offsets for each dispatch.
+    ///
+    /// Everything is rebuilt on every call; to run the pass every frame, build a
+    /// [`BindlessComputeChain`] once with [`Self::build_compute_chain`] instead.
     pub fn run_compute_and_get_buffer(
         &self,
         a: &mut [u32],
@@ -117,59 +124,136 @@ impl VulkanoBindlessRunner {
 
         // BINDLESS STEP 1: Create unified buffers
         // Instead of 6 separate buffers, we create 2 unified buffers
-        let unified_buffers =
-            UnifiedBufferTracker::new(self.memory_allocator.clone(), a, b, c, d, x, v)?;
+        let unified_buffers = UnifiedBufferTracker::builder()
+            .with_buffer("a", a)
+            .with_buffer("b", b)
+            .with_buffer("c", c)
+            .with_buffer("d", d)
+            .with_buffer("x", x)
+            .with_buffer("v", v)
+            .build(self.memory_allocator.clone())?;
+
+        // BINDLESS STEPS 2-4: Build the pipelines, dispatch and execute
+        let chain =
+            self.build_compute_chain(unified_buffers, &self.dispatch_specs(num_workgroups)?)?;
+        chain.execute()?;
+
+        // BINDLESS STEP 5: Read back results from unified buffers
+        // Extract the logical buffers from the unified buffers
+        a.copy_from_slice(&chain.read::<u32>("a")?);
+        x.copy_from_slice(&chain.read::<Vec2>("x")?);
+
+        // For graphics rendering, hand out a view of 'x' within the unified buffer
+        Ok((chain.subbuffer::<Vec2>("x")?, len))
+    }
+
+    /// The dispatches of the runner's compute pass, each over `num_workgroups` workgroups
+    ///
+    /// Passes mixing differently sized kernels (e.g. grid-sized and particle-sized) are
+    /// described with [`shader_buffer_mapping::bindless_dispatch`] instead.
+    pub fn dispatch_specs(&self, num_workgroups: u32) -> CrateResult<Vec<BindlessDispatchSpec>> {
+        BindlessDispatchSpec::from_invocation_info(&self.compute_pass_info, [num_workgroups, 1, 1])
+    }
+
+    /// Build the compute pass `dispatch_specs` over `unified_buffers` once, to be
+    /// executed every frame
+    ///
+    /// The unified buffers stay resident on the GPU and the command buffer is recorded
+    /// once; views of the logical buffers (e.g. the particle positions for the renderer)
+    /// are available from [`BindlessComputeChain::subbuffer`].
+    pub fn build_compute_chain(
+        &self,
+        unified_buffers: UnifiedBufferTracker,
+        dispatch_specs: &[BindlessDispatchSpec],
+    ) -> CrateResult<BindlessComputeChain> {
+        BindlessComputeChain::new(
+            self.device.clone(),
+            self.queue.clone(),
+            dispatch_specs,
+            self.descriptor_set_allocator.clone(),
+            self.command_buffer_allocator.clone(),
+            unified_buffers,
+        )
+    }
 
-        // BINDLESS STEP 2: Create the compute pass with bindless pipelines
-        // This will create pipelines that expect unified buffers and push constants
+    /// Run the compute pass once over logical buffers that were packed into `unified_buffers`
+    ///
+    /// The unified buffers can hold any number of named logical buffers of any element
+    /// type; results are read back with [`UnifiedBufferTracker::read`].
+    pub fn run_compute(
+        &self,
+        unified_buffers: &UnifiedBufferTracker,
+        num_workgroups: u32,
+    ) -> CrateResult<()> {
+        self.run_dispatches(unified_buffers, &self.dispatch_specs(num_workgroups)?)
+    }
+
+    /// Run the dispatches `dispatch_specs` once over `unified_buffers`
+    pub fn run_dispatches(
+        &self,
+        unified_buffers: &UnifiedBufferTracker,
+        dispatch_specs: &[BindlessDispatchSpec],
+    ) -> CrateResult<()> {
         let compute_pass = BindlessComputePass::new(
             self.device.clone(),
-            &self.compute_pass_info,
+            dispatch_specs,
             self.descriptor_set_allocator.clone(),
-            &unified_buffers,
+            unified_buffers,
         )?;
 
-        // BINDLESS STEP 3: Build command buffer and dispatch shaders
         let mut builder = AutoCommandBufferBuilder::primary(
             self.command_buffer_allocator.clone(),
             self.queue.queue_family_index(),
             CommandBufferUsage::OneTimeSubmit,
         )?;
 
-        // Execute all the compute shader dispatches
-        // Each dispatch will use push constants to specify buffer offsets
+        // Each dispatch uses push constants to specify buffer offsets
         compute_pass
-            .dispatch_all(&mut builder, num_workgroups, &unified_buffers)
+            .dispatch_all(&mut builder)
             .inspect_err(|e| println!("Error during dispatch_all: {e}"))?;
-
-        // println!("  Dispatching compute on device '{}'", self.device_name);
-
         let command_buffer = builder.build()?;
 
-        // BINDLESS STEP 4: Execute on GPU
         let future = sync::now(self.device.clone())
             .then_execute(self.queue.clone(), command_buffer)?
             .then_signal_fence_and_flush()?;
         future.wait(None)?;
 
-        // BINDLESS STEP 5: Read back results from unified buffers
-        // Extract the logical buffers from the unified buffers
-        let a_result = unified_buffers.read_u32_buffer("a");
-        a.copy_from_slice(&a_result);
-
-        let x_result = unified_buffers.read_vec2_buffer("x");
-        x.copy_from_slice(&x_result);
-
-        // For graphics rendering, we need to return a buffer containing just 'x'.
-        // Since x is part of the unified buffer, we need to extract it.
-        // For simplicity, we'll create a new buffer with just x data.
-        // (In a real application, you might want to keep using the unified buffer with offsets)
-        let x_standalone = crate::runners::vulkano::buffer::build_and_fill_buffer(
-            self.memory_allocator.clone(),
-            &x_result,
+        Ok(())
+    }
+
+    /// Run one `bindless::` heap kernel (e.g. `p2g`) over `heap` and wait for it to finish
+    ///
+    /// `push_constants` carries the byte offsets of the logical buffers the kernel uses,
+    /// see [`BindlessHeap::byte_offset`].
+    pub fn run_heap_kernel<Pc: BufferContents>(
+        &self,
+        heap: &BindlessHeap,
+        entry_point_name: &str,
+        push_constants: Pc,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        let kernel = HeapKernel::new(
+            self.device.clone(),
+            self.descriptor_set_allocator.clone(),
+            heap,
+            entry_point_name,
+            std::mem::size_of::<Pc>() as u32,
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.command_buffer_allocator.clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
         )?;
+        kernel.dispatch(&mut builder, push_constants, num_workgroups)?;
+        let command_buffer = builder.build()?;
+
+        let future = sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?;
+        future.wait(None)?;
 
-        Ok((x_standalone, len))
+        Ok(())
     }
 
     /// Get the Vulkan instance (needed for creating windows/surfaces)
@@ -0,0 +1,108 @@
+/// A bindless compute pass that stays resident on the GPU across frames
+///
+/// [`VulkanoBindlessRunner::run_compute`](super::VulkanoBindlessRunner::run_compute)
+/// builds the pipelines and descriptor set and records a command buffer for a single
+/// execution. A [`BindlessComputeChain`] does all of that once: the unified buffers
+/// stay on the GPU, and every [`execute`](BindlessComputeChain::execute) resubmits the
+/// same prerecorded command buffer, so it can drive a real-time loop.
+use crate::{
+    error::CrateResult,
+    runners::vulkano_bindless::{
+        shader_buffer_mapping::{BindlessComputePass, BindlessDispatchSpec},
+        unified_buffer::UnifiedBufferTracker,
+    },
+};
+use bytemuck::Pod;
+use std::sync::Arc;
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        PrimaryAutoCommandBuffer,
+    },
+    descriptor_set::allocator::StandardDescriptorSetAllocator,
+    device::{Device, Queue},
+    sync::{self, GpuFuture},
+};
+
+/// A bindless compute pass with resident unified buffers and a prerecorded command buffer
+pub struct BindlessComputeChain {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    unified_buffers: UnifiedBufferTracker,
+    /// Recorded once and resubmitted on every execution; keeps the pipelines and the
+    /// descriptor set of the pass alive
+    command_buffer: Arc<PrimaryAutoCommandBuffer>,
+}
+
+impl BindlessComputeChain {
+    /// Build the pipelines and descriptor set for `dispatch_specs` over
+    /// `unified_buffers`, and record the command buffer of the pass
+    pub fn new(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        dispatch_specs: &[BindlessDispatchSpec],
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        unified_buffers: UnifiedBufferTracker,
+    ) -> CrateResult<Self> {
+        let compute_pass = BindlessComputePass::new(
+            device.clone(),
+            dispatch_specs,
+            descriptor_set_allocator,
+            &unified_buffers,
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::MultipleSubmit,
+        )?;
+        compute_pass.dispatch_all(&mut builder)?;
+        let command_buffer = builder.build()?;
+
+        Ok(Self {
+            device,
+            queue,
+            unified_buffers,
+            command_buffer,
+        })
+    }
+
+    /// Run the compute pass once more over the resident buffers and wait for it to finish
+    pub fn execute(&self) -> CrateResult<()> {
+        let future = sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), self.command_buffer.clone())?
+            .then_signal_fence_and_flush()?;
+        future.wait(None)?;
+
+        Ok(())
+    }
+
+    pub fn unified_buffers(&self) -> &UnifiedBufferTracker {
+        &self.unified_buffers
+    }
+
+    /// A view of a logical buffer within its unified buffer, e.g. to hand the particle
+    /// positions to the renderer without copying them
+    ///
+    /// The view stays valid across executions; the start of each logical buffer is
+    /// aligned to [`UNIFIED_OFFSET_ALIGNMENT`](super::unified_buffer::UNIFIED_OFFSET_ALIGNMENT)
+    /// by default, so it can be bound as a storage buffer on its own.
+    pub fn subbuffer<T: BufferContents + Pod>(
+        &self,
+        buffer_name: &str,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        self.unified_buffers.subbuffer(buffer_name)
+    }
+
+    /// Read back a logical buffer
+    pub fn read<T: BufferContents + Pod>(&self, buffer_name: &str) -> CrateResult<Vec<T>> {
+        self.unified_buffers.read(buffer_name)
+    }
+
+    /// Overwrite a logical buffer with `data` before the next execution
+    pub fn write<T: BufferContents + Pod>(&self, buffer_name: &str, data: &[T]) -> CrateResult<()> {
+        self.unified_buffers.write(buffer_name, data)
+    }
+}
@@ -8,16 +8,17 @@ use vulkano::{
 
 use crate::error::CrateResult;
 
-/// Bind the descriptor set and dispatch the compute shader
+/// Bind the descriptor set, push the push constants and dispatch the compute shader
 /// This is basically like:
-/// * providing arguments (the descriptor set)
+/// * providing arguments (the descriptor set and push constants)
 /// * to a function/function pointer (the pipeline)
 /// * then calling it (dispatch)
 pub fn bind_and_dispatch(
     builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
     pipeline: Arc<ComputePipeline>,
     descriptor_set: Arc<DescriptorSet>,
-    num_wg: u32,
+    push_constants: &[u32],
+    num_wg: [u32; 3],
 ) -> CrateResult<()> {
     builder.bind_descriptor_sets(
         PipelineBindPoint::Compute,
@@ -25,8 +26,12 @@ pub fn bind_and_dispatch(
         0,
         descriptor_set,
     )?;
+    // pushed word by word, since `push_constants` takes a sized value
+    for (i, word) in push_constants.iter().enumerate() {
+        builder.push_constants(pipeline.layout().clone(), 4 * i as u32, *word)?;
+    }
     unsafe {
-        builder.dispatch([num_wg, 1, 1])?;
+        builder.dispatch(num_wg)?;
     }
     Ok(())
 }
@@ -0,0 +1,339 @@
+/// Byte-addressed bindless heap
+///
+/// Where [`UnifiedBufferTracker`](super::unified_buffer::UnifiedBufferTracker) packs
+/// logical buffers into one unified buffer per element type, the heap packs logical
+/// buffers of *every* element type into a single `[u32]` storage buffer bound at
+/// [`HEAP_BINDING`]. Shaders address it in bytes with the typed load/store helpers
+/// of `shaders::heap`, and get the byte offset of each logical buffer in push
+/// constants (e.g. [`shared::P2gHeapPushConstants`]).
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano_bindless::{
+        buffer::AnyBuffer, descriptor_sets, pipeline::build_pipeline, shader::shader_entry_point,
+        shader::shader_module,
+    },
+};
+use bytemuck::Pod;
+use std::{any::TypeId, collections::HashMap, ops::Range, sync::Arc};
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType,
+        },
+        DescriptorSet,
+    },
+    device::Device,
+    memory::allocator::StandardMemoryAllocator,
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    shader::ShaderStages,
+};
+
+/// Binding of the heap in the bindless heap descriptor set
+pub const HEAP_BINDING: u32 = 0;
+
+/// Minimum alignment (in bytes) of the start of each logical buffer: shaders
+/// address the heap in `u32` words.
+pub const HEAP_MIN_ALIGNMENT: usize = 4;
+
+/// Where a logical buffer lives within the heap
+#[derive(Clone, Copy, Debug)]
+pub struct HeapAllocation {
+    /// Offset (in bytes) of the first element
+    pub byte_offset: u32,
+    /// Number of elements
+    pub len: u32,
+    pub element_size: u32,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl HeapAllocation {
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.byte_offset as usize;
+        start..start + (self.len * self.element_size) as usize
+    }
+}
+
+/// Names and byte offsets of the logical buffers in a heap
+#[derive(Clone, Debug, Default)]
+pub struct HeapLayout {
+    allocations: HashMap<String, HeapAllocation>,
+    size_bytes: usize,
+}
+
+impl HeapLayout {
+    /// Size of the heap in bytes, a multiple of 4
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    pub fn contains(&self, buffer_name: &str) -> bool {
+        self.allocations.contains_key(buffer_name)
+    }
+
+    pub fn allocation(&self, buffer_name: &str) -> CrateResult<HeapAllocation> {
+        self.allocations
+            .get(buffer_name)
+            .copied()
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(buffer_name.to_string()))
+    }
+
+    /// Get the offset (in bytes) of a logical buffer within the heap
+    pub fn byte_offset(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.allocation(buffer_name)?.byte_offset)
+    }
+
+    /// Get the number of elements of a logical buffer
+    pub fn len(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.allocation(buffer_name)?.len)
+    }
+
+    /// The byte range of a logical buffer of `T`s, or
+    /// [`ChimeraError::BufferTypeMismatch`] if it holds another element type.
+    pub fn typed_byte_range<T: 'static>(&self, buffer_name: &str) -> CrateResult<Range<usize>> {
+        let allocation = self.allocation(buffer_name)?;
+        if allocation.type_id != TypeId::of::<T>() {
+            return Err(ChimeraError::BufferTypeMismatch {
+                name: buffer_name.to_string(),
+                stored: allocation.type_name.to_string(),
+                requested: std::any::type_name::<T>().to_string(),
+            });
+        }
+        Ok(allocation.byte_range())
+    }
+
+    /// Copy a logical buffer out of the heap contents `heap`
+    pub fn read<T: Pod>(&self, heap: &[u32], buffer_name: &str) -> CrateResult<Vec<T>> {
+        let range = self.typed_byte_range::<T>(buffer_name)?;
+        // the logical buffer is not necessarily aligned for `T`, so copy rather than cast
+        let mut data = vec![T::zeroed(); self.len(buffer_name)? as usize];
+        bytemuck::cast_slice_mut::<T, u8>(&mut data)
+            .copy_from_slice(&bytemuck::cast_slice::<u32, u8>(heap)[range]);
+        Ok(data)
+    }
+
+    /// Overwrite a logical buffer in the heap contents `heap` with `data`, which must
+    /// have the logical buffer's length
+    pub fn write<T: Pod>(
+        &self,
+        heap: &mut [u32],
+        buffer_name: &str,
+        data: &[T],
+    ) -> CrateResult<()> {
+        let range = self.typed_byte_range::<T>(buffer_name)?;
+        let data_bytes: &[u8] = bytemuck::cast_slice(data);
+        if data_bytes.len() != range.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: self.len(buffer_name)? as usize,
+                data_len: data.len(),
+            });
+        }
+        bytemuck::cast_slice_mut::<u32, u8>(heap)[range].copy_from_slice(data_bytes);
+        Ok(())
+    }
+}
+
+/// Builder for a bindless heap
+///
+/// ```ignore
+/// let heap = BindlessHeap::builder()
+///     .with_buffer("x", &x)
+///     .with_buffer("grid", &grid)
+///     .with_buffer("material", &material)
+///     .build(memory_allocator)?;
+/// ```
+pub struct BindlessHeapBuilder {
+    alignment: usize,
+    bytes: Vec<u8>,
+    allocations: Vec<(String, HeapAllocation)>,
+}
+
+impl Default for BindlessHeapBuilder {
+    fn default() -> Self {
+        Self {
+            alignment: HEAP_MIN_ALIGNMENT,
+            bytes: Vec::new(),
+            allocations: Vec::new(),
+        }
+    }
+}
+
+impl BindlessHeapBuilder {
+    /// Align the start of each logical buffer to at least `alignment` bytes
+    /// (e.g. `minStorageBufferOffsetAlignment`, to also bind logical buffers on their own)
+    pub fn with_alignment(mut self, alignment: usize) -> Self {
+        self.alignment = alignment.max(HEAP_MIN_ALIGNMENT);
+        self
+    }
+
+    /// Add a logical buffer named `name`, initialised with `data`
+    ///
+    /// The buffer starts at a byte offset aligned to both the heap alignment and the
+    /// alignment of `T`.
+    pub fn with_buffer<T: Pod>(mut self, name: impl Into<String>, data: &[T]) -> Self {
+        let alignment = self.alignment.max(std::mem::align_of::<T>());
+        let byte_offset = self.bytes.len().next_multiple_of(alignment);
+        self.bytes.resize(byte_offset, 0);
+        self.bytes.extend_from_slice(bytemuck::cast_slice(data));
+
+        self.allocations.push((
+            name.into(),
+            HeapAllocation {
+                byte_offset: byte_offset as u32,
+                len: data.len() as u32,
+                element_size: std::mem::size_of::<T>() as u32,
+                type_id: TypeId::of::<T>(),
+                type_name: std::any::type_name::<T>(),
+            },
+        ));
+        self
+    }
+
+    /// The heap layout and initial contents, without uploading them
+    pub fn build_host(self) -> CrateResult<(HeapLayout, Vec<u32>)> {
+        let mut allocations = HashMap::new();
+        for (name, allocation) in self.allocations {
+            if allocations.insert(name.clone(), allocation).is_some() {
+                return Err(ChimeraError::DuplicateBufferName(name));
+            }
+        }
+
+        // Vulkan does not allow empty buffers
+        let size_bytes = self.bytes.len().next_multiple_of(4).max(4);
+        let mut words = vec![0u32; size_bytes / 4];
+        bytemuck::cast_slice_mut::<u32, u8>(&mut words)[..self.bytes.len()]
+            .copy_from_slice(&self.bytes);
+
+        let layout = HeapLayout {
+            allocations,
+            size_bytes,
+        };
+        Ok((layout, words))
+    }
+
+    /// Upload the heap to the GPU
+    pub fn build(
+        self,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> CrateResult<BindlessHeap> {
+        let (layout, words) = self.build_host()?;
+        Ok(BindlessHeap {
+            layout,
+            buffer: AnyBuffer::from_data(memory_allocator, &words)?,
+        })
+    }
+}
+
+/// A bindless heap uploaded to the GPU
+pub struct BindlessHeap {
+    layout: HeapLayout,
+    buffer: AnyBuffer,
+}
+
+impl BindlessHeap {
+    pub fn builder() -> BindlessHeapBuilder {
+        BindlessHeapBuilder::default()
+    }
+
+    pub fn layout(&self) -> &HeapLayout {
+        &self.layout
+    }
+
+    /// The heap as a `[u32]` storage buffer
+    pub fn buffer(&self) -> &AnyBuffer {
+        &self.buffer
+    }
+
+    /// Get the offset (in bytes) of a logical buffer within the heap
+    pub fn byte_offset(&self, buffer_name: &str) -> CrateResult<u32> {
+        self.layout.byte_offset(buffer_name)
+    }
+
+    /// Read back a logical buffer from the heap
+    pub fn read<T: BufferContents + Pod>(&self, buffer_name: &str) -> CrateResult<Vec<T>> {
+        let heap = self.buffer.downcast::<u32>()?;
+        let content = heap.read()?;
+        self.layout.read(&content, buffer_name)
+    }
+
+    /// Overwrite a logical buffer with `data`, which must have the logical buffer's length
+    pub fn write<T: BufferContents + Pod>(&self, buffer_name: &str, data: &[T]) -> CrateResult<()> {
+        let heap = self.buffer.downcast::<u32>()?;
+        let mut content = heap.write()?;
+        self.layout.write(&mut content, buffer_name, data)
+    }
+}
+
+/// A compute pipeline for a `bindless::` heap entry point, with the heap bound
+pub struct HeapKernel {
+    pipeline: Arc<ComputePipeline>,
+    descriptor_set: Arc<DescriptorSet>,
+}
+
+impl HeapKernel {
+    /// Build the pipeline for `bindless::{entry_point_name}`, which takes the heap at
+    /// [`HEAP_BINDING`] and `push_constant_size` bytes of push constants
+    pub fn new(
+        device: Arc<Device>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        heap: &BindlessHeap,
+        entry_point_name: &str,
+        push_constant_size: u32,
+    ) -> CrateResult<Self> {
+        let entry_point = shader_entry_point(shader_module(device.clone())?, entry_point_name)?;
+
+        let mut binding =
+            DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer);
+        binding.stages = ShaderStages::COMPUTE;
+        binding.descriptor_count = 1;
+        let descriptor_set_layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: [(HEAP_BINDING, binding)].into(),
+                ..Default::default()
+            },
+        )?;
+
+        let pipeline = build_pipeline(
+            device,
+            descriptor_set_layout.clone(),
+            entry_point,
+            push_constant_size,
+        )?;
+        let descriptor_set = descriptor_sets::build_concrete_descriptor_set(
+            descriptor_set_allocator,
+            descriptor_set_layout,
+            vec![heap.buffer().write_descriptor_set_for_binding(HEAP_BINDING)],
+        )?;
+
+        Ok(Self {
+            pipeline,
+            descriptor_set,
+        })
+    }
+
+    /// Record a dispatch of the kernel with `push_constants`
+    pub fn dispatch<Pc: BufferContents>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        push_constants: Pc,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        builder.bind_pipeline_compute(self.pipeline.clone())?;
+        builder.bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            self.pipeline.layout().clone(),
+            0,
+            self.descriptor_set.clone(),
+        )?;
+        builder.push_constants(self.pipeline.layout().clone(), 0, push_constants)?;
+        unsafe {
+            builder.dispatch(num_workgroups)?;
+        }
+        Ok(())
+    }
+}
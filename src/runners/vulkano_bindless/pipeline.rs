@@ -21,10 +21,10 @@ pub fn build_pipeline(
     device: Arc<Device>,
     descriptor_set_layout: Arc<DescriptorSetLayout>,
     entry_point: EntryPoint,
+    push_constant_size: u32,
 ) -> CrateResult<Arc<ComputePipeline>> {
     // Pipeline layout + push constants
     // For bindless, we need to define a push constant range to pass buffer offsets
-    // Our push constant struct is 2x u32 = 8 bytes
     let pipeline_layout = PipelineLayout::new(
         device.clone(),
         PipelineLayoutCreateInfo {
@@ -32,7 +32,7 @@ pub fn build_pipeline(
             push_constant_ranges: vec![PushConstantRange {
                 stages: ShaderStages::COMPUTE,
                 offset: 0,
-                size: 16, // 4 x u32 = 16 bytes (offset_0, offset_1, buffer_size, _padding)
+                size: push_constant_size,
             }],
             ..Default::default()
         },
@@ -3,19 +3,22 @@
 /// This module handles the creation and management of compute pipelines that use
 /// the bindless paradigm. Unlike the traditional approach where each buffer gets
 /// its own descriptor binding, here we use:
-/// - 2 descriptor bindings total (one for all u32 data, one for all Vec2 data)
+/// - One descriptor binding per element type (e.g. one for all u32 data, one for all Vec2 data)
 /// - Push constants to pass buffer offsets to shaders
 /// - The same pipeline can be reused for different buffer combinations by just
 ///   changing push constants
-
 use crate::{
-    error::CrateResult,
-    runners::vulkano::shader_buffer_mapping::ComputePassInvocationInfo,
+    error::{ChimeraError, CrateResult},
+    runners::vulkano::{
+        shader_buffer_mapping::ComputePassInvocationInfo, shader_pipeline_builder::pod_to_words,
+    },
     runners::vulkano_bindless::{
-        descriptor_sets, pipeline::build_pipeline, shader::shader_entry_point,
-        unified_buffer::UnifiedBufferTracker,
+        descriptor_sets, dispatch::bind_and_dispatch, pipeline::build_pipeline,
+        shader::shader_entry_point, unified_buffer::UnifiedBufferTracker,
     },
 };
+use bytemuck::Pod;
+use shared::{AdderPushConstants, ParticlePushConstants};
 use std::sync::Arc;
 use vulkano::{
     command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
@@ -25,13 +28,115 @@ use vulkano::{
             DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
             DescriptorType,
         },
-        DescriptorSet, WriteDescriptorSet,
+        DescriptorSet,
     },
     device::Device,
-    pipeline::{ComputePipeline, Pipeline},
+    pipeline::ComputePipeline,
     shader::ShaderStages,
 };
 
+/// Computes the push constants of a dispatch (as 32-bit words) from the unified buffer
+/// layout, once the logical buffer offsets are known
+type PushConstantsFn = Arc<dyn Fn(&UnifiedBufferTracker) -> CrateResult<Vec<u32>> + Send + Sync>;
+
+/// One dispatch of a bindless compute pass: which entry point to run, over how many
+/// workgroups, and with which push constants
+#[derive(Clone)]
+pub struct BindlessDispatchSpec {
+    invocation_name: String,
+    entry_point_name: String,
+    buf_names: Vec<String>,
+    num_workgroups: [u32; 3],
+    push_constant_size: u32,
+    push_constants: PushConstantsFn,
+}
+
+/// Describe a dispatch of `bindless::{entry_point_name}` over `num_workgroups`
+///
+/// `push_constants` builds the shader's push constant struct (one of the types shared
+/// with the shader through the `shared` crate) from the unified buffer layout, e.g.
+///
+/// ```ignore
+/// bindless_dispatch("clear_grid", "clear_grid", vec!["grid"], num_workgroups_2d(n, n), |u| {
+///     Ok(BindlessGridPushConstants { grid_offset: u.offset("grid")?, n_grid_x: n, n_grid_y: n, _padding: 0 })
+/// })
+/// ```
+pub fn bindless_dispatch<T: Pod>(
+    invocation_name: &str,
+    entry_point_name: &str,
+    buf_names: Vec<&str>,
+    num_workgroups: [u32; 3],
+    push_constants: impl Fn(&UnifiedBufferTracker) -> CrateResult<T> + Send + Sync + 'static,
+) -> BindlessDispatchSpec {
+    BindlessDispatchSpec {
+        invocation_name: invocation_name.to_string(),
+        entry_point_name: entry_point_name.to_string(),
+        buf_names: buf_names.iter().map(|s| s.to_string()).collect(),
+        num_workgroups,
+        push_constant_size: 4 * pod_to_words(&T::zeroed()).len() as u32,
+        push_constants: Arc::new(move |unified_buffers| {
+            Ok(pod_to_words(&push_constants(unified_buffers)?))
+        }),
+    }
+}
+
+impl BindlessDispatchSpec {
+    /// One dispatch per invocation of `compute_pass_info`, all over `num_workgroups`,
+    /// with push constants holding the offsets of the invocation's buffers in order
+    /// followed by the length of the shortest one: [`shared::AdderPushConstants`] for
+    /// `adder`, and [`shared::ParticlePushConstants`] for the particle kernels
+    ///
+    /// Fails for any other entry point, and for invocations with more buffers than
+    /// those push constants hold offsets for.
+    pub fn from_invocation_info(
+        compute_pass_info: &ComputePassInvocationInfo,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<Vec<Self>> {
+        compute_pass_info
+            .pipelines
+            .iter()
+            .map(|template| {
+                let kind = OffsetsPushConstants::for_entry_point(&template.entry_point_name)?;
+                if !(1..=2).contains(&template.buf_names.len()) {
+                    return Err(ChimeraError::PushConstantBufferCountOutOfRange {
+                        invocation_name: template.invocation_name.clone(),
+                        min: 1,
+                        max: 2,
+                        found: template.buf_names.len(),
+                    });
+                }
+                let buf_names = template.buf_names.clone();
+                Ok(BindlessDispatchSpec {
+                    invocation_name: template.invocation_name.clone(),
+                    entry_point_name: template.entry_point_name.clone(),
+                    buf_names: buf_names.clone(),
+                    num_workgroups,
+                    push_constant_size: kind.size(),
+                    push_constants: Arc::new(move |unified_buffers| {
+                        kind.words(&buf_names, unified_buffers)
+                    }),
+                })
+            })
+            .collect()
+    }
+
+    pub fn invocation_name(&self) -> &str {
+        &self.invocation_name
+    }
+
+    pub fn entry_point_name(&self) -> &str {
+        &self.entry_point_name
+    }
+
+    pub fn buf_names(&self) -> &[String] {
+        &self.buf_names
+    }
+
+    pub fn num_workgroups(&self) -> [u32; 3] {
+        self.num_workgroups
+    }
+}
+
 /// Information about a single shader dispatch in the bindless paradigm
 #[allow(dead_code)]
 struct BindlessShaderDispatch {
@@ -43,13 +148,16 @@ struct BindlessShaderDispatch {
     buffer_names: Vec<String>,
     /// The compute pipeline for this shader
     pipeline: Arc<ComputePipeline>,
+    num_workgroups: [u32; 3],
+    /// Push constants (buffer offsets etc.) as 32-bit words
+    push_constants: Vec<u32>,
 }
 
 /// A complete bindless compute pass with all necessary pipelines and descriptor sets
 pub struct BindlessComputePass {
     /// The compute pipelines for each shader invocation
     dispatches: Vec<BindlessShaderDispatch>,
-    /// Descriptor set containing both unified buffers (binding 0 = u32, binding 1 = Vec2)
+    /// Descriptor set containing all unified buffers (e.g. binding 0 = u32, binding 1 = Vec2)
     descriptor_set: Arc<DescriptorSet>,
 }
 
@@ -61,9 +169,10 @@ impl BindlessComputePass {
     /// 2. Creates descriptor set layouts for the unified buffers
     /// 3. Creates compute pipelines for each shader
     /// 4. Creates descriptor sets that bind the unified buffers
+    /// 5. Computes the push constants of each dispatch from the unified buffer layout
     pub fn new(
         device: Arc<Device>,
-        compute_pass_info: &ComputePassInvocationInfo,
+        dispatch_specs: &[BindlessDispatchSpec],
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
         unified_buffers: &UnifiedBufferTracker,
     ) -> CrateResult<Self> {
@@ -72,42 +181,41 @@ impl BindlessComputePass {
             crate::runners::vulkano_bindless::shader::shader_module(device.clone())?;
         println!("Bindless shader module created");
 
-        // Create descriptor set layouts for the two bindings
-        // Binding 0: unified u32 buffer
-        // Binding 1: unified Vec2 buffer
-        let descriptor_set_layout = create_bindless_descriptor_set_layout(device.clone())?;
+        // Create the descriptor set layout, with one binding per unified buffer
+        // (e.g. binding 0: unified u32 buffer, binding 1: unified Vec2 buffer)
+        let descriptor_set_layout =
+            create_bindless_descriptor_set_layout(device.clone(), unified_buffers)?;
         println!("Bindless descriptor set layouts created");
 
-        // Create a single descriptor set that binds both unified buffers
-        // The descriptor set layout has 2 bindings:
-        // - Binding 0: unified u32 buffer
-        // - Binding 1: unified Vec2 buffer
+        // Create a single descriptor set that binds all unified buffers
         let descriptor_set = descriptor_sets::build_concrete_descriptor_set(
             descriptor_set_allocator.clone(),
             descriptor_set_layout.clone(),
-            vec![
-                WriteDescriptorSet::buffer(0, unified_buffers.unified_u32_buffer.clone()),
-                WriteDescriptorSet::buffer(1, unified_buffers.unified_vec2_buffer.clone()),
-            ],
+            unified_buffers.write_descriptor_sets(),
         )?;
 
         // Create pipelines for each shader invocation
         let mut dispatches = Vec::new();
 
-        for pipeline_info in &compute_pass_info.pipelines {
+        for spec in dispatch_specs {
             // Get the shader entry point (e.g., "bindless::adder")
-            let entry_point =
-                shader_entry_point(shader_module.clone(), &pipeline_info.entry_point_name)?;
+            let entry_point = shader_entry_point(shader_module.clone(), &spec.entry_point_name)?;
 
-            // Build the compute pipeline
-            let pipeline =
-                build_pipeline(device.clone(), descriptor_set_layout.clone(), entry_point)?;
+            // Build the compute pipeline, with room for this dispatch's push constants
+            let pipeline = build_pipeline(
+                device.clone(),
+                descriptor_set_layout.clone(),
+                entry_point,
+                spec.push_constant_size,
+            )?;
 
             dispatches.push(BindlessShaderDispatch {
-                invocation_name: pipeline_info.invocation_name.clone(),
-                entry_point_name: pipeline_info.entry_point_name.clone(),
-                buffer_names: pipeline_info.buf_names.clone(),
+                invocation_name: spec.invocation_name.clone(),
+                entry_point_name: spec.entry_point_name.clone(),
+                buffer_names: spec.buf_names.clone(),
                 pipeline,
+                num_workgroups: spec.num_workgroups,
+                push_constants: (spec.push_constants)(unified_buffers)?,
             });
         }
 
@@ -128,39 +236,23 @@ impl BindlessComputePass {
     /// 1. Bind the appropriate pipeline
     /// 2. Bind descriptor sets (always the same ones - the unified buffers)
     /// 3. Set push constants with buffer offsets
-    /// 4. Dispatch the compute shader
+    /// 4. Dispatch the compute shader over its own number of workgroups
     pub fn dispatch_all(
         &self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
-        num_workgroups: u32,
-        unified_buffers: &UnifiedBufferTracker,
     ) -> CrateResult<()> {
-        use vulkano::pipeline::PipelineBindPoint;
-
         for dispatch in &self.dispatches {
-            // Bind the pipeline for this shader
             builder.bind_pipeline_compute(dispatch.pipeline.clone())?;
 
-            // Bind the descriptor set (same for all shaders - contains both unified buffers)
-            // The shader will access the appropriate binding (0 for u32, 1 for Vec2) based on
-            // what it was compiled to use
-            builder.bind_descriptor_sets(
-                PipelineBindPoint::Compute,
-                dispatch.pipeline.layout().clone(),
-                0,
+            // The descriptor set is the same for all shaders - it contains all unified
+            // buffers, and each shader accesses the binding of its element type
+            bind_and_dispatch(
+                builder,
+                dispatch.pipeline.clone(),
                 self.descriptor_set.clone(),
+                &dispatch.push_constants,
+                dispatch.num_workgroups,
             )?;
-
-            // Calculate push constants (buffer offsets) for this dispatch
-            let push_constants = calculate_push_constants(dispatch, unified_buffers)?;
-
-            // Set push constants
-            builder.push_constants(dispatch.pipeline.layout().clone(), 0, push_constants)?;
-
-            // Dispatch the compute shader
-            unsafe {
-                builder.dispatch([num_workgroups, 1, 1])?;
-            }
         }
 
         Ok(())
@@ -169,25 +261,22 @@ impl BindlessComputePass {
 
 /// Create the descriptor set layout for bindless rendering
 ///
-/// This layout has only 2 bindings:
+/// This layout has one storage buffer binding per unified buffer, e.g.:
 /// - Binding 0: Storage buffer for all u32 data
 /// - Binding 1: Storage buffer for all Vec2 data
 fn create_bindless_descriptor_set_layout(
     device: Arc<Device>,
+    unified_buffers: &UnifiedBufferTracker,
 ) -> CrateResult<Arc<DescriptorSetLayout>> {
     let mut bindings = std::collections::BTreeMap::new();
 
-    // Binding 0: unified u32 buffer
-    let mut binding_0 = DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer);
-    binding_0.stages = ShaderStages::COMPUTE;
-    binding_0.descriptor_count = 1;
-    bindings.insert(0, binding_0);
-
-    // Binding 1: unified Vec2 buffer
-    let mut binding_1 = DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer);
-    binding_1.stages = ShaderStages::COMPUTE;
-    binding_1.descriptor_count = 1;
-    bindings.insert(1, binding_1);
+    for unified in unified_buffers.unified_buffers() {
+        let mut binding =
+            DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer);
+        binding.stages = ShaderStages::COMPUTE;
+        binding.descriptor_count = 1;
+        bindings.insert(unified.binding, binding);
+    }
 
     Ok(DescriptorSetLayout::new(
         device,
@@ -198,60 +287,71 @@ fn create_bindless_descriptor_set_layout(
     )?)
 }
 
-/// Push constants structure matching what the shaders expect
-/// Must match the layout in shaders/src/bindless.rs
-#[derive(Clone, Copy, vulkano::buffer::BufferContents)]
-#[repr(C)]
-struct PushConstants {
-    offset_0: u32,
-    offset_1: u32,
-    buffer_size: u32,
-    _padding: u32,
+/// The push constants of the entry points [`BindlessDispatchSpec::from_invocation_info`]
+/// can dispatch, which hold the offsets of up to two logical buffers
+#[derive(Clone, Copy)]
+enum OffsetsPushConstants {
+    /// `adder`: [`AdderPushConstants`] with [a_offset, b_offset]
+    Adder,
+    /// `step_particles`: [`ParticlePushConstants`] with [x_offset, v_offset]
+    ///
+    /// `wrap_particles`: [`ParticlePushConstants`] with [x_offset, 0 (unused)]
+    Particle,
 }
 
-/// Calculate push constants for a shader dispatch
-///
-/// Push constants contain the offsets where each logical buffer starts within
-/// the unified buffer. The format depends on which shader we're calling:
-/// - adder: [a_offset, b_offset]
-/// - step_particles: [x_offset, v_offset]
-/// - wrap_particles: [x_offset, 0 (unused)]
-fn calculate_push_constants(
-    dispatch: &BindlessShaderDispatch,
-    unified_buffers: &UnifiedBufferTracker,
-) -> CrateResult<PushConstants> {
-    // Determine the offsets based on which buffers this shader uses
-    let offsets: Vec<u32> = dispatch
-        .buffer_names
-        .iter()
-        .map(|name| {
-            // Try u32 buffers first
-            if let Some(&offset) = unified_buffers.u32_offsets.get(name) {
-                return offset;
-            }
-            // Then try Vec2 buffers
-            if let Some(&offset) = unified_buffers.vec2_offsets.get(name) {
-                return offset;
-            }
-            // If buffer not found, panic (this is a programmer error)
-            panic!(
-                "Buffer '{}' not found in unified buffers for dispatch '{}'",
-                name, dispatch.invocation_name
-            );
-        })
-        .collect();
-
-    // Create push constants struct
-    // If there's only one offset (like wrap_particles), pad with 0
-    let offset_0 = offsets.get(0).copied().unwrap_or(0);
-    let offset_1 = offsets.get(1).copied().unwrap_or(0);
+impl OffsetsPushConstants {
+    fn for_entry_point(entry_point_name: &str) -> CrateResult<Self> {
+        match entry_point_name {
+            "adder" => Ok(Self::Adder),
+            "step_particles" | "wrap_particles" => Ok(Self::Particle),
+            _ => Err(ChimeraError::KernelNotFound(entry_point_name.to_string())),
+        }
+    }
 
+    /// Size in bytes of the push constants
+    fn size(self) -> u32 {
+        let size = match self {
+            Self::Adder => std::mem::size_of::<AdderPushConstants>(),
+            Self::Particle => std::mem::size_of::<ParticlePushConstants>(),
+        };
+        size as u32
+    }
 
+    /// Calculate the push constants for a dispatch over `buffer_names`
+    ///
+    /// Push constants contain the offsets where each logical buffer starts within
+    /// its unified buffer, followed by `buffer_size`, the length of the shortest
+    /// logical buffer, so no invocation reads or writes past the end of any of them.
+    fn words(
+        self,
+        buffer_names: &[String],
+        unified_buffers: &UnifiedBufferTracker,
+    ) -> CrateResult<Vec<u32>> {
+        // Determine the offsets based on which buffers this shader uses
+        let logical_buffers = buffer_names
+            .iter()
+            .map(|name| unified_buffers.logical_buffer(name))
+            .collect::<CrateResult<Vec<_>>>()?;
+        let offsets: Vec<u32> = logical_buffers.iter().map(|l| l.offset).collect();
+        let buffer_size = logical_buffers.iter().map(|l| l.len).min().unwrap_or(0);
 
-    Ok(PushConstants {
-        offset_0,
-        offset_1,
-        buffer_size: unified_buffers.logical_buffer_size as u32,
-        _padding: 0,
-    })
+        // If there's only one offset (like wrap_particles), pad with 0
+        let offset_0 = offsets.first().copied().unwrap_or(0);
+        let offset_1 = offsets.get(1).copied().unwrap_or(0);
+        let words = match self {
+            Self::Adder => pod_to_words(&AdderPushConstants {
+                a_offset: offset_0,
+                b_offset: offset_1,
+                buffer_size,
+                _padding: 0,
+            }),
+            Self::Particle => pod_to_words(&ParticlePushConstants {
+                x_offset: offset_0,
+                v_offset: offset_1,
+                buffer_size,
+                _padding: 0,
+            }),
+        };
+        Ok(words)
+    }
 }
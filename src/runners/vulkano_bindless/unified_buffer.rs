@@ -1,174 +1,297 @@
 /// Unified buffer management for bindless rendering
 ///
 /// In the bindless approach, instead of creating separate GPU buffers for each
-/// logical buffer (a, b, c, d, x, v, etc.), we pack them all into two large
-/// "unified" buffers:
+/// logical buffer (a, b, c, d, x, v, etc.), we pack them into one large "unified"
+/// buffer per element type:
 /// 1. One unified buffer for all u32 data (a, b, c, d, etc.)
 /// 2. One unified buffer for all Vec2 data (x, v, etc.)
+/// 3. ...and so on for every other element type that is added
 ///
 /// Each logical buffer gets an offset within its unified buffer, and we track
 /// these offsets so we can tell the shader where to find each logical buffer.
-use crate::error::CrateResult;
-use glam::Vec2;
-use std::{collections::HashMap, sync::Arc};
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano_bindless::buffer::AnyBuffer,
+};
+use bytemuck::Pod;
+use std::{any::TypeId, collections::HashMap, sync::Arc};
 use vulkano::{
-    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
-    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    buffer::{BufferContents, Subbuffer},
+    descriptor_set::WriteDescriptorSet,
+    memory::allocator::StandardMemoryAllocator,
 };
 
+/// Default alignment (in bytes) of the start of each logical buffer.
+///
+/// 256 bytes is the largest `minStorageBufferOffsetAlignment` allowed by the Vulkan
+/// spec, so a logical buffer can always be bound on its own as well.
+pub const UNIFIED_OFFSET_ALIGNMENT: u64 = 256;
+
+/// Where a logical buffer lives within its unified buffer
+#[derive(Clone, Copy, Debug)]
+pub struct LogicalBuffer {
+    /// Index of the unified buffer in [`UnifiedBufferTracker::unified_buffers`]
+    pub unified_buffer: usize,
+    /// Offset (in elements) of the first element
+    pub offset: u32,
+    /// Number of elements
+    pub len: u32,
+}
+
+/// One unified buffer, holding every logical buffer of one element type
+#[derive(Clone)]
+pub struct UnifiedBuffer {
+    /// Binding of the unified buffer in the bindless descriptor set
+    pub binding: u32,
+    pub buffer: AnyBuffer,
+}
+
 /// Tracks where each logical buffer is located within the unified buffers
 pub struct UnifiedBufferTracker {
-    /// Offsets (in elements) for u32 logical buffers
-    pub u32_offsets: HashMap<String, u32>,
-    /// Offsets (in elements) for Vec2 logical buffers  
-    pub vec2_offsets: HashMap<String, u32>,
-    /// The actual unified u32 buffer on the GPU
-    pub unified_u32_buffer: Subbuffer<[u32]>,
-    /// The actual unified Vec2 buffer on the GPU
-    pub unified_vec2_buffer: Subbuffer<[Vec2]>,
-    /// Size of each logical buffer (they're all the same size in our case)
-    pub logical_buffer_size: usize,
+    /// The unified buffers on the GPU, in binding order
+    unified_buffers: Vec<UnifiedBuffer>,
+    logical_buffers: HashMap<String, LogicalBuffer>,
 }
 
 impl UnifiedBufferTracker {
-    /// Create unified buffers from the provided data
-    ///
-    /// This function:
-    /// 1. Determines which buffers contain u32 vs Vec2 data
-    /// 2. Packs all u32 buffers into one large buffer
-    /// 3. Packs all Vec2 buffers into one large buffer
-    /// 4. Records the offset of each logical buffer
+    /// Start packing logical buffers into unified buffers
     ///
-    /// # Arguments
-    /// * `memory_allocator` - Vulkan memory allocator
-    /// * `buffer_data` - Map of buffer names to their data (as slices)
-    pub fn new(
-        memory_allocator: Arc<StandardMemoryAllocator>,
-        a: &[u32],
-        b: &[u32],
-        c: &[u32],
-        d: &[u32],
-        x: &[Vec2],
-        v: &[Vec2],
-    ) -> CrateResult<Self> {
-        // All buffers must be the same size
-        let size = a.len();
-        assert_eq!(b.len(), size);
-        assert_eq!(c.len(), size);
-        assert_eq!(d.len(), size);
-        assert_eq!(x.len(), size);
-        assert_eq!(v.len(), size);
-
-        // Build unified u32 buffer by concatenating all u32 buffers
-        // Layout: [a_data][b_data][c_data][d_data]
-        let mut unified_u32_data = Vec::with_capacity(size * 4);
-        let mut u32_offsets = HashMap::new();
-
-        // Add buffer 'a' at offset 0
-        u32_offsets.insert("a".to_string(), 0u32);
-        unified_u32_data.extend_from_slice(a);
-
-        // Add buffer 'b' at offset size
-        u32_offsets.insert("b".to_string(), size as u32);
-        unified_u32_data.extend_from_slice(b);
-
-        // Add buffer 'c' at offset 2*size
-        u32_offsets.insert("c".to_string(), (size * 2) as u32);
-        unified_u32_data.extend_from_slice(c);
-
-        // Add buffer 'd' at offset 3*size
-        u32_offsets.insert("d".to_string(), (size * 3) as u32);
-        unified_u32_data.extend_from_slice(d);
-
-        // Build unified Vec2 buffer by concatenating all Vec2 buffers
-        // Layout: [x_data][v_data]
-        let mut unified_vec2_data = Vec::with_capacity(size * 2);
-        let mut vec2_offsets = HashMap::new();
-
-        // Add buffer 'x' at offset 0
-        vec2_offsets.insert("x".to_string(), 0u32);
-        unified_vec2_data.extend_from_slice(x);
-
-        // Add buffer 'v' at offset size
-        vec2_offsets.insert("v".to_string(), size as u32);
-        unified_vec2_data.extend_from_slice(v);
-
-        // Create the actual GPU buffers
-        let unified_u32_buffer = create_buffer(&memory_allocator, &unified_u32_data)?;
-        let unified_vec2_buffer = create_buffer(&memory_allocator, &unified_vec2_data)?;
-
-        Ok(Self {
-            u32_offsets,
-            vec2_offsets,
-            unified_u32_buffer,
-            unified_vec2_buffer,
-            logical_buffer_size: size,
-        })
+    /// ```ignore
+    /// let unified_buffers = UnifiedBufferTracker::builder()
+    ///     .with_buffer("a", &a)
+    ///     .with_buffer("b", &b)
+    ///     .with_buffer("x", &x)
+    ///     .build(memory_allocator)?;
+    /// ```
+    pub fn builder() -> UnifiedBufferTrackerBuilder {
+        UnifiedBufferTrackerBuilder::default()
     }
 
-    /// Get the offset for a u32 buffer
-    pub fn get_u32_offset(&self, buffer_name: &str) -> u32 {
-        *self
-            .u32_offsets
-            .get(buffer_name)
-            .unwrap_or_else(|| panic!("u32 buffer '{}' not found in unified buffer", buffer_name))
+    /// The unified buffers, in binding order
+    pub fn unified_buffers(&self) -> &[UnifiedBuffer] {
+        &self.unified_buffers
     }
 
-    /// Get the offset for a Vec2 buffer
-    pub fn get_vec2_offset(&self, buffer_name: &str) -> u32 {
-        *self
-            .vec2_offsets
+    /// Descriptor writes binding every unified buffer at its binding
+    pub fn write_descriptor_sets(&self) -> Vec<WriteDescriptorSet> {
+        self.unified_buffers
+            .iter()
+            .map(|u| u.buffer.write_descriptor_set_for_binding(u.binding))
+            .collect()
+    }
+
+    pub fn contains(&self, buffer_name: &str) -> bool {
+        self.logical_buffers.contains_key(buffer_name)
+    }
+
+    pub fn logical_buffer(&self, buffer_name: &str) -> CrateResult<LogicalBuffer> {
+        self.logical_buffers
             .get(buffer_name)
-            .unwrap_or_else(|| panic!("Vec2 buffer '{}' not found in unified buffer", buffer_name))
+            .copied()
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(buffer_name.to_string()))
     }
 
-    /// Read back a u32 logical buffer from the unified buffer
-    pub fn read_u32_buffer(&self, buffer_name: &str) -> Vec<u32> {
-        let offset = self.get_u32_offset(buffer_name) as usize;
-        let size = self.logical_buffer_size;
+    /// Get the offset (in elements) of a logical buffer within its unified buffer
+    pub fn offset(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.logical_buffer(buffer_name)?.offset)
+    }
 
-        let content = self
-            .unified_u32_buffer
-            .read()
-            .expect("Failed to read unified u32 buffer");
+    /// Get the number of elements of a logical buffer
+    pub fn len(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.logical_buffer(buffer_name)?.len)
+    }
 
-        content[offset..offset + size].to_vec()
+    /// The elements of a logical buffer, as a view into its unified buffer
+    pub fn subbuffer<T: BufferContents + Pod>(
+        &self,
+        buffer_name: &str,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        let logical = self.logical_buffer(buffer_name)?;
+        let buffer = &self.unified_buffers[logical.unified_buffer].buffer;
+        if !buffer.is::<T>() {
+            return Err(ChimeraError::BufferTypeMismatch {
+                name: buffer_name.to_string(),
+                stored: buffer.type_name().to_string(),
+                requested: std::any::type_name::<T>().to_string(),
+            });
+        }
+        let start = logical.offset as u64;
+        Ok(buffer
+            .downcast::<T>()?
+            .slice(start..start + logical.len as u64))
     }
 
-    /// Read back a Vec2 logical buffer from the unified buffer
-    pub fn read_vec2_buffer(&self, buffer_name: &str) -> Vec<Vec2> {
-        let offset = self.get_vec2_offset(buffer_name) as usize;
-        let size = self.logical_buffer_size;
+    /// Read back a logical buffer from its unified buffer
+    pub fn read<T: BufferContents + Pod>(&self, buffer_name: &str) -> CrateResult<Vec<T>> {
+        Ok(self.subbuffer::<T>(buffer_name)?.read()?.to_vec())
+    }
 
-        let content = self
-            .unified_vec2_buffer
-            .read()
-            .expect("Failed to read unified Vec2 buffer");
+    /// Overwrite a logical buffer with `data`, which must have the logical buffer's length
+    pub fn write<T: BufferContents + Pod>(&self, buffer_name: &str, data: &[T]) -> CrateResult<()> {
+        let subbuffer = self.subbuffer::<T>(buffer_name)?;
+        let mut content = subbuffer.write()?;
+        if content.len() != data.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: content.len(),
+                data_len: data.len(),
+            });
+        }
+        content.copy_from_slice(data);
+        Ok(())
+    }
+}
 
-        content[offset..offset + size].to_vec()
+/// The element type of one unified buffer while it is being packed
+struct UnifiedBufferData {
+    type_id: TypeId,
+    element_size: usize,
+    upload: fn(Arc<StandardMemoryAllocator>, &[u8]) -> CrateResult<AnyBuffer>,
+}
+
+/// A logical buffer added to the builder, packed into its unified buffer on `build`
+struct PendingBuffer {
+    name: String,
+    /// Index of its unified buffer in the builder
+    unified_buffer: usize,
+    bytes: Vec<u8>,
+}
+
+fn upload_unified_buffer<T: BufferContents + Pod>(
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    bytes: &[u8],
+) -> CrateResult<AnyBuffer> {
+    // `bytes` is not necessarily aligned for `T`, so copy rather than cast
+    let mut data = vec![T::zeroed(); bytes.len() / std::mem::size_of::<T>()];
+    bytemuck::cast_slice_mut::<T, u8>(&mut data).copy_from_slice(bytes);
+    AnyBuffer::from_data(memory_allocator, &data)
+}
+
+/// Builder for a [`UnifiedBufferTracker`]
+///
+/// Logical buffers may have any length and any `BufferContents + Pod` element type.
+/// Buffers with the same element type are packed into the same unified buffer, and
+/// unified buffers are bound in the order their element type is first added.
+pub struct UnifiedBufferTrackerBuilder {
+    alignment: u64,
+    unified_buffers: Vec<UnifiedBufferData>,
+    logical_buffers: Vec<PendingBuffer>,
+}
+
+impl Default for UnifiedBufferTrackerBuilder {
+    fn default() -> Self {
+        Self {
+            alignment: UNIFIED_OFFSET_ALIGNMENT,
+            unified_buffers: Vec::new(),
+            logical_buffers: Vec::new(),
+        }
+    }
+}
+
+impl UnifiedBufferTrackerBuilder {
+    /// Align the start of each logical buffer to `alignment` bytes (rounded up to
+    /// whole elements), whether added before or after this call
+    pub fn with_alignment(mut self, alignment: u64) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    /// Add a logical buffer named `name`, initialised with `data`
+    pub fn with_buffer<T: BufferContents + Pod>(
+        mut self,
+        name: impl Into<String>,
+        data: &[T],
+    ) -> Self {
+        let index = match self
+            .unified_buffers
+            .iter()
+            .position(|u| u.type_id == TypeId::of::<T>())
+        {
+            Some(index) => index,
+            None => {
+                self.unified_buffers.push(UnifiedBufferData {
+                    type_id: TypeId::of::<T>(),
+                    element_size: std::mem::size_of::<T>(),
+                    upload: upload_unified_buffer::<T>,
+                });
+                self.unified_buffers.len() - 1
+            }
+        };
+        self.logical_buffers.push(PendingBuffer {
+            name: name.into(),
+            unified_buffer: index,
+            bytes: bytemuck::cast_slice(data).to_vec(),
+        });
+        self
+    }
+
+    /// Pack the logical buffers at aligned offsets and upload every unified buffer to
+    /// the GPU
+    pub fn build(
+        self,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> CrateResult<UnifiedBufferTracker> {
+        let mut packed: Vec<Vec<u8>> = vec![Vec::new(); self.unified_buffers.len()];
+        let mut logical_buffers = HashMap::new();
+        for pending in self.logical_buffers {
+            let element_size = self.unified_buffers[pending.unified_buffer].element_size;
+            let bytes = &mut packed[pending.unified_buffer];
+
+            // Pad with zeroed elements up to the next aligned offset
+            let aligned_len =
+                aligned_offset(bytes.len() / element_size, element_size, self.alignment);
+            bytes.resize(aligned_len * element_size, 0);
+            bytes.extend_from_slice(&pending.bytes);
+
+            let logical = LogicalBuffer {
+                unified_buffer: pending.unified_buffer,
+                offset: aligned_len as u32,
+                len: (pending.bytes.len() / element_size) as u32,
+            };
+            if logical_buffers
+                .insert(pending.name.clone(), logical)
+                .is_some()
+            {
+                return Err(ChimeraError::DuplicateBufferName(pending.name));
+            }
+        }
+
+        let unified_buffers = self
+            .unified_buffers
+            .iter()
+            .zip(packed)
+            .enumerate()
+            .map(|(binding, (unified, mut bytes))| {
+                // Vulkan does not allow empty buffers
+                if bytes.is_empty() {
+                    bytes.resize(unified.element_size, 0);
+                }
+                Ok(UnifiedBuffer {
+                    binding: binding as u32,
+                    buffer: (unified.upload)(memory_allocator.clone(), &bytes)?,
+                })
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        Ok(UnifiedBufferTracker {
+            unified_buffers,
+            logical_buffers,
+        })
     }
 }
 
-/// Helper function to create a GPU buffer from data
-fn create_buffer<T: BufferContents + Copy>(
-    memory_allocator: &Arc<StandardMemoryAllocator>,
-    data: &[T],
-) -> CrateResult<Subbuffer<[T]>> {
-    let usage = BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST;
-
-    let buffer = Buffer::from_iter(
-        memory_allocator.clone(),
-        BufferCreateInfo {
-            usage,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            memory_type_filter: MemoryTypeFilter::PREFER_HOST
-                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
-            ..Default::default()
-        },
-        data.iter().copied(),
-    )?;
-
-    Ok(buffer)
+/// Round `len` (in elements) up to the next offset that is a multiple of
+/// `alignment` bytes and of whole elements.
+fn aligned_offset(len: usize, element_size: usize, alignment: u64) -> usize {
+    let alignment = alignment as usize;
+    let step_bytes = alignment / gcd(alignment, element_size) * element_size;
+    let step = step_bytes / element_size;
+    len.div_ceil(step) * step
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
@@ -1,26 +1,35 @@
 use crate::{
-    error::CrateResult,
-    runners::vulkano::{
-        buffer_specs::{DescriptorSetByName, IntoDescriptorSetByName},
-        device::compute_capable_device_and_queue,
-        shader::shader_module,
-        shader_pipeline_builder::ShaderPipelineSpec,
-        typed_subbuffer_by_name::TypedSubbufferByName,
+    error::{ChimeraError, CrateResult},
+    runners::{
+        compute_backend::{write_range_bounds, ComputeBackend},
+        vulkano::{
+            buffer_registry::{BufferRegistry, RegistryBufferSpecs},
+            buffer_specs::{DescriptorSetByName, IntoDescriptorSetByName},
+            chain_recorder::{submit, ChainRecorder},
+            dependency_graph::DependencyGraph,
+            device::compute_capable_device_and_queue,
+            frames_in_flight::{FramesInFlight, Snapshots, Submission, DEFAULT_FRAMES_IN_FLIGHT},
+            profiling::{ChainProfile, ChainProfiler},
+            shader::shader_module,
+            shader_pipeline_builder::{Ready, ShaderPipelineBuilder, ShaderPipelineSpec},
+            staging::Staging,
+            typed_subbuffer_by_name::TypedSubbufferByName,
+        },
     },
 };
+use bytemuck::Pod;
+use parking_lot::Mutex;
 use std::sync::Arc;
 
 use vulkano::{
-    buffer::Subbuffer,
+    buffer::{BufferContents, Subbuffer},
     command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        PrimaryAutoCommandBuffer,
+        allocator::StandardCommandBufferAllocator, sys::CommandBuffer, CommandBufferUsage,
     },
     descriptor_set::allocator::StandardDescriptorSetAllocator,
     device::{Device, Queue},
     instance::Instance,
     memory::allocator::StandardMemoryAllocator,
-    sync::{self, GpuFuture},
 };
 
 #[allow(unused)]
@@ -28,6 +37,10 @@ pub struct VulkanoComputeChain<BS>
 where
     BS: IntoDescriptorSetByName<Out: 'static + DescriptorSetByName + TypedSubbufferByName>,
 {
+    /// Submissions the host has not waited for yet; dropped first, so that dropping the
+    /// chain waits for them before freeing their buffers
+    frames: Mutex<FramesInFlight>,
+
     instance: Arc<Instance>,
     device: Arc<Device>,
     queue: Arc<Queue>,
@@ -35,46 +48,80 @@ where
     memory_allocator: Arc<StandardMemoryAllocator>,
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    /// Uploads and downloads of the buffers the host can't map
+    staging: Staging,
 
     gpu_buffer_specs: <BS as IntoDescriptorSetByName>::Out,
     pipeline_specs: Vec<ShaderPipelineSpec>,
 
-    command_buffer: Arc<PrimaryAutoCommandBuffer>,
+    pipelines: Vec<ShaderPipelineBuilder<Ready>>,
+    /// Which invocations wait for which, from the specs' buffer access annotations
+    dependency_graph: DependencyGraph,
+    /// One per frame in flight, recorded once, unless some kernel has per-execution
+    /// push constants; then the command buffer is recorded again from `pipelines` on
+    /// every submission.
+    command_buffers: Option<Vec<Arc<CommandBuffer>>>,
+    /// How many times each submission runs the chain
+    substeps: u32,
+    /// Copies of the double-buffered buffers, made by each frame in flight
+    snapshots: Snapshots,
+    /// Timestamps around each invocation, if profiling is on and supported
+    profiler: Option<Mutex<ChainProfiler>>,
 }
 
-pub fn build_compute_pass_command_buffer<T: DescriptorSetByName>(
-    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+/// Build the pipelines and descriptor sets for every invocation of the chain
+pub fn build_ready_pipelines<T: DescriptorSetByName + TypedSubbufferByName>(
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
-    queue: Arc<Queue>,
     device: Arc<Device>,
     shader_module: Arc<vulkano::shader::ShaderModule>,
     gpu_buffer_specs: &T,
-    pipeline_specs: &Vec<ShaderPipelineSpec>,
-) -> CrateResult<Arc<PrimaryAutoCommandBuffer>> {
-    let mut builder = AutoCommandBufferBuilder::primary(
-        command_buffer_allocator.clone(),
-        queue.queue_family_index(),
-        CommandBufferUsage::MultipleSubmit,
-    )?;
-
-    for spec in pipeline_specs.iter() {
-        let pipeline = spec
-            .to_builder()
-            .with_entry_point(shader_module.clone())?
-            .with_descriptor_set_layout(device.clone())?
-            .with_pipeline(device.clone())?
-            .with_descriptor_set(gpu_buffer_specs, descriptor_set_allocator.clone())?;
-        pipeline.bind_and_dispatch(&mut builder)?;
-    }
-
-    Ok(builder.build()?)
+    pipeline_specs: &[ShaderPipelineSpec],
+) -> CrateResult<Vec<ShaderPipelineBuilder<Ready>>> {
+    pipeline_specs
+        .iter()
+        .map(|spec| {
+            spec.to_builder()
+                .with_entry_point(shader_module.clone())?
+                .with_descriptor_set_layout(device.clone())?
+                .with_pipeline(device.clone())?
+                .with_descriptor_set(gpu_buffer_specs, descriptor_set_allocator.clone())
+        })
+        .collect()
 }
 
 impl<BS> VulkanoComputeChain<BS>
 where
     BS: IntoDescriptorSetByName<Out: 'static + DescriptorSetByName + TypedSubbufferByName>,
 {
-    pub fn typed_subbuffer_by_name<T: 'static>(&self, name: &str) -> CrateResult<Subbuffer<[T]>> {
+    /// The chain's own buffer `name`, for the host to access once every submission
+    /// finished
+    ///
+    /// Fails with [`ChimeraError::DeviceLocalBuffer`] if the buffer is
+    /// [`Residency::DeviceLocal`], which the host can't map: use [`Self::readback`] and
+    /// [`Self::write_buffer`] instead.
+    ///
+    /// [`Residency::DeviceLocal`]:
+    ///     crate::runners::vulkano::buffer_specs::Residency::DeviceLocal
+    pub fn typed_subbuffer_by_name<T: BufferContents>(
+        &self,
+        name: &str,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        let buffer = self.buffer::<T>(name)?;
+        if !self
+            .gpu_buffer_specs
+            .residency_by_name(name)?
+            .is_host_visible()
+        {
+            return Err(ChimeraError::DeviceLocalBuffer(name.to_string()));
+        }
+        // the chain's submissions are not tracked by vulkano, which can't tell that
+        // they still access the buffer
+        self.sync()?;
+        Ok(buffer)
+    }
+
+    /// The chain's own buffer `name`, wherever it lives
+    fn buffer<T: 'static>(&self, name: &str) -> CrateResult<Subbuffer<[T]>> {
         self.gpu_buffer_specs.subbuffer::<T>(name)
     }
 
@@ -101,49 +148,411 @@ where
 
         println!("Allocators created");
 
-        let buffer_specs_for_gpu = buffer_specs.with_gpu_buffer(memory_allocator.clone())?;
+        let staging = Staging::new(
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            queue.clone(),
+        );
+        let buffer_specs_for_gpu = buffer_specs.with_gpu_buffer(&staging)?;
 
         pipeline_specs
             .iter()
             .try_for_each(|spec| spec.validate_against_buffer_specs(&buffer_specs_for_gpu))?;
 
-        let command_buffer = build_compute_pass_command_buffer(
-            command_buffer_allocator.clone(),
+        let dependency_graph = DependencyGraph::new(&pipeline_specs);
+
+        let pipelines = build_ready_pipelines(
             descriptor_set_allocator.clone(),
-            queue.clone(),
             device.clone(),
             shader_module.clone(),
             &buffer_specs_for_gpu,
             &pipeline_specs,
         )?;
 
+        let frames = FramesInFlight::new(DEFAULT_FRAMES_IN_FLIGHT)?;
+        let snapshots = Snapshots::new(memory_allocator.clone(), frames.len(), &[])?;
+
         println!("VulkanoRunner::new ok");
-        Ok(Self {
+        let mut chain = Self {
             instance,
             device,
             queue,
             memory_allocator,
             descriptor_set_allocator,
             command_buffer_allocator,
+            staging,
             shader_module,
 
             gpu_buffer_specs: buffer_specs_for_gpu,
 
             // buffer_specs: buffer_specs.clone(),
             pipeline_specs,
-            command_buffer,
-        })
+            pipelines,
+            dependency_graph,
+            command_buffers: None,
+            substeps: 1,
+            frames: Mutex::new(frames),
+            snapshots,
+            profiler: None,
+        };
+        chain.record_command_buffers()?;
+        Ok(chain)
+    }
+
+    /// Keep `frames_in_flight` submissions running before [`Self::submit`] blocks, and
+    /// have each of them copy the buffers named in `double_buffered` for
+    /// [`Self::snapshot`]
+    pub fn with_frames_in_flight(
+        mut self,
+        frames_in_flight: usize,
+        double_buffered: &[&'static str],
+    ) -> CrateResult<Self> {
+        self.sync()?;
+        let frames = FramesInFlight::new(frames_in_flight)?;
+        let buffers = double_buffered
+            .iter()
+            .map(|name| Ok((*name, self.gpu_buffer_specs.bytes_by_name(name)?)))
+            .collect::<CrateResult<Vec<_>>>()?;
+        self.snapshots = Snapshots::new(self.memory_allocator.clone(), frames.len(), &buffers)?;
+        self.frames = Mutex::new(frames);
+        self.record_command_buffers()?;
+        Ok(self)
+    }
+
+    /// Run the chain `substeps` times in a row in every submission, with no round trip
+    /// to the host in between
+    pub fn with_substeps(mut self, substeps: u32) -> CrateResult<Self> {
+        self.set_substeps(substeps)?;
+        Ok(self)
+    }
+
+    /// Change how many times the chain runs per submission, from the next submission on
+    pub fn set_substeps(&mut self, substeps: u32) -> CrateResult<()> {
+        if substeps == 0 {
+            return Err(ChimeraError::ZeroSubsteps);
+        }
+        self.substeps = substeps;
+        // submissions in flight keep their command buffers alive
+        self.record_command_buffers()
+    }
+
+    pub fn substeps(&self) -> u32 {
+        self.substeps
     }
 
+    /// Time each invocation on the GPU, keeping the last `window` submissions in
+    /// [`Self::profile`]
+    ///
+    /// Without timestamp support on the chain's queue, the chain runs unprofiled and
+    /// [`Self::profile`] returns `None`.
+    pub fn with_profiling(mut self, window: usize) -> CrateResult<Self> {
+        let profile = ChainProfile::new(
+            self.pipeline_specs
+                .iter()
+                .map(ShaderPipelineSpec::invocation_name),
+            window,
+        );
+        let frames_in_flight = self.frames.get_mut().len();
+        let profiler = ChainProfiler::new(
+            self.device.clone(),
+            &self.queue,
+            frames_in_flight,
+            self.substeps,
+            profile,
+        )?;
+        self.sync()?;
+        self.profiler = profiler.map(Mutex::new);
+        self.record_command_buffers()?;
+        Ok(self)
+    }
+
+    /// The GPU time of each invocation over the last submissions that finished, if
+    /// profiling
+    pub fn profile(&self) -> CrateResult<Option<ChainProfile>> {
+        let Some(profiler) = &self.profiler else {
+            return Ok(None);
+        };
+        let frames = self.frames.lock();
+        let mut profiler = profiler.lock();
+        for slot in 0..frames.len() {
+            if frames.is_slot_complete(slot)? {
+                profiler.collect(slot)?;
+            }
+        }
+        Ok(Some(profiler.profile().clone()))
+    }
+
+    /// Record the command buffer of every frame in flight, unless they have to be
+    /// recorded on every submission
+    fn record_command_buffers(&mut self) -> CrateResult<()> {
+        let frames_in_flight = self.frames.get_mut().len();
+        if let Some(profiler) = &mut self.profiler {
+            // the pools may be replaced, read them first
+            let profiler = profiler.get_mut();
+            self.frames.get_mut().wait_all()?;
+            for slot in 0..profiler.len() {
+                profiler.collect(slot)?;
+            }
+            profiler.reallocate(frames_in_flight, self.substeps)?;
+        }
+
+        let has_per_execution_push_constants = self.pipeline_specs.iter().any(|spec| {
+            spec.kernel_config()
+                .push_constants()
+                .is_some_and(|pc| pc.is_per_execution())
+        });
+        self.command_buffers = if has_per_execution_push_constants {
+            None
+        } else {
+            Some(
+                (0..frames_in_flight)
+                    .map(|slot| self.command_buffer(slot, CommandBufferUsage::MultipleSubmit))
+                    .collect::<CrateResult<_>>()?,
+            )
+        };
+        Ok(())
+    }
+
+    /// The command buffer of frame in flight `slot`, which also copies the
+    /// double-buffered buffers into the slot's snapshots, and writes the slot's
+    /// timestamps if profiling
+    fn command_buffer(
+        &self,
+        slot: usize,
+        usage: CommandBufferUsage,
+    ) -> CrateResult<Arc<CommandBuffer>> {
+        let copies = self
+            .snapshots
+            .slot(slot)
+            .map(|(name, snapshot)| {
+                Ok((self.gpu_buffer_specs.bytes_by_name(name)?, snapshot.clone()))
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+        let timestamps = self
+            .profiler
+            .as_ref()
+            .map(|profiler| profiler.lock().pool(slot).clone());
+        // a barrier only where the dependency graph needs one
+        ChainRecorder::new(&self.pipelines, &self.dependency_graph)
+            .with_substeps(self.substeps)
+            .with_copies(copies)
+            .with_timestamps(timestamps)
+            .record(self.command_buffer_allocator.clone(), &self.queue, usage)
+    }
+
+    /// Run the chain once per substep, and wait for it to finish
     pub fn execute(&self) -> CrateResult<()> {
-        let future = sync::now(self.device.clone())
-            .then_execute(self.queue.clone(), self.command_buffer.clone())?
-            .then_signal_fence_and_flush()?;
-        future.wait(None)?;
+        let submission = self.submit()?;
+        self.wait(submission)
+    }
+
+    /// Run the chain once per substep without waiting for it to finish
+    ///
+    /// Blocks only while all frames in flight are still running, until the oldest one
+    /// finished. Submissions run in order, each after the previous one.
+    pub fn submit(&self) -> CrateResult<Submission> {
+        let mut frames = self.frames.lock();
+        let slot = frames.acquire()?;
+        if let Some(profiler) = &self.profiler {
+            // before the slot's timestamps are written again
+            profiler.lock().collect(slot)?;
+        }
+        let command_buffer = match &self.command_buffers {
+            Some(command_buffers) => command_buffers[slot].clone(),
+            None => self.command_buffer(slot, CommandBufferUsage::OneTimeSubmit)?,
+        };
+        let fence = submit(self.device.clone(), &self.queue, command_buffer.clone())?;
+        if let Some(profiler) = &self.profiler {
+            profiler.lock().submitted(slot);
+        }
+        Ok(frames.submitted(slot, fence, command_buffer))
+    }
+
+    /// Block until `submission` finished
+    pub fn wait(&self, submission: Submission) -> CrateResult<()> {
+        self.frames.lock().wait(submission)
+    }
+
+    /// Whether `submission` finished, without blocking
+    pub fn is_complete(&self, submission: Submission) -> CrateResult<bool> {
+        self.frames.lock().is_complete(submission)
+    }
+
+    /// Block until every submission finished, so the host can read the buffers
+    pub fn sync(&self) -> CrateResult<()> {
+        self.frames.lock().wait_all()
+    }
+
+    /// Wait for every submission, then copy buffer `name` back to the host, through a
+    /// staging buffer if it is device-local
+    pub fn readback<T: Pod + Send + Sync>(&self, name: &str) -> CrateResult<Vec<T>> {
+        self.sync()?;
+        let buffer = self.buffer::<T>(name)?;
+        let residency = self.gpu_buffer_specs.residency_by_name(name)?;
+        self.staging.read(&buffer, residency)
+    }
 
+    /// Wait for every submission, then overwrite buffer `name` with `data`, which must
+    /// have the buffer's length; through a staging buffer if it is device-local
+    pub fn write_buffer<T: Pod + Send + Sync>(&self, name: &str, data: &[T]) -> CrateResult<()> {
+        let buffer_len = self.buffer::<T>(name)?.len() as usize;
+        if buffer_len != data.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len,
+                data_len: data.len(),
+            });
+        }
+        self.write_range(name, 0, data)
+    }
+
+    /// Wait for every submission, then overwrite the elements of buffer `name` from
+    /// `offset` on with `data`; through a staging buffer if it is device-local
+    ///
+    /// Snapshots only see the new contents once the next submission copied them.
+    pub fn write_range<T: Pod + Send + Sync>(
+        &self,
+        name: &str,
+        offset: usize,
+        data: &[T],
+    ) -> CrateResult<()> {
+        let buffer = self.buffer::<T>(name)?;
+        let range = write_range_bounds(name, buffer.len() as usize, offset, data.len())?;
+        if range.is_empty() {
+            return Ok(());
+        }
+        let residency = self.gpu_buffer_specs.residency_by_name(name)?;
+        self.sync()?;
+        let target = buffer.slice(range.start as u64..range.end as u64);
+        self.staging.write(&target, residency, data)
+    }
+
+    /// Grow or shrink buffer `name` to `new_len` elements, keeping its contents up to the
+    /// new length and filling any new elements with `value`
+    ///
+    /// Waits for every submission first. The invocations that bind the buffer, or
+    /// dispatch indirectly from it, get new descriptor sets, workgroup counts and push
+    /// constants over the buffer; its snapshots are allocated again if it is
+    /// double-buffered, and the command buffers are recorded again. Until the next
+    /// submission, [`Self::snapshot`] has no snapshot of a resized buffer to return.
+    ///
+    /// Fails with [`ChimeraError::FixedInvocationOverResizedBuffer`] if an invocation
+    /// that uses the buffer has fixed push constants or a grid dispatch size. On failure
+    /// the chain keeps the buffer it had.
+    pub fn resize_buffer<T: Pod + Send + Sync>(
+        &mut self,
+        name: &str,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<()> {
+        if new_len == 0 {
+            return Err(ChimeraError::ZeroLengthBuffer(name.to_string()));
+        }
+        self.pipeline_specs
+            .iter()
+            .try_for_each(|spec| spec.validate_resize(name))?;
+        let buffer = self.buffer::<T>(name)?;
+        let residency = self.gpu_buffer_specs.residency_by_name(name)?;
+        self.sync()?;
+        let resized = self.staging.resized(&buffer, residency, new_len, value)?;
+
+        let replaced = self.gpu_buffer_specs.replace_subbuffer(name, resized)?;
+        if let Err(e) = self.rebind(name) {
+            self.gpu_buffer_specs.replace_subbuffer(name, replaced)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Rebuild what depends on buffer `name` after it was replaced, committing nothing
+    /// unless everything was rebuilt
+    fn rebind(&mut self, name: &str) -> CrateResult<()> {
+        self.pipeline_specs
+            .iter()
+            .try_for_each(|spec| spec.validate_against_buffer_specs(&self.gpu_buffer_specs))?;
+
+        let pipelines = self
+            .pipelines
+            .iter()
+            .map(|pipeline| {
+                // the indirect buffer of a dispatch is not bound, but is recorded too
+                let uses_buffer = pipeline
+                    .spec()
+                    .buffer_accesses()
+                    .iter()
+                    .any(|(buf_name, _)| *buf_name == name);
+                if uses_buffer {
+                    pipeline.with_descriptor_set_rebuilt(
+                        &self.gpu_buffer_specs,
+                        self.descriptor_set_allocator.clone(),
+                    )
+                } else {
+                    Ok(pipeline.clone())
+                }
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        let snapshots = if self.snapshots.names().any(|snapshot| snapshot == name) {
+            let buffers = self
+                .snapshots
+                .names()
+                .map(|snapshot| Ok((snapshot, self.gpu_buffer_specs.bytes_by_name(snapshot)?)))
+                .collect::<CrateResult<Vec<_>>>()?;
+            let frames_in_flight = self.frames.get_mut().len();
+            Some(Snapshots::new(
+                self.memory_allocator.clone(),
+                frames_in_flight,
+                &buffers,
+            )?)
+        } else {
+            None
+        };
+
+        if snapshots.is_some() {
+            // the submissions made so far copied into the old snapshots
+            self.frames.get_mut().retire_all()?;
+        }
+        let pipelines = std::mem::replace(&mut self.pipelines, pipelines);
+        let snapshots =
+            snapshots.map(|snapshots| std::mem::replace(&mut self.snapshots, snapshots));
+        if let Err(e) = self.record_command_buffers() {
+            self.pipelines = pipelines;
+            if let Some(snapshots) = snapshots {
+                self.snapshots = snapshots;
+            }
+            self.record_command_buffers()?;
+            return Err(e);
+        }
         Ok(())
     }
 
+    /// Number of elements of buffer `name`
+    pub fn buffer_len(&self, name: &str) -> CrateResult<usize> {
+        self.gpu_buffer_specs.len_by_name(name)
+    }
+
+    /// The copy of the double-buffered buffer `name` made by the newest submission that
+    /// finished, which later submissions leave alone while at most `frames_in_flight - 1`
+    /// of them are made
+    ///
+    /// Waits for the oldest submission if none finished yet.
+    pub fn snapshot<T: BufferContents>(&self, name: &str) -> CrateResult<Subbuffer<[T]>> {
+        // checks the element type
+        self.buffer::<T>(name)?;
+        let frames = self.frames.lock();
+        let (_, slot) = frames.wait_latest_complete()?.ok_or_else(|| {
+            ChimeraError::Other(format!("no submission has made a snapshot of `{name}`"))
+        })?;
+        Ok(self.snapshots.get(slot, name)?.clone().reinterpret::<[T]>())
+    }
+
+    /// The dependencies between the chain's invocations, and where it records barriers
+    ///
+    /// Its [`DependencyGraph::warnings`] name the invocations that write a buffer with
+    /// no ordering between them.
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        &self.dependency_graph
+    }
+
     /// Get the Vulkan instance (needed for creating windows/surfaces)
     pub fn instance(&self) -> &Arc<Instance> {
         &self.instance
@@ -164,3 +573,71 @@ where
         &self.memory_allocator
     }
 }
+
+impl VulkanoComputeChain<RegistryBufferSpecs<'_>> {
+    /// Upload `data` into a new buffer of the chain's registry.
+    ///
+    /// The buffer can be read and written right away; invocations only bind it once the
+    /// chain is built again with specs that use it. Fails with
+    /// [`ChimeraError::DuplicateBufferName`] if the registry already has a buffer named
+    /// `name`: change existing buffers with [`Self::write_buffer`] and
+    /// [`Self::resize_buffer`], which keep the invocations that bind them in sync.
+    pub fn insert_buffer<T: BufferContents + Pod>(
+        &mut self,
+        name: impl Into<String>,
+        binding: u32,
+        data: &[T],
+    ) -> CrateResult<()> {
+        self.gpu_buffer_specs
+            .insert_data(self.memory_allocator.clone(), name, binding, data)
+    }
+
+    pub fn buffer_registry(&self) -> &BufferRegistry {
+        &self.gpu_buffer_specs
+    }
+}
+
+impl<BS> ComputeBackend for VulkanoComputeChain<BS>
+where
+    BS: IntoDescriptorSetByName<Out: 'static + DescriptorSetByName + TypedSubbufferByName>,
+{
+    type BufferSpecs = BS;
+
+    fn create(buffer_specs: &BS, pipeline_specs: Vec<ShaderPipelineSpec>) -> CrateResult<Self> {
+        Self::new(buffer_specs, pipeline_specs)
+    }
+
+    fn run_chain(&self) -> CrateResult<()> {
+        self.execute()
+    }
+
+    fn set_substeps(&mut self, substeps: u32) -> CrateResult<()> {
+        VulkanoComputeChain::set_substeps(self, substeps)
+    }
+
+    fn read_buffer<T: Pod + Send + Sync>(&self, name: &str) -> CrateResult<Vec<T>> {
+        self.readback::<T>(name)
+    }
+
+    fn write_buffer<T: Pod + Send + Sync>(&self, name: &str, data: &[T]) -> CrateResult<()> {
+        VulkanoComputeChain::write_buffer(self, name, data)
+    }
+
+    fn resize_buffer<T: Pod + Send + Sync>(
+        &mut self,
+        name: &str,
+        new_len: usize,
+        value: T,
+    ) -> CrateResult<()> {
+        VulkanoComputeChain::resize_buffer(self, name, new_len, value)
+    }
+
+    fn write_range<T: Pod + Send + Sync>(
+        &self,
+        name: &str,
+        offset: usize,
+        data: &[T],
+    ) -> CrateResult<()> {
+        VulkanoComputeChain::write_range(self, name, offset, data)
+    }
+}
@@ -0,0 +1,78 @@
+pub mod buffers;
+pub mod shader_buffer_mapping;
+
+use self::buffers::DescriptorArrayBuffers;
+use self::shader_buffer_mapping::DescriptorArrayComputePass;
+use crate::{
+    error::CrateResult,
+    runners::{
+        vulkano::buffer::AnyBuffer,
+        vulkano_separate_buffers::{BindingModel, SeparateBuffersRunner},
+    },
+};
+
+use bytemuck::Pod;
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::DeviceFeatures,
+    memory::allocator::StandardMemoryAllocator,
+};
+
+/// The descriptor indexing binding model
+///
+/// The fourth binding model, beside the bindful runners,
+/// [`VulkanoBindlessRunner`](super::VulkanoBindlessRunner) and
+/// [`VulkanoDeviceAddressRunner`](super::VulkanoDeviceAddressRunner):
+/// - Every logical buffer is its own allocation, as in the bindful approach
+/// - A single descriptor binding holds a runtime-sized array of all of them; shaders
+///   get the slot of each buffer in push constants and index the array with it
+///
+/// The runner uses the entry points of the `descriptor_array::` shader module, which
+/// with its descriptor set layout needs a runtime array of storage buffers, indexed
+/// dynamically, allocated with a variable descriptor count, partially bound and
+/// updatable after binding.
+pub struct DescriptorArray;
+
+/// Vulkan-based runner for compute shaders using descriptor indexing
+pub type VulkanoDescriptorArrayRunner = SeparateBuffersRunner<DescriptorArray>;
+
+impl BindingModel for DescriptorArray {
+    const RUNNER_NAME: &'static str = "VulkanoDescriptorArrayRunner";
+
+    fn device_features() -> DeviceFeatures {
+        DeviceFeatures {
+            runtime_descriptor_array: true,
+            shader_storage_buffer_array_dynamic_indexing: true,
+            descriptor_binding_storage_buffer_update_after_bind: true,
+            descriptor_binding_partially_bound: true,
+            descriptor_binding_variable_descriptor_count: true,
+            ..DeviceFeatures::empty()
+        }
+    }
+
+    fn upload<T: BufferContents + Pod>(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: &[T],
+    ) -> CrateResult<AnyBuffer> {
+        AnyBuffer::from_data(memory_allocator, data)
+    }
+
+    fn record_compute_pass(
+        runner: &VulkanoDescriptorArrayRunner,
+        buffers: &DescriptorArrayBuffers,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        DescriptorArrayComputePass::new(
+            runner.device().clone(),
+            runner.compute_pass_info(),
+            runner.descriptor_set_allocator().clone(),
+            buffers,
+        )?
+        .dispatch_all(builder, num_workgroups)
+    }
+}
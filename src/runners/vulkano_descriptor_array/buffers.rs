@@ -0,0 +1,33 @@
+/// Logical buffers for the descriptor indexing binding model
+///
+/// Every logical buffer is its own allocation, and gets a slot: its index in the
+/// runtime array of storage buffer descriptors the shaders see at
+/// [`DESCRIPTOR_ARRAY_BINDING`](super::shader_buffer_mapping::DESCRIPTOR_ARRAY_BINDING).
+/// Slots are assigned in the order the buffers are added to the builder.
+use super::DescriptorArray;
+use crate::{error::CrateResult, runners::vulkano_separate_buffers::buffers::SeparateBuffers};
+use vulkano::descriptor_set::WriteDescriptorSet;
+
+/// Named logical buffers, each in its own allocation and descriptor array slot
+pub type DescriptorArrayBuffers = SeparateBuffers<DescriptorArray>;
+
+impl DescriptorArrayBuffers {
+    /// Number of slots in use, i.e. the variable descriptor count of the array
+    pub fn num_slots(&self) -> u32 {
+        self.buffers().len() as u32
+    }
+
+    /// The slot of a logical buffer in the descriptor array, to pass to shaders
+    pub fn slot(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.index(buffer_name)? as u32)
+    }
+
+    /// Write every buffer to its slot of the descriptor array at `binding`
+    pub fn write_descriptor_set(&self, binding: u32) -> WriteDescriptorSet {
+        WriteDescriptorSet::buffer_array(
+            binding,
+            0,
+            self.buffers().iter().map(|buffer| buffer.bytes().clone()),
+        )
+    }
+}
@@ -0,0 +1,265 @@
+/// Compute pipelines for the descriptor indexing binding model
+///
+/// Every pipeline shares one descriptor set layout: a single runtime-sized array of
+/// storage buffer descriptors at [`DESCRIPTOR_ARRAY_BINDING`], created with
+/// `descriptor_indexing` binding flags (update-after-bind, partially bound, variable
+/// descriptor count). The descriptor set is written once with every logical buffer
+/// in its slot, and each dispatch only pushes the slots of the buffers it uses.
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::{
+        vulkano::{
+            shader::{shader_entry_point, spirv_shader_module},
+            shader_buffer_mapping::ComputePassInvocationInfo,
+        },
+        vulkano_bindless::pipeline::build_pipeline,
+        vulkano_descriptor_array::buffers::DescriptorArrayBuffers,
+    },
+};
+use shared::{AdderSlotPushConstants, ParticleSlotPushConstants};
+use std::sync::Arc;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorBindingFlags, DescriptorSetLayout, DescriptorSetLayoutBinding,
+            DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo, DescriptorType,
+        },
+        DescriptorSet,
+    },
+    device::Device,
+    pipeline::{ComputePipeline, Pipeline, PipelineBindPoint},
+    shader::ShaderStages,
+};
+
+/// Module path of the descriptor indexing entry points in the shader module
+const ENTRY_POINT_PREFIX: &str = "descriptor_array::";
+
+/// Binding of the storage buffer descriptor array
+pub const DESCRIPTOR_ARRAY_BINDING: u32 = 0;
+
+/// Upper bound on the number of slots in the descriptor array, further clamped to
+/// the device's `maxPerStageDescriptorUpdateAfterBindStorageBuffers`
+pub const MAX_BUFFER_SLOTS: u32 = 1024;
+
+/// Push constants of the dispatches built from a [`ComputePassInvocationInfo`]: the
+/// ones of `adder` or of the particle kernels, the only entry points with slots of up
+/// to two buffers
+#[derive(Clone, Copy)]
+enum SlotPushConstants {
+    Adder(AdderSlotPushConstants),
+    Particle(ParticleSlotPushConstants),
+}
+
+impl SlotPushConstants {
+    /// Size in bytes, for the push constant range of the pipeline layout
+    fn size(&self) -> u32 {
+        let size = match self {
+            Self::Adder(_) => std::mem::size_of::<AdderSlotPushConstants>(),
+            Self::Particle(_) => std::mem::size_of::<ParticleSlotPushConstants>(),
+        };
+        size as u32
+    }
+}
+
+/// A pipeline of the descriptor indexing compute pass and the slots it is dispatched
+/// with
+struct DescriptorArrayShaderDispatch {
+    pipeline: Arc<ComputePipeline>,
+    push_constants: SlotPushConstants,
+}
+
+/// A complete descriptor indexing compute pass: one descriptor set holding every
+/// logical buffer, and one pipeline and set of slots per invocation
+pub struct DescriptorArrayComputePass {
+    descriptor_set: Arc<DescriptorSet>,
+    dispatches: Vec<DescriptorArrayShaderDispatch>,
+}
+
+impl DescriptorArrayComputePass {
+    /// Build the descriptor array over `buffers`, and a pipeline for every invocation
+    /// of `compute_pass_info` (with entry points in the `descriptor_array::` module)
+    pub fn new(
+        device: Arc<Device>,
+        compute_pass_info: &ComputePassInvocationInfo,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        buffers: &DescriptorArrayBuffers,
+    ) -> CrateResult<Self> {
+        let capacity = descriptor_array_capacity(&device);
+        if buffers.num_slots() > capacity {
+            return Err(ChimeraError::TooManyBufferSlots {
+                requested: buffers.num_slots() as usize,
+                capacity,
+            });
+        }
+        let descriptor_set_layout = descriptor_array_layout(device.clone(), capacity)?;
+
+        // Only the slots in use are allocated (and written); the shaders never index
+        // past them
+        let descriptor_set = DescriptorSet::new_variable(
+            descriptor_set_allocator,
+            descriptor_set_layout.clone(),
+            buffers.num_slots(),
+            [buffers.write_descriptor_set(DESCRIPTOR_ARRAY_BINDING)],
+            [],
+        )?;
+
+        let shader_module =
+            spirv_shader_module(device.clone(), crate::DESCRIPTOR_ARRAY_SHADERS_SPIRV)?;
+        let dispatches = compute_pass_info
+            .pipelines
+            .iter()
+            .map(|template| {
+                let push_constants = slot_push_constants(
+                    &template.invocation_name,
+                    &template.entry_point_name,
+                    &template.buf_names,
+                    buffers,
+                )?;
+                let entry_point = shader_entry_point(
+                    shader_module.clone(),
+                    &format!("{ENTRY_POINT_PREFIX}{}", template.entry_point_name),
+                )?;
+                let pipeline = build_pipeline(
+                    device.clone(),
+                    descriptor_set_layout.clone(),
+                    entry_point,
+                    push_constants.size(),
+                )?;
+
+                Ok(DescriptorArrayShaderDispatch {
+                    pipeline,
+                    push_constants,
+                })
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        Ok(Self {
+            descriptor_set,
+            dispatches,
+        })
+    }
+
+    /// Dispatch all compute shaders in the pass
+    ///
+    /// Every pipeline has the same layout, but the descriptor set is bound per dispatch
+    /// like the other runners do, which keeps each dispatch self-contained.
+    pub fn dispatch_all(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        for dispatch in &self.dispatches {
+            let layout = dispatch.pipeline.layout().clone();
+            builder.bind_pipeline_compute(dispatch.pipeline.clone())?;
+            builder.bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                layout.clone(),
+                0,
+                self.descriptor_set.clone(),
+            )?;
+            match dispatch.push_constants {
+                SlotPushConstants::Adder(push_constants) => {
+                    builder.push_constants(layout, 0, push_constants)?
+                }
+                SlotPushConstants::Particle(push_constants) => {
+                    builder.push_constants(layout, 0, push_constants)?
+                }
+            };
+            unsafe {
+                builder.dispatch(num_workgroups)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of slots of the descriptor array layout on `device`
+///
+/// An update-after-bind array counts against both the per-stage and the per-set
+/// update-after-bind limits, so it gets the smaller of the two.
+pub fn descriptor_array_capacity(device: &Device) -> u32 {
+    let properties = device.physical_device().properties();
+    [
+        properties.max_per_stage_descriptor_update_after_bind_storage_buffers,
+        properties.max_descriptor_set_update_after_bind_storage_buffers,
+    ]
+    .into_iter()
+    .flatten()
+    .fold(MAX_BUFFER_SLOTS, u32::min)
+}
+
+/// The layout of a runtime array of up to `capacity` storage buffers at
+/// [`DESCRIPTOR_ARRAY_BINDING`]
+fn descriptor_array_layout(
+    device: Arc<Device>,
+    capacity: u32,
+) -> CrateResult<Arc<DescriptorSetLayout>> {
+    let mut binding = DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer);
+    binding.stages = ShaderStages::COMPUTE;
+    binding.descriptor_count = capacity;
+    binding.binding_flags = DescriptorBindingFlags::UPDATE_AFTER_BIND
+        | DescriptorBindingFlags::PARTIALLY_BOUND
+        | DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+
+    let layout = DescriptorSetLayout::new(
+        device,
+        DescriptorSetLayoutCreateInfo {
+            flags: DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL,
+            bindings: [(DESCRIPTOR_ARRAY_BINDING, binding)].into(),
+            ..Default::default()
+        },
+    )?;
+    Ok(layout)
+}
+
+/// The slots of an invocation's buffers, in order, and the length of the shortest one,
+/// in the push constants of `entry_point_name`
+fn slot_push_constants(
+    invocation_name: &str,
+    entry_point_name: &str,
+    buffer_names: &[String],
+    buffers: &DescriptorArrayBuffers,
+) -> CrateResult<SlotPushConstants> {
+    if !(1..=2).contains(&buffer_names.len()) {
+        return Err(ChimeraError::PushConstantBufferCountOutOfRange {
+            invocation_name: invocation_name.to_string(),
+            min: 1,
+            max: 2,
+            found: buffer_names.len(),
+        });
+    }
+    let slots = buffer_names
+        .iter()
+        .map(|name| buffers.slot(name))
+        .collect::<CrateResult<Vec<_>>>()?;
+    let buffer_size = buffer_names
+        .iter()
+        .map(|name| buffers.len(name))
+        .collect::<CrateResult<Vec<_>>>()?
+        .into_iter()
+        .min()
+        .unwrap_or(0);
+
+    // If there's only one buffer (like wrap_particles), the second slot is unused
+    let slot_0 = slots[0];
+    let slot_1 = slots.get(1).copied().unwrap_or(0);
+    match entry_point_name {
+        "adder" => Ok(SlotPushConstants::Adder(AdderSlotPushConstants {
+            a: slot_0,
+            b: slot_1,
+            buffer_size,
+            _padding: 0,
+        })),
+        "step_particles" | "wrap_particles" => {
+            Ok(SlotPushConstants::Particle(ParticleSlotPushConstants {
+                x: slot_0,
+                v: slot_1,
+                buffer_size,
+                _padding: 0,
+            }))
+        }
+        _ => Err(ChimeraError::KernelNotFound(entry_point_name.to_string())),
+    }
+}
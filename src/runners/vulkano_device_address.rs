@@ -0,0 +1,68 @@
+pub mod buffers;
+pub mod shader_buffer_mapping;
+
+use self::buffers::{build_and_fill_addressable_buffer, DeviceAddressBuffers};
+use self::shader_buffer_mapping::DeviceAddressComputePass;
+use crate::{
+    error::CrateResult,
+    runners::{
+        vulkano::buffer::AnyBuffer,
+        vulkano_separate_buffers::{BindingModel, SeparateBuffersRunner},
+    },
+};
+
+use bytemuck::Pod;
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::DeviceFeatures,
+    memory::allocator::StandardMemoryAllocator,
+};
+
+/// The buffer device address binding model
+///
+/// The third binding model, beside the bindful runners and
+/// [`VulkanoBindlessRunner`](super::VulkanoBindlessRunner):
+/// - Every logical buffer is its own allocation, as in the bindful approach
+/// - No descriptor sets at all: shaders get the `VkDeviceAddress` of each buffer in
+///   push constants and dereference it as a physical storage buffer pointer
+///
+/// The runner uses the entry points of the `device_address::` shader module, which
+/// needs `buffer_device_address` and `shader_int64` to take buffer addresses as u64
+/// and dereference them.
+pub struct DeviceAddress;
+
+/// Vulkan-based runner for compute shaders using buffer device addresses
+pub type VulkanoDeviceAddressRunner = SeparateBuffersRunner<DeviceAddress>;
+
+impl BindingModel for DeviceAddress {
+    const RUNNER_NAME: &'static str = "VulkanoDeviceAddressRunner";
+
+    fn device_features() -> DeviceFeatures {
+        DeviceFeatures {
+            buffer_device_address: true,
+            shader_int64: true,
+            ..DeviceFeatures::empty()
+        }
+    }
+
+    fn upload<T: BufferContents + Pod>(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: &[T],
+    ) -> CrateResult<AnyBuffer> {
+        Ok(build_and_fill_addressable_buffer(memory_allocator, data)?.into())
+    }
+
+    fn record_compute_pass(
+        runner: &VulkanoDeviceAddressRunner,
+        buffers: &DeviceAddressBuffers,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        DeviceAddressComputePass::new(runner.device().clone(), runner.compute_pass_info(), buffers)?
+            .dispatch_all(builder, num_workgroups)
+    }
+}
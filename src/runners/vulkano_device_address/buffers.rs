@@ -0,0 +1,49 @@
+/// Logical buffers for the buffer device address binding model
+///
+/// Every logical buffer is its own allocation, created with
+/// `BufferUsage::SHADER_DEVICE_ADDRESS` so that shaders can reach it through its
+/// `VkDeviceAddress` instead of a descriptor binding.
+use super::DeviceAddress;
+use crate::{error::CrateResult, runners::vulkano_separate_buffers::buffers::SeparateBuffers};
+use std::sync::Arc;
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+};
+
+/// Named logical buffers, each in its own device-addressable allocation
+pub type DeviceAddressBuffers = SeparateBuffers<DeviceAddress>;
+
+/// Upload `data` into a new host-visible storage buffer that shaders can address
+pub fn build_and_fill_addressable_buffer<T: BufferContents + Copy>(
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    data: &[T],
+) -> CrateResult<Subbuffer<[T]>> {
+    let usage = BufferUsage::STORAGE_BUFFER
+        | BufferUsage::SHADER_DEVICE_ADDRESS
+        | BufferUsage::TRANSFER_SRC
+        | BufferUsage::TRANSFER_DST;
+
+    let buffer: Subbuffer<[T]> = Buffer::from_iter(
+        memory_allocator,
+        BufferCreateInfo {
+            usage,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        data.iter().copied(),
+    )?;
+
+    Ok(buffer)
+}
+
+impl DeviceAddressBuffers {
+    /// The `VkDeviceAddress` of a logical buffer, to pass to shaders
+    pub fn address(&self, buffer_name: &str) -> CrateResult<u64> {
+        Ok(self.buffer(buffer_name)?.bytes().device_address()?.get())
+    }
+}
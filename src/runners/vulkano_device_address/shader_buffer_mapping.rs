@@ -0,0 +1,192 @@
+/// Compute pipelines for the buffer device address binding model
+///
+/// The pipelines have no descriptor sets at all: every logical buffer a dispatch
+/// uses is passed to the shader as its `VkDeviceAddress`, in push constants.
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::{
+        vulkano::{
+            shader::{shader_entry_point, spirv_shader_module},
+            shader_buffer_mapping::ComputePassInvocationInfo,
+        },
+        vulkano_device_address::buffers::DeviceAddressBuffers,
+    },
+};
+use shared::{AdderAddressPushConstants, ParticleAddressPushConstants};
+use std::sync::Arc;
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Device,
+    pipeline::{
+        compute::{ComputePipeline, ComputePipelineCreateInfo},
+        layout::{PipelineLayout, PipelineLayoutCreateInfo, PushConstantRange},
+        Pipeline, PipelineShaderStageCreateInfo,
+    },
+    shader::ShaderStages,
+};
+
+/// Module path of the device address entry points in the shader module
+const ENTRY_POINT_PREFIX: &str = "device_address::";
+
+/// Push constants of the dispatches built from a [`ComputePassInvocationInfo`]: the
+/// ones of `adder` or of the particle kernels, the only entry points with addresses
+/// of up to two buffers
+#[derive(Clone, Copy)]
+enum AddressPushConstants {
+    Adder(AdderAddressPushConstants),
+    Particle(ParticleAddressPushConstants),
+}
+
+impl AddressPushConstants {
+    /// Size in bytes, for the push constant range of the pipeline layout
+    fn size(&self) -> u32 {
+        let size = match self {
+            Self::Adder(_) => std::mem::size_of::<AdderAddressPushConstants>(),
+            Self::Particle(_) => std::mem::size_of::<ParticleAddressPushConstants>(),
+        };
+        size as u32
+    }
+}
+
+/// A pipeline of the device address compute pass and the buffer addresses it is
+/// dispatched with
+struct DeviceAddressShaderDispatch {
+    pipeline: Arc<ComputePipeline>,
+    push_constants: AddressPushConstants,
+}
+
+/// A complete device address compute pass: one pipeline and one set of buffer
+/// addresses per invocation
+pub struct DeviceAddressComputePass {
+    dispatches: Vec<DeviceAddressShaderDispatch>,
+}
+
+impl DeviceAddressComputePass {
+    /// Build a pipeline for every invocation of `compute_pass_info` (with entry points
+    /// in the `device_address::` module), and look up the addresses of its buffers
+    pub fn new(
+        device: Arc<Device>,
+        compute_pass_info: &ComputePassInvocationInfo,
+        buffers: &DeviceAddressBuffers,
+    ) -> CrateResult<Self> {
+        let shader_module =
+            spirv_shader_module(device.clone(), crate::DEVICE_ADDRESS_SHADERS_SPIRV)?;
+
+        let dispatches = compute_pass_info
+            .pipelines
+            .iter()
+            .map(|template| {
+                let push_constants = address_push_constants(
+                    &template.invocation_name,
+                    &template.entry_point_name,
+                    &template.buf_names,
+                    buffers,
+                )?;
+                let entry_point = shader_entry_point(
+                    shader_module.clone(),
+                    &format!("{ENTRY_POINT_PREFIX}{}", template.entry_point_name),
+                )?;
+                let stage = PipelineShaderStageCreateInfo::new(entry_point);
+                let layout = PipelineLayout::new(
+                    device.clone(),
+                    PipelineLayoutCreateInfo {
+                        set_layouts: vec![],
+                        push_constant_ranges: vec![PushConstantRange {
+                            stages: ShaderStages::COMPUTE,
+                            offset: 0,
+                            size: push_constants.size(),
+                        }],
+                        ..Default::default()
+                    },
+                )?;
+                let pipeline = ComputePipeline::new(
+                    device.clone(),
+                    None,
+                    ComputePipelineCreateInfo::stage_layout(stage, layout),
+                )?;
+
+                Ok(DeviceAddressShaderDispatch {
+                    pipeline,
+                    push_constants,
+                })
+            })
+            .collect::<CrateResult<Vec<_>>>()?;
+
+        Ok(Self { dispatches })
+    }
+
+    /// Dispatch all compute shaders in the pass; there is nothing to bind but the
+    /// pipeline and the push constants
+    pub fn dispatch_all(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()> {
+        for dispatch in &self.dispatches {
+            let layout = dispatch.pipeline.layout().clone();
+            builder.bind_pipeline_compute(dispatch.pipeline.clone())?;
+            match dispatch.push_constants {
+                AddressPushConstants::Adder(push_constants) => {
+                    builder.push_constants(layout, 0, push_constants)?
+                }
+                AddressPushConstants::Particle(push_constants) => {
+                    builder.push_constants(layout, 0, push_constants)?
+                }
+            };
+            unsafe {
+                builder.dispatch(num_workgroups)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The addresses of an invocation's buffers, in order, and the length of the
+/// shortest one, in the push constants of `entry_point_name`
+fn address_push_constants(
+    invocation_name: &str,
+    entry_point_name: &str,
+    buffer_names: &[String],
+    buffers: &DeviceAddressBuffers,
+) -> CrateResult<AddressPushConstants> {
+    if !(1..=2).contains(&buffer_names.len()) {
+        return Err(ChimeraError::PushConstantBufferCountOutOfRange {
+            invocation_name: invocation_name.to_string(),
+            min: 1,
+            max: 2,
+            found: buffer_names.len(),
+        });
+    }
+    let addresses = buffer_names
+        .iter()
+        .map(|name| buffers.address(name))
+        .collect::<CrateResult<Vec<_>>>()?;
+    let buffer_size = buffer_names
+        .iter()
+        .map(|name| buffers.len(name))
+        .collect::<CrateResult<Vec<_>>>()?
+        .into_iter()
+        .min()
+        .unwrap_or(0);
+
+    // If there's only one buffer (like wrap_particles), pad with a null address
+    let address_0 = addresses[0];
+    let address_1 = addresses.get(1).copied().unwrap_or(0);
+    match entry_point_name {
+        "adder" => Ok(AddressPushConstants::Adder(AdderAddressPushConstants {
+            a: address_0,
+            b: address_1,
+            buffer_size,
+            _padding: 0,
+        })),
+        "step_particles" | "wrap_particles" => Ok(AddressPushConstants::Particle(
+            ParticleAddressPushConstants {
+                x: address_0,
+                v: address_1,
+                buffer_size,
+                _padding: 0,
+            },
+        )),
+        _ => Err(ChimeraError::KernelNotFound(entry_point_name.to_string())),
+    }
+}
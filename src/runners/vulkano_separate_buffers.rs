@@ -0,0 +1,203 @@
+pub mod buffers;
+
+use self::buffers::SeparateBuffers;
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano::{
+        buffer::AnyBuffer, device::compute_capable_device_and_queue_with_features,
+        shader_buffer_mapping::ComputePassInvocationInfo,
+    },
+};
+
+use bytemuck::Pod;
+use glam::Vec2;
+use shared::WORKGROUP_SIZE;
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
+        PrimaryAutoCommandBuffer,
+    },
+    descriptor_set::allocator::StandardDescriptorSetAllocator,
+    device::{Device, DeviceFeatures, Queue},
+    instance::Instance,
+    memory::allocator::StandardMemoryAllocator,
+    sync::{self, GpuFuture},
+};
+
+/// A binding model in which every logical buffer is its own allocation, and shaders
+/// find the buffers of a dispatch through push constants, e.g. by address
+/// ([`DeviceAddress`](super::vulkano_device_address::DeviceAddress)) or by slot in a
+/// descriptor array ([`DescriptorArray`](super::vulkano_descriptor_array::DescriptorArray))
+pub trait BindingModel: Sized + 'static {
+    /// Name of the runner, for [`ChimeraError::UnsupportedDeviceFeatures`]
+    const RUNNER_NAME: &'static str;
+
+    /// Device features the model's shader module and pipelines need
+    fn device_features() -> DeviceFeatures;
+
+    /// Upload `data` into a new storage buffer that the model can pass to shaders
+    fn upload<T: BufferContents + Pod>(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: &[T],
+    ) -> CrateResult<AnyBuffer>;
+
+    /// Build a pipeline for every invocation of `compute_pass_info` over `buffers`,
+    /// and record a dispatch of each over `num_workgroups` into `builder`
+    fn record_compute_pass(
+        runner: &SeparateBuffersRunner<Self>,
+        buffers: &SeparateBuffers<Self>,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        num_workgroups: [u32; 3],
+    ) -> CrateResult<()>;
+}
+
+/// Vulkan-based runner for compute shaders with every logical buffer in its own
+/// allocation, passed to shaders as the binding model `M` prescribes
+///
+/// Unlike [`VulkanoBindlessRunner`](super::VulkanoBindlessRunner), buffers are not
+/// packed into unified buffers, so [`Self::run_compute_and_get_buffer`] can hand the
+/// renderer the x buffer as is. It runs the same [`ComputePassInvocationInfo`] as the
+/// bindless runner.
+pub struct SeparateBuffersRunner<M: BindingModel> {
+    instance: Arc<Instance>,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    /// Information about the compute pass (shader entry points, buffer mappings, etc.)
+    compute_pass_info: ComputePassInvocationInfo,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    _binding_model: std::marker::PhantomData<M>,
+}
+
+impl<M: BindingModel> SeparateBuffersRunner<M> {
+    /// Create a new runner on a device with [`BindingModel::device_features`]
+    ///
+    /// Fails with [`ChimeraError::UnsupportedDeviceFeatures`] if there is no such
+    /// device.
+    pub fn new(compute_pass_info: ComputePassInvocationInfo) -> CrateResult<Self> {
+        let (instance, _device_name, device, queue) =
+            compute_capable_device_and_queue_with_features(M::RUNNER_NAME, M::device_features())?;
+
+        // If the binding model enables buffer_device_address, the standard allocator
+        // allocates memory that buffers can take a device address of
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        // The allocator creates update-after-bind pools for update-after-bind layouts
+        let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
+            device.clone(),
+            Default::default(),
+        ));
+        let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+            device.clone(),
+            Default::default(),
+        ));
+
+        Ok(Self {
+            instance,
+            device,
+            queue,
+            compute_pass_info,
+            memory_allocator,
+            descriptor_set_allocator,
+            command_buffer_allocator,
+            _binding_model: std::marker::PhantomData,
+        })
+    }
+
+    /// Run the compute pass over the buffers a..d, x and v and return the x buffer for
+    /// graphics rendering, like [`super::VulkanoBindlessRunner::run_compute_and_get_buffer`]
+    ///
+    /// Fails with [`ChimeraError::BufferLengthMismatch`] if `a` and `b` have different
+    /// lengths.
+    pub fn run_compute_and_get_buffer(
+        &self,
+        a: &mut [u32],
+        b: &[u32],
+        c: &[u32],
+        d: &[u32],
+        x: &mut [Vec2],
+        v: &[Vec2],
+    ) -> CrateResult<(Subbuffer<[Vec2]>, usize)> {
+        if a.len() != b.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: a.len(),
+                data_len: b.len(),
+            });
+        }
+        let len = a.len();
+        let num_workgroups = (len as u32).div_ceil(WORKGROUP_SIZE);
+
+        let buffers = SeparateBuffers::<M>::builder()
+            .with_buffer("a", a)
+            .with_buffer("b", b)
+            .with_buffer("c", c)
+            .with_buffer("d", d)
+            .with_buffer("x", x)
+            .with_buffer("v", v)
+            .build(self.memory_allocator.clone())?;
+
+        self.run_compute(&buffers, num_workgroups)?;
+
+        a.copy_from_slice(&buffers.read::<u32>("a")?);
+        x.copy_from_slice(&buffers.read::<Vec2>("x")?);
+
+        // x is its own allocation, so the renderer can use it as is
+        Ok((buffers.subbuffer::<Vec2>("x")?, len))
+    }
+
+    /// Run the compute pass once over `buffers`, every dispatch over `num_workgroups`
+    pub fn run_compute(
+        &self,
+        buffers: &SeparateBuffers<M>,
+        num_workgroups: u32,
+    ) -> CrateResult<()> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.command_buffer_allocator.clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        M::record_compute_pass(self, buffers, &mut builder, [num_workgroups, 1, 1])?;
+        let command_buffer = builder.build()?;
+
+        let future = sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?;
+        future.wait(None)?;
+
+        Ok(())
+    }
+
+    /// Information about the compute pass (shader entry points, buffer mappings, etc.)
+    pub fn compute_pass_info(&self) -> &ComputePassInvocationInfo {
+        &self.compute_pass_info
+    }
+
+    /// Get the Vulkan instance (needed for creating windows/surfaces)
+    pub fn instance(&self) -> &Arc<Instance> {
+        &self.instance
+    }
+
+    /// Get the device (useful for creating graphics resources on the same device)
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Get the queue (useful for graphics rendering on the same queue)
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Get the memory allocator
+    pub fn memory_allocator(&self) -> &Arc<StandardMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    /// Get the descriptor set allocator
+    pub fn descriptor_set_allocator(&self) -> &Arc<StandardDescriptorSetAllocator> {
+        &self.descriptor_set_allocator
+    }
+}
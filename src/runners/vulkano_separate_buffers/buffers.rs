@@ -0,0 +1,156 @@
+/// Logical buffers for the binding models of
+/// [`SeparateBuffersRunner`](super::SeparateBuffersRunner)
+///
+/// Every logical buffer is its own allocation, uploaded by the binding model so that
+/// its shaders can reach it. Buffers are kept in the order they are added to the
+/// builder, which the descriptor array model uses as their slots.
+use super::BindingModel;
+use crate::{
+    error::{ChimeraError, CrateResult},
+    runners::vulkano::buffer::AnyBuffer,
+};
+use bytemuck::Pod;
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+use vulkano::{
+    buffer::{BufferContents, Subbuffer},
+    memory::allocator::StandardMemoryAllocator,
+};
+
+type UploadFn = Box<dyn FnOnce(Arc<StandardMemoryAllocator>) -> CrateResult<AnyBuffer>>;
+
+/// Named logical buffers, each in its own allocation
+pub struct SeparateBuffers<M: BindingModel> {
+    /// In the order they were added
+    buffers: Vec<AnyBuffer>,
+    indices: HashMap<String, usize>,
+    _binding_model: PhantomData<M>,
+}
+
+impl<M: BindingModel> SeparateBuffers<M> {
+    /// Start collecting logical buffers
+    ///
+    /// ```ignore
+    /// let buffers = DeviceAddressBuffers::builder()
+    ///     .with_buffer("a", &a)
+    ///     .with_buffer("x", &x)
+    ///     .build(memory_allocator)?;
+    /// ```
+    pub fn builder() -> SeparateBuffersBuilder<M> {
+        SeparateBuffersBuilder::default()
+    }
+
+    pub fn contains(&self, buffer_name: &str) -> bool {
+        self.indices.contains_key(buffer_name)
+    }
+
+    /// Position of a logical buffer in the order the buffers were added
+    pub(crate) fn index(&self, buffer_name: &str) -> CrateResult<usize> {
+        self.indices
+            .get(buffer_name)
+            .copied()
+            .ok_or_else(|| ChimeraError::DescriptorSetNameNotFound(buffer_name.to_string()))
+    }
+
+    /// Every logical buffer, in the order they were added
+    pub(crate) fn buffers(&self) -> &[AnyBuffer] {
+        &self.buffers
+    }
+
+    pub fn buffer(&self, buffer_name: &str) -> CrateResult<&AnyBuffer> {
+        Ok(&self.buffers[self.index(buffer_name)?])
+    }
+
+    /// Get the number of elements of a logical buffer
+    pub fn len(&self, buffer_name: &str) -> CrateResult<u32> {
+        Ok(self.buffer(buffer_name)?.len() as u32)
+    }
+
+    /// A logical buffer as `Subbuffer<[T]>`, or [`ChimeraError::BufferTypeMismatch`]
+    /// if it holds another element type.
+    pub fn subbuffer<T: BufferContents + Pod>(
+        &self,
+        buffer_name: &str,
+    ) -> CrateResult<Subbuffer<[T]>> {
+        let buffer = self.buffer(buffer_name)?;
+        if !buffer.is::<T>() {
+            return Err(ChimeraError::BufferTypeMismatch {
+                name: buffer_name.to_string(),
+                stored: buffer.type_name().to_string(),
+                requested: std::any::type_name::<T>().to_string(),
+            });
+        }
+        buffer.downcast::<T>()
+    }
+
+    /// Read back a logical buffer
+    pub fn read<T: BufferContents + Pod>(&self, buffer_name: &str) -> CrateResult<Vec<T>> {
+        Ok(self.subbuffer::<T>(buffer_name)?.read()?.to_vec())
+    }
+
+    /// Overwrite a logical buffer with `data`, which must have the logical buffer's length
+    pub fn write<T: BufferContents + Pod>(&self, buffer_name: &str, data: &[T]) -> CrateResult<()> {
+        let subbuffer = self.subbuffer::<T>(buffer_name)?;
+        let mut content = subbuffer.write()?;
+        if content.len() != data.len() {
+            return Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: content.len(),
+                data_len: data.len(),
+            });
+        }
+        content.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Builder for [`SeparateBuffers`]
+pub struct SeparateBuffersBuilder<M: BindingModel> {
+    buffers: Vec<(String, UploadFn)>,
+    _binding_model: PhantomData<M>,
+}
+
+impl<M: BindingModel> Default for SeparateBuffersBuilder<M> {
+    fn default() -> Self {
+        Self {
+            buffers: Vec::new(),
+            _binding_model: PhantomData,
+        }
+    }
+}
+
+impl<M: BindingModel> SeparateBuffersBuilder<M> {
+    /// Add a logical buffer named `name`, initialised with `data` (which must not be
+    /// empty, as Vulkan does not allow empty buffers)
+    pub fn with_buffer<T: BufferContents + Pod>(
+        mut self,
+        name: impl Into<String>,
+        data: &[T],
+    ) -> Self {
+        let data = data.to_vec();
+        self.buffers.push((
+            name.into(),
+            Box::new(move |memory_allocator| M::upload(memory_allocator, &data)),
+        ));
+        self
+    }
+
+    /// Upload every logical buffer to the GPU
+    pub fn build(
+        self,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> CrateResult<SeparateBuffers<M>> {
+        let mut buffers = Vec::with_capacity(self.buffers.len());
+        let mut indices = HashMap::new();
+        for (name, upload) in self.buffers {
+            if indices.contains_key(&name) {
+                return Err(ChimeraError::DuplicateBufferName(name));
+            }
+            indices.insert(name, buffers.len());
+            buffers.push(upload(memory_allocator.clone())?);
+        }
+        Ok(SeparateBuffers {
+            buffers,
+            indices,
+            _binding_model: PhantomData,
+        })
+    }
+}
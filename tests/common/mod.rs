@@ -0,0 +1,85 @@
+// Helpers shared by the integration tests of the Vulkano runners
+//
+// Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use glam::Vec2;
+use rust_gpu_chimera_demo::runners::vulkano::shader_buffer_mapping::ComputePassInvocationInfo;
+use rust_gpu_chimera_demo::runners::vulkano_separate_buffers::{
+    BindingModel, SeparateBuffersRunner,
+};
+use rust_gpu_chimera_demo::runners::VulkanoBindlessRunner;
+
+/// The full compute pass, as in main.rs
+pub fn full_compute_pass_info() -> ComputePassInvocationInfo {
+    let adder_kernel = ("adder", vec![0, 1]);
+    let step_particles_kernel = ("step_particles", vec![2, 3]);
+    let wrap_particles_kernel = ("wrap_particles", vec![2]);
+
+    ComputePassInvocationInfo::from_lists(vec![
+        ("adder_ab", vec!["a", "b"], adder_kernel.clone()),
+        ("adder_ac", vec!["a", "c"], adder_kernel.clone()),
+        ("adder_ad", vec!["a", "d"], adder_kernel.clone()),
+        (
+            "step_particles_0",
+            vec!["x", "v"],
+            step_particles_kernel.clone(),
+        ),
+        (
+            "step_particles_1",
+            vec!["x", "v"],
+            step_particles_kernel.clone(),
+        ),
+        (
+            "step_particles_2",
+            vec!["x", "v"],
+            step_particles_kernel.clone(),
+        ),
+        (
+            "step_particles_3",
+            vec!["x", "v"],
+            step_particles_kernel.clone(),
+        ),
+        ("wrap_particles", vec!["x"], wrap_particles_kernel.clone()),
+    ])
+}
+
+/// Run the full compute pass with the binding model `M` and with the bindless runner,
+/// and check that both produce the same buffers
+pub fn assert_matches_bindless<M: BindingModel>() {
+    let n = 1000;
+    let a: Vec<u32> = (0..n as u32).collect();
+    let b: Vec<u32> = (0..n as u32).map(|i| 2 * i).collect();
+    let c = vec![100u32; n];
+    let d: Vec<u32> = (0..n as u32).map(|i| i % 7).collect();
+    let x: Vec<Vec2> = (0..n)
+        .map(|i| Vec2::new(i as f32 / n as f32, 0.5))
+        .collect();
+    let v: Vec<Vec2> = (0..n).map(|i| Vec2::new(0.01, -0.001 * i as f32)).collect();
+
+    let runner = SeparateBuffersRunner::<M>::new(full_compute_pass_info())
+        .unwrap_or_else(|e| panic!("Failed to create {}: {e}", M::RUNNER_NAME));
+    let (mut a_separate, mut x_separate) = (a.clone(), x.clone());
+    let (x_buffer, len) = runner
+        .run_compute_and_get_buffer(&mut a_separate, &b, &c, &d, &mut x_separate, &v)
+        .unwrap_or_else(|e| panic!("{} compute pass failed: {e}", M::RUNNER_NAME));
+    assert_eq!(len, n);
+    assert_eq!(x_buffer.read().unwrap().to_vec(), x_separate);
+
+    let bindless_runner = VulkanoBindlessRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoBindlessRunner");
+    let (mut a_bindless, mut x_bindless) = (a.clone(), x.clone());
+    bindless_runner
+        .run_compute_and_get_buffer(&mut a_bindless, &b, &c, &d, &mut x_bindless, &v)
+        .expect("Bindless compute pass failed");
+
+    assert_eq!(a_separate, a_bindless);
+    assert_eq!(a_separate[10], 10 + 20 + 100 + 3);
+    for (i, (separate, bindless)) in x_separate.iter().zip(x_bindless.iter()).enumerate() {
+        assert!(
+            (*separate - *bindless).length() < 1e-6,
+            "x[{i}]: {} {separate:?} != bindless {bindless:?}",
+            M::RUNNER_NAME
+        );
+    }
+}
@@ -1,753 +1,2232 @@
-//! Integration tests for VulkanoComputeChain
+//! Integration tests for compute chains
 //!
-//! These tests exercise the key workflows around VulkanoComputeChain:
+//! These tests exercise the key workflows around a `ComputeBackend`:
 //! - Setting up concrete data vecs that will provide the initial input to the compute chain
 //! - Setting up buf_specs based on that data
 //! - Setting up kernel specs
 //! - Setting compute shader pipeline chains from vec of invoc_specs
-//! - Setting up a VulkanoComputeChain with the needed buf_specs and invocation chain
+//! - Setting up a compute chain with the needed buf_specs and invocation chain
 //! - Executing the chain (1 or more times)
 //! - Verifying that the correct outputs are stored in the buffers
+//!
+//! Every test runs against both `VulkanoComputeChain` (needs a Vulkan driver) and
+//! `CpuComputeChain` (runs anywhere).
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
 use bytemuck::Zeroable;
-use glam::Vec2;
-use rust_gpu_chimera_demo::runners::{
-    vulkano::{
-        buffer_specs::buf_spec,
-        shader_pipeline_builder::{invoc_spec, kernel},
+use glam::{Mat2, Vec2};
+use rust_gpu_chimera_demo::{
+    error::ChimeraError,
+    runners::{
+        vulkano::{
+            buffer::AnyBuffer,
+            buffer_registry::RegistryBufferSpecs,
+            buffer_specs::{buf_spec, DescriptorSetByName, Residency},
+            shader_pipeline_builder::{
+                inferred_kernel, invoc_spec, invoc_spec_by_param, invoc_spec_indirect, kernel,
+                over_buffer, over_grid_2d, BufferAccess,
+            },
+        },
+        vulkano_compute_chain::VulkanoComputeChain,
+        ComputeBackend, CpuComputeChain,
     },
-    vulkano_compute_chain::VulkanoComputeChain,
 };
-use shared::{grid::GridCell, num_workgroups_1d, num_workgroups_2d};
-
-//
-// BASIC SINGLE KERNEL TESTS
-//
-
-#[test]
-fn test_single_adder_execution() {
-    // Create data for the adder shader (a += b)
-    let n = 64;
-    let mut a = vec![1u32; n];
-    let mut b = vec![10u32; n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
-
-    // Create invocation chain with a single invocation
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
-
-    // Expected: a[i] = 1 + 10 = 11
-    let expected = vec![11u32; n];
-    assert_eq!(
-        &a_read[..],
-        &expected[..],
-        "Buffer a was not updated correctly"
-    );
-}
+use shared::{
+    grid::GridCell, num_workgroups_1d, num_workgroups_2d, particles::ParticleMatrices,
+    DispatchIndirectCommand, ScalarPushConstants, N_GRID_X,
+};
 
-#[test]
-fn test_single_step_particles_execution() {
-    // Create data for the step_particles shader (x += v)
-    let n = 64;
-    let mut x = vec![Vec2::new(0.5, 0.5); n];
-    let mut v = vec![Vec2::new(0.1, 0.2); n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec(
-        "step_particles_0",
-        vec!["x", "v"],
-        step_particles_kernel,
-    )];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 0.5 + 0.1 = 0.6, y = 0.5 + 0.2 = 0.7
-    let expected = Vec2::new(0.6, 0.7);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
-        );
-    }
-}
+macro_rules! compute_pass_tests {
+    ($backend:ty) => {
+        //
+        // BASIC SINGLE KERNEL TESTS
+        //
+
+        #[test]
+        fn test_single_adder_execution() {
+            // Create data for the adder shader (a += b)
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+
+            // Create invocation chain with a single invocation
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: a[i] = 1 + 10 = 11
+            let expected = vec![11u32; n];
+            assert_eq!(
+                &a_read[..],
+                &expected[..],
+                "Buffer a was not updated correctly"
+            );
+        }
 
-#[test]
-fn test_single_wrap_particles_execution() {
-    // Create data for the wrap_particles shader (x = x % 1.0)
-    let n = 64;
-    // Start with some particles outside [0, 1] range
-    let mut x = vec![Vec2::new(1.3, 2.7); n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("x", 2, &mut x),);
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec(
-        "wrap_particles",
-        vec!["x"],
-        wrap_particles_kernel,
-    )];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 1.3 % 1.0 = 0.3, y = 2.7 % 1.0 = 0.7
-    let expected = Vec2::new(0.3, 0.7);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect after wrapping. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
-        );
-    }
-}
+        #[test]
+        fn test_single_step_particles_execution() {
+            // Create data for the step_particles shader (x += v)
+            let n = 64;
+            let mut x = vec![Vec2::new(0.5, 0.5); n];
+            let mut v = vec![Vec2::new(0.1, 0.2); n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec(
+                "step_particles_0",
+                vec!["x", "v"],
+                step_particles_kernel,
+            )];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 0.5 + 0.1 = 0.6, y = 0.5 + 0.2 = 0.7
+            let expected = Vec2::new(0.6, 0.7);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
 
-#[test]
-fn test_single_fill_grid_random_execution() {
-    // Create data for the fill_grid_random shader
-    let grid_size = 256;
-    let mut grid = vec![GridCell::zeroed(); grid_size * grid_size];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("grid", 4, &mut grid),);
-
-    // Create kernel configuration for 2D dispatch
-    let wg_2d = num_workgroups_2d(grid_size as u32, grid_size as u32);
-    let fill_grid_random_kernel = kernel("fill_grid_random", vec![4], wg_2d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec(
-        "fill_grid_random",
-        vec!["grid"],
-        fill_grid_random_kernel,
-    )];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_grid = compute_chain
-        .typed_subbuffer_by_name::<GridCell>("grid")
-        .expect("Failed to get buffer grid");
-    let grid_read = buffer_grid.read().expect("Failed to read buffer grid");
-
-    // Verify that the grid has been filled with non-zero values
-    // (the shader fills with pseudo-random values)
-    let mut non_zero_count = 0;
-    for cell in grid_read.iter() {
-        if cell.mass > 0.0 || cell.v.x != 0.0 || cell.v.y != 0.0 {
-            non_zero_count += 1;
+        #[test]
+        fn test_single_wrap_particles_execution() {
+            // Create data for the wrap_particles shader (x = x % 1.0)
+            let n = 64;
+            // Start with some particles outside [0, 1] range
+            let mut x = vec![Vec2::new(1.3, 2.7); n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("x", 2, &mut x),);
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec(
+                "wrap_particles",
+                vec!["x"],
+                wrap_particles_kernel,
+            )];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 1.3 % 1.0 = 0.3, y = 2.7 % 1.0 = 0.7
+            let expected = Vec2::new(0.3, 0.7);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect after wrapping. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
         }
-    }
 
-    // Expect most cells to have non-zero values (random, so not all will be zero)
-    assert!(
-        non_zero_count > (grid_size * grid_size) / 2,
-        "Grid should be filled with mostly non-zero values. Found {} non-zero cells out of {}",
-        non_zero_count,
-        grid_size * grid_size
-    );
-}
+        #[test]
+        fn test_single_fill_grid_random_execution() {
+            // Create data for the fill_grid_random shader
+            let grid_size = 256;
+            let mut grid = vec![GridCell::zeroed(); grid_size * grid_size];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("grid", 4, &mut grid),);
+
+            // Create kernel configuration for 2D dispatch
+            let wg_2d = num_workgroups_2d(grid_size as u32, grid_size as u32);
+            let fill_grid_random_kernel = kernel("fill_grid_random", vec![4], wg_2d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec(
+                "fill_grid_random",
+                vec!["grid"],
+                fill_grid_random_kernel,
+            )];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let grid_read = compute_chain
+                .read_buffer::<GridCell>("grid")
+                .expect("Failed to read buffer grid");
+
+            // Verify that the grid has been filled with non-zero values
+            // (the shader fills with pseudo-random values)
+            let mut non_zero_count = 0;
+            for cell in grid_read.iter() {
+                if cell.mass > 0.0 || cell.v.x != 0.0 || cell.v.y != 0.0 {
+                    non_zero_count += 1;
+                }
+            }
+
+            // Expect most cells to have non-zero values (random, so not all will be zero)
+            assert!(
+                non_zero_count > (grid_size * grid_size) / 2,
+                "Grid should be filled with mostly non-zero values. Found {} non-zero cells out of {}",
+                non_zero_count,
+                grid_size * grid_size
+            );
+        }
 
-//
-// MULTIPLE EXECUTION TESTS
-//
-
-#[test]
-fn test_multiple_adder_executions() {
-    // Test executing the adder kernel multiple times with different buffers
-    // NOTE: The adder kernel always uses bindings 0 and 1, but we can map different
-    // logical buffer names to those bindings in different invocations
-    let n = 64;
-    let mut a = vec![0u32; n];
-    let mut b = vec![1u32; n];
-    let mut c = vec![10u32; n];
-    let mut d = vec![100u32; n];
-
-    // Create buf_specs - map buffers to bindings
-    // The adder kernel expects binding 0 (first arg) and binding 1 (second arg)
-    let buf_specs = (
-        buf_spec("a", 0, &mut a),
-        buf_spec("b", 1, &mut b),
-        buf_spec("c", 1, &mut c), // c also maps to binding 1
-        buf_spec("d", 1, &mut d), // d also maps to binding 1
-    );
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
-
-    // Create invocation chain: a += b, then a += c, then a += d
-    // Each invocation maps buffer names to the shader's expected bindings
-    let invocation_chain = vec![
-        invoc_spec("adder_ab", vec!["a", "b"], adder_kernel.clone()),
-        invoc_spec("adder_ac", vec!["a", "c"], adder_kernel.clone()),
-        invoc_spec("adder_ad", vec!["a", "d"], adder_kernel.clone()),
-    ];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
-
-    // Expected: a[i] = 0 + 1 + 10 + 100 = 111
-    let expected = vec![111u32; n];
-    assert_eq!(
-        &a_read[..],
-        &expected[..],
-        "Buffer a was not updated correctly after multiple additions"
-    );
-}
+        //
+        // MULTIPLE EXECUTION TESTS
+        //
+
+        #[test]
+        fn test_multiple_adder_executions() {
+            // Test executing the adder kernel multiple times with different buffers
+            // NOTE: The adder kernel always uses bindings 0 and 1, but we can map different
+            // logical buffer names to those bindings in different invocations
+            let n = 64;
+            let mut a = vec![0u32; n];
+            let mut b = vec![1u32; n];
+            let mut c = vec![10u32; n];
+            let mut d = vec![100u32; n];
+
+            // Create buf_specs - map buffers to bindings
+            // The adder kernel expects binding 0 (first arg) and binding 1 (second arg)
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("c", 1, &mut c), // c also maps to binding 1
+                buf_spec("d", 1, &mut d), // d also maps to binding 1
+            );
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+
+            // Create invocation chain: a += b, then a += c, then a += d
+            // Each invocation maps buffer names to the shader's expected bindings
+            let invocation_chain = vec![
+                invoc_spec("adder_ab", vec!["a", "b"], adder_kernel.clone()),
+                invoc_spec("adder_ac", vec!["a", "c"], adder_kernel.clone()),
+                invoc_spec("adder_ad", vec!["a", "d"], adder_kernel.clone()),
+            ];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: a[i] = 0 + 1 + 10 + 100 = 111
+            let expected = vec![111u32; n];
+            assert_eq!(
+                &a_read[..],
+                &expected[..],
+                "Buffer a was not updated correctly after multiple additions"
+            );
+        }
 
-#[test]
-fn test_multiple_step_particles_executions() {
-    // Test stepping particles multiple times
-    let n = 64;
-    let mut x = vec![Vec2::new(0.1, 0.2); n];
-    let mut v = vec![Vec2::new(0.05, 0.1); n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
-
-    // Create invocation chain: step 4 times
-    let invocation_chain = vec![
-        invoc_spec(
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_2",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_3",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-    ];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 0.1 + 4*0.05 = 0.3, y = 0.2 + 4*0.1 = 0.6
-    let expected = Vec2::new(0.3, 0.6);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect after 4 steps. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
-        );
-    }
-}
+        #[test]
+        fn test_multiple_step_particles_executions() {
+            // Test stepping particles multiple times
+            let n = 64;
+            let mut x = vec![Vec2::new(0.1, 0.2); n];
+            let mut v = vec![Vec2::new(0.05, 0.1); n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+
+            // Create invocation chain: step 4 times
+            let invocation_chain = vec![
+                invoc_spec(
+                    "step_particles_0",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_1",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_2",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_3",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+            ];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 0.1 + 4*0.05 = 0.3, y = 0.2 + 4*0.1 = 0.6
+            let expected = Vec2::new(0.3, 0.6);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect after 4 steps. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
 
-//
-// CHAINED KERNEL TESTS
-//
-
-#[test]
-fn test_chained_step_and_wrap() {
-    // Test chaining step_particles followed by wrap_particles
-    let n = 64;
-    let mut x = vec![Vec2::new(0.8, 0.9); n];
-    let mut v = vec![Vec2::new(0.15, 0.15); n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
-
-    // Create kernel configurations
-    let wg_1d = num_workgroups_1d(n as u32);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
-    let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
-
-    // Create invocation chain: step twice, then wrap
-    let invocation_chain = vec![
-        invoc_spec(
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
-    ];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 0.8 + 2*0.15 = 1.1 -> wrap -> 0.1
-    //           y = 0.9 + 2*0.15 = 1.2 -> wrap -> 0.2
-    let expected = Vec2::new(0.1, 0.2);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect after step+wrap. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
-        );
-    }
-}
+        //
+        // CHAINED KERNEL TESTS
+        //
+
+        #[test]
+        fn test_chained_step_and_wrap() {
+            // Test chaining step_particles followed by wrap_particles
+            let n = 64;
+            let mut x = vec![Vec2::new(0.8, 0.9); n];
+            let mut v = vec![Vec2::new(0.15, 0.15); n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            // Create kernel configurations
+            let wg_1d = num_workgroups_1d(n as u32);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+            let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
+
+            // Create invocation chain: step twice, then wrap
+            let invocation_chain = vec![
+                invoc_spec(
+                    "step_particles_0",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_1",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
+            ];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 0.8 + 2*0.15 = 1.1 -> wrap -> 0.1
+            //           y = 0.9 + 2*0.15 = 1.2 -> wrap -> 0.2
+            let expected = Vec2::new(0.1, 0.2);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect after step+wrap. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
 
-#[test]
-fn test_full_chain_adder_and_particles() {
-    // Test a full chain combining adder and particle operations
-    let n = 128;
-
-    let mut a = vec![1u32; n];
-    let mut b = vec![2u32; n];
-    let mut x = vec![Vec2::new(0.5, 0.5); n];
-    let mut v = vec![Vec2::new(0.1, 0.1); n];
-
-    // Create buf_specs
-    let buf_specs = (
-        buf_spec("a", 0, &mut a),
-        buf_spec("b", 1, &mut b),
-        buf_spec("x", 2, &mut x),
-        buf_spec("v", 3, &mut v),
-    );
-
-    // Create kernel configurations
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
-    let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![
-        invoc_spec("adder_ab", vec!["a", "b"], adder_kernel),
-        invoc_spec(
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
-    ];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify adder results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
-    assert_eq!(&a_read[..], &vec![3u32; n][..], "Adder result incorrect");
-
-    // Verify particle results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 0.5 + 2*0.1 = 0.7
-    let expected = Vec2::new(0.7, 0.7);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
-        );
-    }
-}
+        #[test]
+        fn test_full_chain_adder_and_particles() {
+            // Test a full chain combining adder and particle operations
+            let n = 128;
+
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            let mut x = vec![Vec2::new(0.5, 0.5); n];
+            let mut v = vec![Vec2::new(0.1, 0.1); n];
+
+            // Create buf_specs
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("x", 2, &mut x),
+                buf_spec("v", 3, &mut v),
+            );
+
+            // Create kernel configurations
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+            let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![
+                invoc_spec("adder_ab", vec!["a", "b"], adder_kernel),
+                invoc_spec(
+                    "step_particles_0",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_1",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
+            ];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify adder results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+            assert_eq!(&a_read[..], &vec![3u32; n][..], "Adder result incorrect");
+
+            // Verify particle results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 0.5 + 2*0.1 = 0.7
+            let expected = Vec2::new(0.7, 0.7);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
 
-//
-// VARIED WORKGROUP SIZE TESTS
-//
+        #[test]
+        fn test_annotated_chain_with_independent_invocations() {
+            // adder and particle invocations interleaved: with access annotations the
+            // chain only orders the invocations that share a buffer
+            let n = 128;
+
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            let mut x = vec![Vec2::new(0.5, 0.5); n];
+            let mut v = vec![Vec2::new(0.2, 0.2); n];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("x", 2, &mut x),
+                buf_spec("v", 3, &mut v),
+            );
+
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+            let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
+
+            let invocation_chain = vec![
+                invoc_spec("adder_0", vec!["a", "b"], adder_kernel.clone())
+                    .with_access("b", BufferAccess::Read),
+                invoc_spec(
+                    "step_particles_0",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                )
+                .with_access("v", BufferAccess::Read),
+                invoc_spec("adder_1", vec!["a", "b"], adder_kernel)
+                    .with_access("b", BufferAccess::Read),
+                invoc_spec("step_particles_1", vec!["x", "v"], step_particles_kernel)
+                    .with_access("v", BufferAccess::Read),
+                invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
+            ];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![5u32; n][..], "Adder result incorrect");
+
+            // 0.5 + 2 * 0.2 = 0.9
+            let x_read = compute_chain.read_buffer::<Vec2>("x").unwrap();
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual - Vec2::new(0.9, 0.9)).length() < 1e-5,
+                    "Position x[{i}] incorrect: {actual:?}"
+                );
+            }
+        }
 
-#[test]
-fn test_large_buffer_multiple_workgroups() {
-    // Test with a buffer size that requires multiple workgroups
-    let n = 512; // Requires 8 workgroups with WORKGROUP_SIZE=64
+        #[test]
+        fn test_access_annotation_for_unbound_buffer_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
 
-    let mut a = vec![5u32; n];
-    let mut b = vec![3u32; n];
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
 
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)
+                .with_access("grid", BufferAccess::Atomic)];
 
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::AccessAnnotationNotBound { ref buf_name, .. })
+                    if buf_name == "grid"
+            ));
+        }
 
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        //
+        // VARIED WORKGROUP SIZE TESTS
+        //
 
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
+        #[test]
+        fn test_large_buffer_multiple_workgroups() {
+            // Test with a buffer size that requires multiple workgroups
+            let n = 512; // Requires 8 workgroups with WORKGROUP_SIZE=64
 
-    compute_chain.execute().expect("Failed to execute");
+            let mut a = vec![5u32; n];
+            let mut b = vec![3u32; n];
 
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
 
-    // Expected: a[i] = 5 + 3 = 8
-    let expected = vec![8u32; n];
-    assert_eq!(
-        &a_read[..],
-        &expected[..],
-        "Large buffer not computed correctly"
-    );
-}
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
 
-#[test]
-fn test_large_2d_grid() {
-    // Test with a large 2D grid
-    let grid_size = 128; // 128x128 grid
-    let mut grid = vec![GridCell::zeroed(); grid_size * grid_size];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("grid", 4, &mut grid),);
-
-    // Create kernel configuration for 2D dispatch
-    let wg_2d = num_workgroups_2d(grid_size as u32, grid_size as u32);
-    let fill_grid_random_kernel = kernel("fill_grid_random", vec![4], wg_2d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec(
-        "fill_grid_random",
-        vec!["grid"],
-        fill_grid_random_kernel,
-    )];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_grid = compute_chain
-        .typed_subbuffer_by_name::<GridCell>("grid")
-        .expect("Failed to get buffer grid");
-    let grid_read = buffer_grid.read().expect("Failed to read buffer grid");
-
-    // Verify grid size is correct
-    assert_eq!(
-        grid_read.len(),
-        grid_size * grid_size,
-        "Grid size incorrect"
-    );
-
-    // Sample a few cells to verify they're filled
-    assert!(
-        grid_read[0].mass >= 0.0 && grid_read[0].mass <= 1.0,
-        "Grid cell mass out of expected range"
-    );
-}
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
 
-//
-// EDGE CASE TESTS
-//
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
 
-#[test]
-fn test_zero_inputs() {
-    // Test with all zero inputs
-    let n = 64;
-    let mut a = vec![0u32; n];
-    let mut b = vec![0u32; n];
+            compute_chain.run_chain().expect("Failed to execute");
 
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
 
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+            // Expected: a[i] = 5 + 3 = 8
+            let expected = vec![8u32; n];
+            assert_eq!(
+                &a_read[..],
+                &expected[..],
+                "Large buffer not computed correctly"
+            );
+        }
 
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        #[test]
+        fn test_large_2d_grid() {
+            // Test with a large 2D grid
+            let grid_size = 128; // 128x128 grid
+            let mut grid = vec![GridCell::zeroed(); grid_size * grid_size];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("grid", 4, &mut grid),);
+
+            // Create kernel configuration for 2D dispatch
+            let wg_2d = num_workgroups_2d(grid_size as u32, grid_size as u32);
+            let fill_grid_random_kernel = kernel("fill_grid_random", vec![4], wg_2d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec(
+                "fill_grid_random",
+                vec!["grid"],
+                fill_grid_random_kernel,
+            )];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let grid_read = compute_chain
+                .read_buffer::<GridCell>("grid")
+                .expect("Failed to read buffer grid");
+
+            // Verify grid size is correct
+            assert_eq!(
+                grid_read.len(),
+                grid_size * grid_size,
+                "Grid size incorrect"
+            );
+
+            // Sample a few cells to verify they're filled
+            assert!(
+                grid_read[0].mass >= 0.0 && grid_read[0].mass <= 1.0,
+                "Grid cell mass out of expected range"
+            );
+        }
 
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
+        //
+        // EDGE CASE TESTS
+        //
 
-    compute_chain.execute().expect("Failed to execute");
+        #[test]
+        fn test_zero_inputs() {
+            // Test with all zero inputs
+            let n = 64;
+            let mut a = vec![0u32; n];
+            let mut b = vec![0u32; n];
 
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
 
-    // Expected: all zeros
-    let expected = vec![0u32; n];
-    assert_eq!(&a_read[..], &expected[..], "Zero input test failed");
-}
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: all zeros
+            let expected = vec![0u32; n];
+            assert_eq!(&a_read[..], &expected[..], "Zero input test failed");
+        }
 
-#[test]
-fn test_overflow_behavior() {
-    // Test u32 overflow behavior
-    let n = 64;
-    let mut a = vec![u32::MAX; n];
-    let mut b = vec![1u32; n];
+        #[test]
+        fn test_overflow_behavior() {
+            // Test u32 overflow behavior
+            let n = 64;
+            let mut a = vec![u32::MAX; n];
+            let mut b = vec![1u32; n];
 
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
 
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
 
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: u32::MAX + 1 wraps to 0
+            let expected = vec![0u32; n];
+            assert_eq!(&a_read[..], &expected[..], "Overflow test failed");
+        }
+
+        #[test]
+        fn test_negative_velocity_wrapping() {
+            // Test particles with negative velocities that go below 0
+            let n = 64;
+            let mut x = vec![Vec2::new(0.1, 0.2); n];
+            let mut v = vec![Vec2::new(-0.05, -0.1); n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            // Create kernel configurations
+            let wg_1d = num_workgroups_1d(n as u32);
+            let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+            let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![
+                invoc_spec(
+                    "step_particles_0",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec(
+                    "step_particles_1",
+                    vec!["x", "v"],
+                    step_particles_kernel.clone(),
+                ),
+                invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
+            ];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let x_read = compute_chain
+                .read_buffer::<Vec2>("x")
+                .expect("Failed to read buffer x");
+
+            // Expected: x = 0.1 + 2*(-0.05) = 0.0
+            //           y = 0.2 + 2*(-0.1) = 0.0
+            let expected = Vec2::new(0.0, 0.0);
+            for (i, &actual) in x_read.iter().enumerate() {
+                assert!(
+                    (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
+                    "Position x[{}] incorrect with negative velocity. Got: {:?}, Expected: {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
+
+        //
+        // EXECUTION MULTIPLE TIMES TESTS
+        //
+
+        #[test]
+        fn test_execute_chain_multiple_times() {
+            // Test executing the same chain multiple times
+            let n = 64;
+            let mut a = vec![0u32; n];
+            let mut b = vec![1u32; n];
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            // Create the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            // Execute 3 times
+            compute_chain.run_chain().expect("Failed to execute (1)");
+            compute_chain.run_chain().expect("Failed to execute (2)");
+            compute_chain.run_chain().expect("Failed to execute (3)");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: a = 0 + 1 + 1 + 1 = 3 (executed 3 times)
+            let expected = vec![3u32; n];
+            assert_eq!(&a_read[..], &expected[..], "Multiple executions failed");
+        }
+
+        #[test]
+        fn test_varied_data_patterns() {
+            // Test with varied input data (not all uniform)
+            let n = 64;
+            let mut a = (0..n as u32).collect::<Vec<u32>>();
+            let mut b = (0..n as u32).map(|i| i * 2).collect::<Vec<u32>>();
+
+            // Create buf_specs
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+
+            // Create kernel configuration
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+
+            // Create invocation chain
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            // Create and execute the compute chain
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // Verify results
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+
+            // Expected: a[i] = i + 2*i = 3*i
+            let expected: Vec<u32> = (0..n as u32).map(|i| 3 * i).collect();
+            assert_eq!(
+                &a_read[..],
+                &expected[..],
+                "Varied data pattern test failed"
+            );
+        }
+
+        //
+        // BUFFER REGISTRY
+        //
+
+        #[test]
+        fn test_registry_with_more_buffers_than_tuple_slots() {
+            let n = 64;
+            // 20 unused buffers on top of the two the chain uses
+            let mut extra = (0..20).map(|i| vec![i as u32; n]).collect::<Vec<_>>();
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let mut buf_specs = RegistryBufferSpecs::new();
+            for (i, data) in extra.iter_mut().enumerate() {
+                let name: &'static str = Box::leak(format!("extra_{i}").into_boxed_str());
+                buf_specs = buf_specs.with(buf_spec(name, 9, data));
+            }
+            let buf_specs = buf_specs
+                .with(buf_spec("a", 0, &mut a))
+                .with(buf_spec("b", 1, &mut b));
+
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+            assert_eq!(&a_read[..], &vec![11u32; n][..]);
+            let extra_read = compute_chain
+                .read_buffer::<u32>("extra_19")
+                .expect("Failed to read buffer extra_19");
+            assert_eq!(&extra_read[..], &vec![19u32; n][..]);
+        }
+
+        #[test]
+        fn test_registry_read_with_wrong_type() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = RegistryBufferSpecs::new()
+                .with(buf_spec("a", 0, &mut a))
+                .with(buf_spec("b", 1, &mut b));
+
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            assert!(matches!(
+                compute_chain.read_buffer::<f32>("a"),
+                Err(ChimeraError::BufferTypeMismatch { ref name, .. }) if name == "a"
+            ));
+            assert!(matches!(
+                compute_chain.read_buffer::<u32>("missing"),
+                Err(ChimeraError::DescriptorSetNameNotFound(_))
+            ));
+        }
+
+        #[test]
+        fn test_registry_duplicate_buffer_name_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = RegistryBufferSpecs::new()
+                .with(buf_spec("a", 0, &mut a))
+                .with(buf_spec("a", 1, &mut b));
+
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_aa", vec!["a", "a"], adder_kernel)];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::DuplicateBufferName(ref name)) if name == "a"
+            ));
+        }
+
+        //
+        // KERNELS WITH BINDINGS FROM THE SHADER
+        //
+
+        #[test]
+        fn test_inferred_kernel_positional_buffers() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+
+            let adder_kernel = inferred_kernel("adder", num_workgroups_1d(n as u32))
+                .expect("Failed to infer kernel");
+            assert_eq!(adder_kernel.binding_nums_in_shader(), &vec![0, 1]);
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+            assert_eq!(&a_read[..], &vec![11u32; n][..]);
+        }
+
+        #[test]
+        fn test_invoc_spec_by_param() {
+            let n = 64;
+            let mut pos = vec![Vec2::new(0.1, 0.2); n];
+            let mut vel = vec![Vec2::new(0.01, 0.02); n];
+
+            let buf_specs = (buf_spec("pos", 2, &mut pos), buf_spec("vel", 3, &mut vel));
+
+            // parameters of `step_particles` are `x` and `v`; order does not matter
+            let step_kernel = inferred_kernel("step_particles", num_workgroups_1d(n as u32))
+                .expect("Failed to infer kernel");
+            let spec = invoc_spec_by_param("step", vec![("v", "vel"), ("x", "pos")], step_kernel)
+                .expect("Failed to map buffers by parameter name");
+            assert_eq!(spec.buf_name_for_binding(2), Some("pos"));
+            assert_eq!(spec.buf_name_for_binding(3), Some("vel"));
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, vec![spec])
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let pos_read = compute_chain
+                .read_buffer::<Vec2>("pos")
+                .expect("Failed to read buffer pos");
+            for p in pos_read.iter() {
+                assert!((p.x - 0.11).abs() < 1e-6 && (p.y - 0.22).abs() < 1e-6);
+            }
+        }
+
+        #[test]
+        fn test_invoc_spec_by_unknown_param() {
+            let step_kernel = inferred_kernel("step_particles", num_workgroups_1d(64))
+                .expect("Failed to infer kernel");
+            let result = invoc_spec_by_param("step", vec![("pos", "x")], step_kernel);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::ShaderParameterNotFound { ref param, .. }) if param == "pos"
+            ));
+        }
+
+        //
+        // PUSH CONSTANTS
+        //
+
+        #[test]
+        fn test_push_constant_value() {
+            let n = 100;
+            let mut a = vec![1u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a),);
+
+            // one more workgroup than needed; the shader skips the extra invocations
+            let add_kernel = inferred_kernel("add_scalar", num_workgroups_1d(n as u32))
+                .expect("Failed to infer kernel")
+                .with_push_constants(ScalarPushConstants {
+                    scalar: 5,
+                    num_elements: n as u32,
+                });
+            let invocation_chain = vec![invoc_spec("add_5", vec!["a"], add_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+            assert_eq!(&a_read[..], &vec![11u32; n][..]);
+        }
+
+        #[test]
+        fn test_push_constants_per_execution() {
+            let n = 64;
+            let mut a = vec![0u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a),);
+
+            // adds 1, then 2, then 3
+            let executions = Arc::new(AtomicU32::new(0));
+            let counter = executions.clone();
+            let add_kernel = inferred_kernel("add_scalar", num_workgroups_1d(n as u32))
+                .expect("Failed to infer kernel")
+                .with_push_constants_fn(move || ScalarPushConstants {
+                    scalar: counter.fetch_add(1, Ordering::Relaxed) + 1,
+                    num_elements: n as u32,
+                });
+            let invocation_chain = vec![invoc_spec("add_count", vec!["a"], add_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            for _ in 0..3 {
+                compute_chain.run_chain().expect("Failed to execute");
+            }
+
+            assert_eq!(executions.load(Ordering::Relaxed), 3);
+            let a_read = compute_chain
+                .read_buffer::<u32>("a")
+                .expect("Failed to read buffer a");
+            assert_eq!(&a_read[..], &vec![6u32; n][..]);
+        }
 
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
+        #[test]
+        fn test_missing_push_constants_is_rejected() {
+            let n = 64;
+            let mut a = vec![0u32; n];
 
-    compute_chain.execute().expect("Failed to execute");
+            let buf_specs = (buf_spec("a", 0, &mut a),);
 
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
+            let add_kernel = kernel("add_scalar", vec![0], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("add", vec!["a"], add_kernel)];
 
-    // Expected: u32::MAX + 1 wraps to 0
-    let expected = vec![0u32; n];
-    assert_eq!(&a_read[..], &expected[..], "Overflow test failed");
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::PushConstantsMismatch {
+                    shader_has_push_constants: true,
+                    ..
+                })
+            ));
+        }
+
+        //
+        // VALIDATION AGAINST THE SHADER
+        //
+
+        #[test]
+        fn test_wrong_binding_number_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+
+            // `adder` uses bindings 0 and 1, not 0 and 2
+            let wg_1d = num_workgroups_1d(n as u32);
+            let adder_kernel = kernel("adder", vec![0, 2], wg_1d);
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::KernelBindingMismatch { ref shader_bindings, .. })
+                    if shader_bindings == &vec![0, 1]
+            ));
+        }
+
+        #[test]
+        fn test_wrong_element_size_is_rejected() {
+            let n = 64;
+            let mut x = vec![Vec2::ZERO; n];
+            // `step_particles` reads `v` as `Vec2`s
+            let mut v = vec![0.0f32; n];
+
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            let wg_1d = num_workgroups_1d(n as u32);
+            let step_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+            let invocation_chain = vec![invoc_spec("step", vec!["x", "v"], step_kernel)];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::BufferElementSizeMismatch {
+                    binding: 3,
+                    shader_stride: 8,
+                    buffer_element_size: 4,
+                    ..
+                })
+            ));
+        }
+
+        //
+        // DISPATCH SIZES
+        //
+
+        #[test]
+        fn test_dispatch_over_buffer_follows_buffer_lengths() {
+            // neither length is N_PARTICLES, and they differ from each other
+            let (n_a, n_x) = (128, 320);
+            let mut a = vec![1u32; n_a];
+            let mut b = vec![2u32; n_a];
+            let mut x = vec![Vec2::ZERO; n_x];
+            let mut v = vec![Vec2::new(0.25, 0.5); n_x];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("x", 2, &mut x),
+                buf_spec("v", 3, &mut v),
+            );
+
+            let adder_kernel = kernel("adder", vec![0, 1], over_buffer("a"));
+            let step_kernel = kernel("step_particles", vec![2, 3], over_buffer("x"));
+            let invocation_chain = vec![
+                invoc_spec("adder_ab", vec!["a", "b"], adder_kernel),
+                invoc_spec("step", vec!["x", "v"], step_kernel),
+            ];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![3u32; n_a][..]);
+            let x_read = compute_chain.read_buffer::<Vec2>("x").unwrap();
+            assert_eq!(&x_read[..], &vec![Vec2::new(0.25, 0.5); n_x][..]);
+        }
+
+        #[test]
+        fn test_dispatch_over_buffer_with_partial_workgroup() {
+            // 100 elements round up to two workgroups of 64; add_scalar skips the rest
+            let n = 100;
+            let mut a = vec![1u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a),);
+
+            let add_kernel = inferred_kernel("add_scalar", over_buffer("a"))
+                .expect("Failed to infer kernel")
+                .with_push_constants(ScalarPushConstants {
+                    scalar: 5,
+                    num_elements: n as u32,
+                });
+            let invocation_chain = vec![invoc_spec("add_5", vec!["a"], add_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![6u32; n][..]);
+        }
+
+        #[test]
+        fn test_dispatch_over_grid_2d() {
+            let grid_size = N_GRID_X as usize;
+            let mut grid = vec![GridCell::zeroed(); grid_size * grid_size];
+            for cell in grid.iter_mut() {
+                cell.mass = 1.0;
+            }
+
+            let buf_specs = (buf_spec("grid", 4, &mut grid),);
+
+            let grid_cells = over_grid_2d(N_GRID_X, N_GRID_X);
+            let clear_grid_kernel = kernel("clear_grid", vec![4], grid_cells);
+            let invocation_chain = vec![invoc_spec("clear_grid", vec!["grid"], clear_grid_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let grid_read = compute_chain.read_buffer::<GridCell>("grid").unwrap();
+            assert!(grid_read.iter().all(|cell| cell.mass == 0.0));
+        }
+
+        #[test]
+        fn test_dispatch_over_unbound_buffer_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            let mut x = vec![Vec2::ZERO; n];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("x", 2, &mut x),
+            );
+
+            // `x` is a chain buffer, but adder_ab does not bind it
+            let adder_kernel = kernel("adder", vec![0, 1], over_buffer("x"));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::DispatchBufferNotBound { ref buf_name, .. }) if buf_name == "x"
+            ));
+        }
+
+        #[test]
+        fn test_indirect_dispatch_from_earlier_invocation() {
+            let n = 256;
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            // 100 active elements round up to two workgroups of 64
+            let mut count = vec![100u32];
+            let mut args = vec![DispatchIndirectCommand::default()];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("count", 9, &mut count),
+                buf_spec("args", 10, &mut args),
+            );
+
+            let args_kernel = kernel("indirect_args_1d", vec![9, 10], [1, 1, 1]);
+            let adder_kernel = kernel("adder", vec![0, 1], [1, 1, 1]);
+            let invocation_chain = vec![
+                invoc_spec("args", vec!["count", "args"], args_kernel)
+                    .with_access("count", BufferAccess::Read)
+                    .with_access("args", BufferAccess::Write),
+                invoc_spec_indirect("adder_ab", vec!["a", "b"], adder_kernel, "args")
+                    .with_access("b", BufferAccess::Read),
+            ];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let args_read = compute_chain
+                .read_buffer::<DispatchIndirectCommand>("args")
+                .unwrap();
+            assert_eq!(args_read[0].num_workgroups(), [2, 1, 1]);
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..128], &vec![3u32; 128][..]);
+            assert_eq!(&a_read[128..], &vec![1u32; n - 128][..]);
+        }
+
+        #[test]
+        fn test_indirect_buffer_with_wrong_element_size_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            // three u32s are not one DispatchIndirectCommand
+            let mut args = vec![1u32, 1, 1];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("args", 10, &mut args),
+            );
+
+            let adder_kernel = kernel("adder", vec![0, 1], [1, 1, 1]);
+            let invocation_chain = vec![invoc_spec_indirect(
+                "adder_ab",
+                vec!["a", "b"],
+                adder_kernel,
+                "args",
+            )];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::IndirectBufferElementSizeMismatch {
+                    buffer_element_size: 4,
+                    ..
+                })
+            ));
+        }
+
+        //
+        // SUBSTEPS
+        //
+
+        #[test]
+        fn test_substeps_run_the_chain_repeatedly() {
+            let n = 64;
+            let mut x = vec![Vec2::new(0.5, 0.5); n];
+            let mut v = vec![Vec2::new(0.15, 0.2); n];
+
+            let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
+
+            let wg_1d = num_workgroups_1d(n as u32);
+            let invocation_chain = vec![
+                invoc_spec("step", vec!["x", "v"], kernel("step_particles", vec![2, 3], wg_1d))
+                    .with_access("v", BufferAccess::Read),
+                invoc_spec("wrap", vec!["x"], kernel("wrap_particles", vec![2], wg_1d)),
+            ];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            compute_chain.set_substeps(4).expect("Failed to set substeps");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // 4 steps: (0.5 + 0.6, 0.5 + 0.8) wrapped
+            let expected = Vec2::new(0.1, 0.3);
+            for x in compute_chain.read_buffer::<Vec2>("x").unwrap() {
+                assert!((x - expected).length() < 1e-5, "x = {x:?}");
+            }
+
+            // 4 + 2 steps: (0.5 + 0.9, 0.5 + 1.2) wrapped
+            compute_chain.set_substeps(2).expect("Failed to set substeps");
+            compute_chain.run_chain().expect("Failed to execute");
+            let expected = Vec2::new(0.4, 0.7);
+            for x in compute_chain.read_buffer::<Vec2>("x").unwrap() {
+                assert!((x - expected).length() < 1e-5, "x = {x:?}");
+            }
+        }
+
+        #[test]
+        fn test_substeps_with_push_constants_per_execution() {
+            let n = 64;
+            let mut a = vec![0u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a),);
+
+            // every substep gets the next value: adds 1, then 2, then 3
+            let executions = Arc::new(AtomicU32::new(0));
+            let counter = executions.clone();
+            let add_kernel = inferred_kernel("add_scalar", num_workgroups_1d(n as u32))
+                .expect("Failed to infer kernel")
+                .with_push_constants_fn(move || ScalarPushConstants {
+                    scalar: counter.fetch_add(1, Ordering::Relaxed) + 1,
+                    num_elements: n as u32,
+                });
+            let invocation_chain = vec![invoc_spec("add_count", vec!["a"], add_kernel)];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            compute_chain.set_substeps(3).expect("Failed to set substeps");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            assert_eq!(executions.load(Ordering::Relaxed), 3);
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![6u32; n][..]);
+        }
+
+        #[test]
+        fn test_zero_substeps_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            assert!(matches!(
+                compute_chain.set_substeps(0),
+                Err(ChimeraError::ZeroSubsteps)
+            ));
+
+            // still one substep
+            compute_chain.run_chain().expect("Failed to execute");
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![3u32; n][..]);
+        }
+
+        //
+        // RESIDENCY
+        //
+
+        #[test]
+        fn test_buffers_of_every_residency() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+            let mut c = vec![2u32; n];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a).with_residency(Residency::DeviceLocal),
+                buf_spec("b", 1, &mut b).with_residency(Residency::Readback),
+                buf_spec("c", 2, &mut c),
+            );
+
+            let wg_1d = num_workgroups_1d(n as u32);
+            let invocation_chain = vec![
+                invoc_spec("adder_ab", vec!["a", "b"], kernel("adder", vec![0, 1], wg_1d)),
+                invoc_spec("adder_ba", vec!["b", "c"], kernel("adder", vec![0, 1], wg_1d)),
+            ];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // b: 10 + 2 + 2; a: 1 + 10 + 12
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![23u32; n][..]);
+            let b_read = compute_chain.read_buffer::<u32>("b").unwrap();
+            assert_eq!(&b_read[..], &vec![14u32; n][..]);
+        }
+
+        //
+        // WRITING BUFFERS
+        //
+
+        #[test]
+        fn test_write_buffer_between_executions() {
+            for residency in [Residency::HostVisible, Residency::DeviceLocal] {
+                let n = 64;
+                let mut a = vec![1u32; n];
+                let mut b = vec![10u32; n];
+
+                let buf_specs = (
+                    buf_spec("a", 0, &mut a).with_residency(residency),
+                    buf_spec("b", 1, &mut b).with_residency(residency),
+                );
+                let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+                let invocation_chain =
+                    vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+                let compute_chain: $backend =
+                    ComputeBackend::create(&buf_specs, invocation_chain)
+                        .expect("Failed to create compute chain");
+                compute_chain.run_chain().expect("Failed to execute");
+
+                // reset a, and add 5 instead of 10 from now on
+                compute_chain.write_buffer("a", &vec![0u32; n]).unwrap();
+                compute_chain.write_buffer("b", &vec![5u32; n]).unwrap();
+                assert_eq!(compute_chain.read_buffer::<u32>("a").unwrap(), vec![0u32; n]);
+
+                compute_chain.run_chain().expect("Failed to execute");
+                let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+                assert_eq!(&a_read[..], &vec![5u32; n][..], "{residency:?}");
+            }
+        }
+
+        #[test]
+        fn test_write_range_leaves_other_elements() {
+            for residency in [Residency::HostVisible, Residency::DeviceLocal] {
+                let n = 64;
+                let mut x = vec![Vec2::new(0.5, 0.5); n];
+                let mut v = vec![Vec2::ZERO; n];
+
+                let buf_specs = (
+                    buf_spec("x", 2, &mut x),
+                    buf_spec("v", 3, &mut v).with_residency(residency),
+                );
+                let step_kernel = kernel("step_particles", vec![2, 3], num_workgroups_1d(n as u32));
+                let invocation_chain = vec![invoc_spec("step", vec!["x", "v"], step_kernel)];
+
+                let compute_chain: $backend =
+                    ComputeBackend::create(&buf_specs, invocation_chain)
+                        .expect("Failed to create compute chain");
+
+                // set particles 8..12 moving
+                let moving = vec![Vec2::new(0.1, 0.0); 4];
+                compute_chain.write_range("v", 8, &moving).unwrap();
+                compute_chain.run_chain().expect("Failed to execute");
+
+                let x_read = compute_chain.read_buffer::<Vec2>("x").unwrap();
+                for (i, x) in x_read.iter().enumerate() {
+                    let expected = if (8..12).contains(&i) {
+                        Vec2::new(0.6, 0.5)
+                    } else {
+                        Vec2::new(0.5, 0.5)
+                    };
+                    assert!((*x - expected).length() < 1e-5, "x[{i}] = {x:?}");
+                }
+                // an empty write is a no-op, even at the end of the buffer
+                compute_chain.write_range::<Vec2>("v", n, &[]).unwrap();
+            }
+        }
+
+        #[test]
+        fn test_write_with_wrong_length_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            assert!(matches!(
+                compute_chain.write_buffer("a", &vec![0u32; n - 1]),
+                Err(ChimeraError::BufferLengthMismatch {
+                    buffer_len: 64,
+                    data_len: 63
+                })
+            ));
+            assert!(matches!(
+                compute_chain.write_range("a", n - 2, &[0u32; 4]),
+                Err(ChimeraError::BufferRangeOutOfBounds {
+                    offset: 62,
+                    data_len: 4,
+                    buffer_len: 64,
+                    ..
+                })
+            ));
+            assert!(matches!(
+                compute_chain.write_range("a", usize::MAX, &[0u32; 2]),
+                Err(ChimeraError::BufferRangeOutOfBounds { .. })
+            ));
+            assert!(compute_chain.write_buffer("c", &vec![0u32; n]).is_err());
+
+            // nothing was written
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![1u32; n][..]);
+        }
+
+        #[test]
+        fn test_write_with_wrong_type_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = RegistryBufferSpecs::new()
+                .with(buf_spec("a", 0, &mut a))
+                .with(buf_spec("b", 1, &mut b).with_residency(Residency::DeviceLocal));
+            let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let compute_chain: $backend = ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+
+            for name in ["a", "b"] {
+                assert!(matches!(
+                    compute_chain.write_buffer(name, &vec![0f32; n]),
+                    Err(ChimeraError::BufferTypeMismatch { .. })
+                ));
+                assert!(matches!(
+                    compute_chain.write_range(name, 0, &[Vec2::ZERO; 2]),
+                    Err(ChimeraError::BufferTypeMismatch { .. })
+                ));
+            }
+        }
+
+        //
+        // RESIZING BUFFERS
+        //
+
+        #[test]
+        fn test_grow_buffers_keeps_contents() {
+            for residency in [Residency::HostVisible, Residency::DeviceLocal] {
+                let n = 64;
+                let mut x = vec![Vec2::new(0.5, 0.5); n];
+                let mut v = vec![Vec2::new(0.1, 0.0); n];
+
+                let buf_specs = (
+                    buf_spec("x", 2, &mut x).with_residency(residency),
+                    buf_spec("v", 3, &mut v).with_residency(residency),
+                );
+                let step_kernel = kernel("step_particles", vec![2, 3], over_buffer("x"));
+                let invocation_chain = vec![invoc_spec("step", vec!["x", "v"], step_kernel)];
+
+                let mut compute_chain: $backend =
+                    ComputeBackend::create(&buf_specs, invocation_chain)
+                        .expect("Failed to create compute chain");
+                compute_chain
+                    .resize_buffer("x", 2 * n, Vec2::new(0.2, 0.2))
+                    .unwrap();
+                compute_chain
+                    .resize_buffer("v", 2 * n, Vec2::new(0.0, 0.1))
+                    .unwrap();
+                compute_chain.run_chain().expect("Failed to execute");
+
+                // the dispatch covers the new particles too
+                let x_read = compute_chain.read_buffer::<Vec2>("x").unwrap();
+                assert_eq!(x_read.len(), 2 * n);
+                for (i, x) in x_read.iter().enumerate() {
+                    let expected = if i < n {
+                        Vec2::new(0.6, 0.5)
+                    } else {
+                        Vec2::new(0.2, 0.3)
+                    };
+                    assert!((*x - expected).length() < 1e-5, "x[{i}] = {x:?}");
+                }
+            }
+        }
+
+        #[test]
+        fn test_resize_indirect_buffer() {
+            let n = 256;
+            let mut a = vec![1u32; n];
+            let mut b = vec![2u32; n];
+            // 100 active elements round up to two workgroups of 64
+            let mut count = vec![100u32];
+            let mut args = vec![DispatchIndirectCommand::default()];
+
+            let buf_specs = (
+                buf_spec("a", 0, &mut a),
+                buf_spec("b", 1, &mut b),
+                buf_spec("count", 9, &mut count),
+                buf_spec("args", 10, &mut args),
+            );
+
+            let args_kernel = kernel("indirect_args_1d", vec![9, 10], [1, 1, 1]);
+            let adder_kernel = kernel("adder", vec![0, 1], [1, 1, 1]);
+            let invocation_chain = vec![
+                invoc_spec("args", vec!["count", "args"], args_kernel)
+                    .with_access("count", BufferAccess::Read)
+                    .with_access("args", BufferAccess::Write),
+                invoc_spec_indirect("adder_ab", vec!["a", "b"], adder_kernel, "args")
+                    .with_access("b", BufferAccess::Read),
+            ];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // the indirect dispatch reads its workgroup count from the resized buffer
+            compute_chain
+                .resize_buffer("args", 2, DispatchIndirectCommand::default())
+                .unwrap();
+            compute_chain.write_buffer("count", &[200u32]).unwrap();
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let args_read = compute_chain
+                .read_buffer::<DispatchIndirectCommand>("args")
+                .unwrap();
+            assert_eq!(args_read.len(), 2);
+            assert_eq!(args_read[0].num_workgroups(), [4, 1, 1]);
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..128], &vec![5u32; 128][..]);
+            assert_eq!(&a_read[128..], &vec![3u32; n - 128][..]);
+        }
+
+        #[test]
+        fn test_shrink_buffers_keeps_contents() {
+            let n = 128;
+            let mut a = (0..n as u32).collect::<Vec<_>>();
+            let mut b = vec![1000u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            let adder_kernel = kernel("adder", vec![0, 1], over_buffer("a"));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            compute_chain.resize_buffer("a", n / 2, 0u32).unwrap();
+            compute_chain.resize_buffer("b", n / 2, 0u32).unwrap();
+            compute_chain.run_chain().expect("Failed to execute");
+
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            let expected = (1000..1000 + n as u32 / 2).collect::<Vec<_>>();
+            assert_eq!(a_read, expected);
+        }
+
+        #[test]
+        fn test_invalid_resize_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![10u32; n];
+
+            let buf_specs = RegistryBufferSpecs::new()
+                .with(buf_spec("a", 0, &mut a))
+                .with(buf_spec("b", 1, &mut b));
+            let adder_kernel = kernel("adder", vec![0, 1], over_buffer("a"));
+            let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+
+            assert!(matches!(
+                compute_chain.resize_buffer("a", 0, 0u32),
+                Err(ChimeraError::ZeroLengthBuffer(_))
+            ));
+            assert!(matches!(
+                compute_chain.resize_buffer("a", 2 * n, 0f32),
+                Err(ChimeraError::BufferTypeMismatch { .. })
+            ));
+            assert!(compute_chain.resize_buffer("c", n, 0u32).is_err());
+
+            // the chain still runs over the buffers it had
+            compute_chain.run_chain().expect("Failed to execute");
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![11u32; n][..]);
+        }
+
+        #[test]
+        fn test_resize_follows_push_constants_over_buffer() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a),);
+            let add_kernel = inferred_kernel("add_scalar", over_buffer("a"))
+                .expect("Failed to infer kernel")
+                .with_push_constants_over_buffer("a", |len| ScalarPushConstants {
+                    scalar: 5,
+                    num_elements: len,
+                });
+            let invocation_chain = vec![invoc_spec("add_5", vec!["a"], add_kernel)];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+            compute_chain.resize_buffer("a", 2 * n, 1u32).unwrap();
+            compute_chain.run_chain().expect("Failed to execute");
+
+            // num_elements covers the new elements too
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![6u32; 2 * n][..]);
+        }
+
+        #[test]
+        fn test_resize_under_fixed_invocations_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut grid = vec![GridCell::zeroed(); (N_GRID_X * N_GRID_X) as usize];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("grid", 4, &mut grid));
+            let add_kernel = inferred_kernel("add_scalar", over_buffer("a"))
+                .expect("Failed to infer kernel")
+                .with_push_constants(ScalarPushConstants {
+                    scalar: 5,
+                    num_elements: n as u32,
+                });
+            let clear_grid_kernel = kernel("clear_grid", vec![4], over_grid_2d(N_GRID_X, N_GRID_X));
+            let invocation_chain = vec![
+                invoc_spec("add_5", vec!["a"], add_kernel),
+                invoc_spec("clear_grid", vec!["grid"], clear_grid_kernel),
+            ];
+
+            let mut compute_chain: $backend =
+                ComputeBackend::create(&buf_specs, invocation_chain)
+                    .expect("Failed to create compute chain");
+
+            // num_elements and the grid size would keep the old lengths
+            assert!(matches!(
+                compute_chain.resize_buffer("a", 2 * n, 1u32),
+                Err(ChimeraError::FixedInvocationOverResizedBuffer { ref invocation_name, .. })
+                    if invocation_name == "add_5"
+            ));
+            assert!(matches!(
+                compute_chain.resize_buffer("grid", 4, GridCell::zeroed()),
+                Err(ChimeraError::FixedInvocationOverResizedBuffer { ref invocation_name, .. })
+                    if invocation_name == "clear_grid"
+            ));
+
+            // the chain keeps the buffers it had
+            compute_chain.run_chain().expect("Failed to execute");
+            let a_read = compute_chain.read_buffer::<u32>("a").unwrap();
+            assert_eq!(&a_read[..], &vec![6u32; n][..]);
+            assert_eq!(
+                compute_chain.read_buffer::<GridCell>("grid").unwrap().len(),
+                (N_GRID_X * N_GRID_X) as usize
+            );
+        }
+
+        #[test]
+        fn test_push_constants_over_unbound_buffer_is_rejected() {
+            let n = 64;
+            let mut a = vec![1u32; n];
+            let mut b = vec![1u32; n];
+
+            let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+            let add_kernel = inferred_kernel("add_scalar", over_buffer("a"))
+                .expect("Failed to infer kernel")
+                .with_push_constants_over_buffer("b", |len| ScalarPushConstants {
+                    scalar: 5,
+                    num_elements: len,
+                });
+            let invocation_chain = vec![invoc_spec("add_5", vec!["a"], add_kernel)];
+
+            let result: Result<$backend, _> = ComputeBackend::create(&buf_specs, invocation_chain);
+            assert!(matches!(
+                result,
+                Err(ChimeraError::PushConstantBufferNotBound { ref buf_name, .. }) if buf_name == "b"
+            ));
+        }
+    };
 }
 
-#[test]
-fn test_negative_velocity_wrapping() {
-    // Test particles with negative velocities that go below 0
-    let n = 64;
-    let mut x = vec![Vec2::new(0.1, 0.2); n];
-    let mut v = vec![Vec2::new(-0.05, -0.1); n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("x", 2, &mut x), buf_spec("v", 3, &mut v));
-
-    // Create kernel configurations
-    let wg_1d = num_workgroups_1d(n as u32);
-    let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
-    let wrap_particles_kernel = kernel("wrap_particles", vec![2], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![
-        invoc_spec(
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec(
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        invoc_spec("wrap_particles", vec!["x"], wrap_particles_kernel),
-    ];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_x = compute_chain
-        .typed_subbuffer_by_name::<Vec2>("x")
-        .expect("Failed to get buffer x");
-    let x_read = buffer_x.read().expect("Failed to read buffer x");
-
-    // Expected: x = 0.1 + 2*(-0.05) = 0.0
-    //           y = 0.2 + 2*(-0.1) = 0.0
-    let expected = Vec2::new(0.0, 0.0);
-    for (i, &actual) in x_read.iter().enumerate() {
-        assert!(
-            (actual.x - expected.x).abs() < 1e-5 && (actual.y - expected.y).abs() < 1e-5,
-            "Position x[{}] incorrect with negative velocity. Got: {:?}, Expected: {:?}",
-            i,
-            actual,
-            expected
+mod vulkano_backend {
+    use super::*;
+
+    compute_pass_tests!(VulkanoComputeChain<_>);
+
+    #[test]
+    fn test_insert_buffer_after_build() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = RegistryBufferSpecs::new()
+            .with(buf_spec("a", 0, &mut a))
+            .with(buf_spec("b", 1, &mut b));
+
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+        let mut compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain");
+
+        let grid = vec![GridCell::zeroed(); 16];
+        compute_chain
+            .insert_buffer("grid", 4, &grid)
+            .expect("Failed to insert buffer");
+
+        assert!(compute_chain.buffer_registry().contains("grid"));
+        let grid_read = compute_chain
+            .read_buffer::<GridCell>("grid")
+            .expect("Failed to read buffer grid");
+        assert_eq!(grid_read.len(), 16);
+        assert!(matches!(
+            compute_chain.read_buffer::<Vec2>("grid"),
+            Err(ChimeraError::BufferTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            compute_chain.insert_buffer("a", 0, &vec![0u32; n]),
+            Err(ChimeraError::DuplicateBufferName(ref name)) if name == "a"
+        ));
+        // the rejected insert leaves the registered buffer alone
+        let a_read = compute_chain
+            .read_buffer::<u32>("a")
+            .expect("Failed to read buffer a");
+        assert_eq!(&a_read[..], &vec![1u32; n][..]);
+    }
+
+    #[test]
+    fn test_device_local_registry_buffer_reads_through_staging() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = RegistryBufferSpecs::new()
+            .with(buf_spec("a", 0, &mut a).with_residency(Residency::DeviceLocal))
+            .with(buf_spec("b", 1, &mut b).with_residency(Residency::Readback));
+
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain");
+        compute_chain.execute().expect("Failed to execute");
+
+        let registry = compute_chain.buffer_registry();
+        assert_eq!(
+            registry.residency_by_name("a").unwrap(),
+            Residency::DeviceLocal
         );
+        assert_eq!(
+            registry.residency_by_name("b").unwrap(),
+            Residency::Readback
+        );
+        assert!(matches!(
+            registry.residency_by_name("c"),
+            Err(ChimeraError::DescriptorSetNameNotFound(_))
+        ));
+
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), vec![11u32; n]);
+        assert_eq!(compute_chain.readback::<u32>("b").unwrap(), vec![10u32; n]);
+        assert!(matches!(
+            compute_chain.readback::<Vec2>("a"),
+            Err(ChimeraError::BufferTypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_device_local_buffer_is_not_handed_out() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = RegistryBufferSpecs::new()
+            .with(buf_spec("a", 0, &mut a).with_residency(Residency::DeviceLocal))
+            .with(buf_spec("b", 1, &mut b));
+
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain");
+        compute_chain.execute().expect("Failed to execute");
+
+        // host writes to a copy would be lost, so the host goes through the chain
+        assert!(matches!(
+            compute_chain.typed_subbuffer_by_name::<u32>("a"),
+            Err(ChimeraError::DeviceLocalBuffer(ref name)) if name == "a"
+        ));
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), vec![11u32; n]);
+        compute_chain.write_buffer("a", &vec![2u32; n]).unwrap();
+        compute_chain.execute().expect("Failed to execute");
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), vec![12u32; n]);
+
+        assert!(matches!(
+            compute_chain.typed_subbuffer_by_name::<Vec2>("a"),
+            Err(ChimeraError::BufferTypeMismatch { .. })
+        ));
+        let b_view = compute_chain.typed_subbuffer_by_name::<u32>("b").unwrap();
+        assert_eq!(b_view.read().unwrap().to_vec(), vec![10u32; n]);
+    }
+
+    #[test]
+    fn test_resize_double_buffered_buffer() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![20u32; n];
+
+        let buf_specs = RegistryBufferSpecs::new()
+            .with(buf_spec("a", 0, &mut a).with_residency(Residency::DeviceLocal))
+            .with(buf_spec("b", 1, &mut b));
+        let adder_kernel = kernel("adder", vec![0, 1], over_buffer("a"));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+
+        let mut compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain")
+            .with_frames_in_flight(2, &["a"])
+            .expect("Failed to set frames in flight");
+        compute_chain.execute().expect("Failed to execute");
+
+        compute_chain.resize_buffer("a", 2 * n, 0u32).unwrap();
+        compute_chain.resize_buffer("b", 2 * n, 1u32).unwrap();
+        assert_eq!(compute_chain.buffer_len("a").unwrap(), 2 * n);
+        compute_chain.execute().expect("Failed to execute");
+
+        let snapshot = compute_chain.snapshot::<u32>("a").unwrap();
+        assert_eq!(snapshot.len() as usize, 2 * n);
+        let mut expected = vec![41u32; n];
+        expected.extend(vec![1u32; n]);
+        assert_eq!(snapshot.read().unwrap().to_vec(), expected);
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_chain_records_only_needed_barriers() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+        let mut x = vec![Vec2::new(0.5, 0.5); n];
+        let mut v = vec![Vec2::new(0.1, 0.1); n];
+
+        let buf_specs = (
+            buf_spec("a", 0, &mut a),
+            buf_spec("b", 1, &mut b),
+            buf_spec("x", 2, &mut x),
+            buf_spec("v", 3, &mut v),
+        );
+        let wg_1d = num_workgroups_1d(n as u32);
+        let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
+        let step_particles_kernel = kernel("step_particles", vec![2, 3], wg_1d);
+        let invocation_chain = vec![
+            invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)
+                .with_access("b", BufferAccess::Read),
+            invoc_spec(
+                "step_particles_0",
+                vec!["x", "v"],
+                step_particles_kernel.clone(),
+            )
+            .with_access("v", BufferAccess::Read),
+            invoc_spec("step_particles_1", vec!["x", "v"], step_particles_kernel)
+                .with_access("v", BufferAccess::Read),
+        ];
+
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain");
+
+        // adder_ab and step_particles_0 are independent; step_particles_1 waits for
+        // step_particles_0
+        let graph = compute_chain.dependency_graph();
+        assert_eq!(graph.barrier_count(), 1);
+        assert!(graph.needs_barrier_before(2));
+        assert!(graph.warnings().is_empty());
+
+        compute_chain.execute().expect("Failed to execute");
+        compute_chain.execute().expect("Failed to execute");
+        assert_eq!(
+            compute_chain.read_buffer::<u32>("a").unwrap(),
+            vec![21u32; n]
+        );
+        for x in compute_chain.read_buffer::<Vec2>("x").unwrap() {
+            assert!((x - Vec2::new(0.9, 0.9)).length() < 1e-5, "x = {x:?}");
+        }
+    }
+
+    #[test]
+    fn test_submissions_in_flight_run_in_order() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain")
+            .with_frames_in_flight(3, &[])
+            .expect("Failed to set frames in flight");
+
+        // more submissions than frames in flight, each adding to what the previous wrote
+        let submissions = (0..5)
+            .map(|_| compute_chain.submit().expect("Failed to submit"))
+            .collect::<Vec<_>>();
+        compute_chain
+            .wait(submissions[1])
+            .expect("Failed to wait for a submission");
+        assert!(compute_chain.is_complete(submissions[0]).unwrap());
+
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), vec![51u32; n]);
+        for submission in submissions {
+            assert!(compute_chain.is_complete(submission).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_host_visible_buffer_reads_after_submissions() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain.clone())
+            .expect("Failed to create compute chain")
+            .with_frames_in_flight(2, &[])
+            .expect("Failed to set frames in flight");
+
+        // the chain's own buffer, once every submission finished
+        let submission = compute_chain.submit().expect("Failed to submit");
+        compute_chain.submit().expect("Failed to submit");
+        let a_view = compute_chain.typed_subbuffer_by_name::<u32>("a").unwrap();
+        assert!(compute_chain.is_complete(submission).unwrap());
+        assert_eq!(a_view.read().unwrap().to_vec(), vec![21u32; n]);
+
+        assert!(matches!(
+            VulkanoComputeChain::new(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain")
+                .with_frames_in_flight(0, &[]),
+            Err(ChimeraError::ZeroFramesInFlight)
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_of_double_buffered_buffer() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain")
+            .with_frames_in_flight(2, &["a"])
+            .expect("Failed to set frames in flight");
+
+        assert!(matches!(
+            compute_chain.snapshot::<u32>("a"),
+            Err(ChimeraError::Other(_))
+        ));
+
+        compute_chain.submit().expect("Failed to submit");
+        compute_chain.submit().expect("Failed to submit");
+        compute_chain.sync().expect("Failed to sync");
+        let snapshot = compute_chain.snapshot::<u32>("a").unwrap();
+        assert_eq!(snapshot.read().unwrap().to_vec(), vec![21u32; n]);
+
+        // the snapshot keeps its contents while the next submission updates `a`
+        compute_chain.submit().expect("Failed to submit");
+        assert_eq!(snapshot.read().unwrap().to_vec(), vec![21u32; n]);
+        assert_eq!(compute_chain.readback::<u32>("a").unwrap(), vec![31u32; n]);
+
+        assert!(matches!(
+            compute_chain.snapshot::<Vec2>("a"),
+            Err(ChimeraError::TypedDescriptorSetNameNotFound(..))
+        ));
+        assert!(matches!(
+            compute_chain.snapshot::<u32>("b"),
+            Err(ChimeraError::DescriptorSetNameNotFound(_))
+        ));
     }
-}
 
-//
-// EXECUTION MULTIPLE TIMES TESTS
-//
-
-#[test]
-fn test_execute_chain_multiple_times() {
-    // Test executing the same chain multiple times
-    let n = 64;
-    let mut a = vec![0u32; n];
-    let mut b = vec![1u32; n];
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
-
-    // Create the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    // Execute 3 times
-    compute_chain.execute().expect("Failed to execute (1)");
-    compute_chain.execute().expect("Failed to execute (2)");
-    compute_chain.execute().expect("Failed to execute (3)");
-
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
-
-    // Expected: a = 0 + 1 + 1 + 1 = 3 (executed 3 times)
-    let expected = vec![3u32; n];
-    assert_eq!(&a_read[..], &expected[..], "Multiple executions failed");
+    #[test]
+    fn test_profile_times_each_invocation() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+        let mut x = vec![Vec2::new(0.5, 0.5); n];
+        let mut v = vec![Vec2::new(0.1, 0.1); n];
+
+        let buf_specs = (
+            buf_spec("a", 0, &mut a),
+            buf_spec("b", 1, &mut b),
+            buf_spec("x", 2, &mut x),
+            buf_spec("v", 3, &mut v),
+        );
+        let wg_1d = num_workgroups_1d(n as u32);
+        let invocation_chain = vec![
+            invoc_spec(
+                "adder_ab",
+                vec!["a", "b"],
+                kernel("adder", vec![0, 1], wg_1d),
+            ),
+            invoc_spec(
+                "step_particles",
+                vec!["x", "v"],
+                kernel("step_particles", vec![2, 3], wg_1d),
+            ),
+        ];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain")
+            .with_substeps(2)
+            .expect("Failed to set substeps")
+            .with_profiling(3)
+            .expect("Failed to enable profiling");
+
+        for _ in 0..5 {
+            compute_chain.execute().expect("Failed to execute");
+        }
+        assert_eq!(
+            compute_chain.read_buffer::<u32>("a").unwrap(),
+            vec![101u32; n]
+        );
+
+        // devices without timestamps run unprofiled
+        let Some(profile) = compute_chain.profile().expect("Failed to read timestamps") else {
+            return;
+        };
+        for name in ["adder_ab", "step_particles"] {
+            let timings = profile.timings(name).expect("No timings");
+            assert_eq!(timings.samples, 3);
+            assert!(timings.min <= timings.mean && timings.mean <= timings.max);
+        }
+        assert_eq!(profile.to_csv().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_any_buffer_typed_access() {
+        let n = 64;
+        let mut a = vec![1u32; n];
+        let mut b = vec![10u32; n];
+
+        let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
+        let adder_kernel = kernel("adder", vec![0, 1], num_workgroups_1d(n as u32));
+        let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
+        let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
+            .expect("Failed to create compute chain");
+
+        let matrices = vec![ParticleMatrices::new(); 8];
+        let buffer = AnyBuffer::from_data(compute_chain.memory_allocator().clone(), &matrices)
+            .expect("Failed to create buffer");
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(
+            buffer.element_size(),
+            std::mem::size_of::<ParticleMatrices>()
+        );
+        assert!(buffer.is::<ParticleMatrices>());
+
+        let mut updated = matrices.clone();
+        updated[3].C = Mat2::from_cols_array(&[1.0, 2.0, 3.0, 4.0]);
+        buffer.write(&updated).expect("Failed to write buffer");
+        let read = buffer
+            .read::<ParticleMatrices>()
+            .expect("Failed to read buffer");
+        assert_eq!(read[3].C, updated[3].C);
+        assert_eq!(read[0].F, Mat2::IDENTITY);
+
+        assert!(matches!(
+            buffer.downcast::<GridCell>(),
+            Err(ChimeraError::AnyBufferTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            buffer.read::<Vec2>(),
+            Err(ChimeraError::AnyBufferTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            buffer.write(&matrices[..4]),
+            Err(ChimeraError::BufferLengthMismatch {
+                buffer_len: 8,
+                data_len: 4
+            })
+        ));
+    }
 }
 
-#[test]
-fn test_varied_data_patterns() {
-    // Test with varied input data (not all uniform)
-    let n = 64;
-    let mut a = (0..n as u32).collect::<Vec<u32>>();
-    let mut b = (0..n as u32).map(|i| i * 2).collect::<Vec<u32>>();
-
-    // Create buf_specs
-    let buf_specs = (buf_spec("a", 0, &mut a), buf_spec("b", 1, &mut b));
-
-    // Create kernel configuration
-    let wg_1d = num_workgroups_1d(n as u32);
-    let adder_kernel = kernel("adder", vec![0, 1], wg_1d);
-
-    // Create invocation chain
-    let invocation_chain = vec![invoc_spec("adder_ab", vec!["a", "b"], adder_kernel)];
-
-    // Create and execute the compute chain
-    let compute_chain = VulkanoComputeChain::new(&buf_specs, invocation_chain)
-        .expect("Failed to create VulkanoComputeChain");
-
-    compute_chain.execute().expect("Failed to execute");
-
-    // Verify results
-    let buffer_a = compute_chain
-        .typed_subbuffer_by_name::<u32>("a")
-        .expect("Failed to get buffer a");
-    let a_read = buffer_a.read().expect("Failed to read buffer a");
-
-    // Expected: a[i] = i + 2*i = 3*i
-    let expected: Vec<u32> = (0..n as u32).map(|i| 3 * i).collect();
-    assert_eq!(
-        &a_read[..],
-        &expected[..],
-        "Varied data pattern test failed"
-    );
+mod cpu_backend {
+    use super::*;
+
+    compute_pass_tests!(CpuComputeChain<_>);
+
+    #[test]
+    fn test_too_small_push_constants_are_rejected() {
+        let n = 64;
+        let mut a = vec![0u32; n];
+
+        let buf_specs = (buf_spec("a", 0, &mut a),);
+
+        // only `scalar`, without `num_elements`
+        let add_kernel =
+            kernel("add_scalar", vec![0], num_workgroups_1d(n as u32)).with_push_constants(1u32);
+        let invocation_chain = vec![invoc_spec("add", vec!["a"], add_kernel)];
+
+        let compute_chain: CpuComputeChain<_> =
+            ComputeBackend::create(&buf_specs, invocation_chain)
+                .expect("Failed to create compute chain");
+        assert!(matches!(
+            compute_chain.run_chain(),
+            Err(ChimeraError::PushConstantsMismatch {
+                shader_has_push_constants: true,
+                ..
+            })
+        ));
+    }
 }
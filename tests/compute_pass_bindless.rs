@@ -7,45 +7,34 @@
 //
 // The tests verify that all buffers are correctly modified after the pass.
 
+mod common;
+
+use bytemuck::Zeroable;
+use common::full_compute_pass_info;
 use glam::Vec2;
+use rust_gpu_chimera_demo::error::ChimeraError;
+use rust_gpu_chimera_demo::runners::cpu_dispatch::{dispatch_serial, LOCAL_SIZE_1D};
 use rust_gpu_chimera_demo::runners::vulkano::shader_buffer_mapping::ComputePassInvocationInfo;
+use rust_gpu_chimera_demo::runners::vulkano_bindless::heap::BindlessHeap;
+use rust_gpu_chimera_demo::runners::vulkano_bindless::shader_buffer_mapping::{
+    bindless_dispatch, BindlessDispatchSpec,
+};
+use rust_gpu_chimera_demo::runners::vulkano_bindless::unified_buffer::{
+    UnifiedBufferTracker, UNIFIED_OFFSET_ALIGNMENT,
+};
 use rust_gpu_chimera_demo::runners::vulkano_bindless::VulkanoBindlessRunner;
+use shared::grid::GridCell;
+use shared::particles::{Material, MaterialPod, ParticleDeformation, ParticleMatrices};
+use shared::{
+    num_workgroups_1d, num_workgroups_2d, AdderPushConstants, BindlessGridPushConstants,
+    P2gHeapPushConstants, ParticlePushConstants, N_GRID_TOTAL,
+};
 
 /// Create a VulkanoBindlessRunner with the full compute pass configuration
 /// This matches the configuration used in main.rs
 fn create_full_compute_pass_runner() -> VulkanoBindlessRunner {
-    let adder_kernel = ("adder", vec![0, 1]);
-    let step_particles_kernel = ("step_particles", vec![2, 3]);
-    let wrap_particles_kernel = ("wrap_particles", vec![2]);
-
-    let shader_buffers = ComputePassInvocationInfo::from_lists(vec![
-        ("adder_ab", vec!["a", "b"], adder_kernel.clone()),
-        ("adder_ac", vec!["a", "c"], adder_kernel.clone()),
-        ("adder_ad", vec!["a", "d"], adder_kernel.clone()),
-        (
-            "step_particles_0",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        (
-            "step_particles_1",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        (
-            "step_particles_2",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        (
-            "step_particles_3",
-            vec!["x", "v"],
-            step_particles_kernel.clone(),
-        ),
-        ("wrap_particles", vec!["x"], wrap_particles_kernel.clone()),
-    ]);
-
-    VulkanoBindlessRunner::new(shader_buffers).expect("Failed to create VulkanoBindlessRunner")
+    VulkanoBindlessRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoBindlessRunner")
 }
 
 #[test]
@@ -412,3 +401,386 @@ fn test_compute_pass_spiral_pattern() {
         );
     }
 }
+
+#[test]
+fn test_unified_buffers_of_different_lengths() {
+    let runner = create_full_compute_pass_runner();
+
+    // Logical buffers of different lengths, plus a buffer no shader uses
+    let a = (0..100u32).collect::<Vec<u32>>();
+    let b = vec![1u32; 100];
+    let c = vec![2u32; 100];
+    let d = vec![3u32; 100];
+    let x = vec![Vec2::new(0.5, 0.5); 37];
+    let v = vec![Vec2::new(0.1, 0.0); 37];
+    let grid = vec![GridCell::zeroed(); 10];
+
+    let unified_buffers = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("x", &x)
+        .with_buffer("b", &b)
+        .with_buffer("grid", &grid)
+        .with_buffer("c", &c)
+        .with_buffer("v", &v)
+        .with_buffer("d", &d)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build unified buffers");
+
+    // One unified buffer per element type, bound in the order the types were added
+    assert_eq!(unified_buffers.unified_buffers().len(), 3);
+    for name in ["a", "b", "c", "d", "x", "v", "grid"] {
+        let logical = unified_buffers.logical_buffer(name).unwrap();
+        let element_size = unified_buffers.unified_buffers()[logical.unified_buffer]
+            .buffer
+            .element_size() as u64;
+        assert_eq!(
+            logical.offset as u64 * element_size % UNIFIED_OFFSET_ALIGNMENT,
+            0,
+            "Logical buffer {name} is not aligned"
+        );
+    }
+    assert_eq!(unified_buffers.len("x").unwrap(), 37);
+
+    runner
+        .run_compute(&unified_buffers, 2)
+        .expect("Compute pass failed");
+
+    let a_result = unified_buffers.read::<u32>("a").unwrap();
+    assert_eq!(a_result, (0..100u32).map(|i| i + 6).collect::<Vec<_>>());
+
+    let x_result = unified_buffers.read::<Vec2>("x").unwrap();
+    for (i, actual) in x_result.iter().enumerate() {
+        assert!(
+            (actual.x - 0.9).abs() < 1e-5 && (actual.y - 0.5).abs() < 1e-5,
+            "Buffer x[{i}] incorrect: {actual:?}"
+        );
+    }
+    assert_eq!(unified_buffers.read::<GridCell>("grid").unwrap().len(), 10);
+}
+
+#[test]
+fn test_compute_chain_persists_across_executions() {
+    let runner = create_full_compute_pass_runner();
+
+    let a = vec![1u32; 8];
+    let b = vec![10u32; 8];
+    let c = vec![100u32; 8];
+    let d = vec![1000u32; 8];
+    let x = vec![Vec2::new(0.5, 0.5); 8];
+    let v = vec![Vec2::new(0.025, 0.0); 8];
+    let unified_buffers = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("b", &b)
+        .with_buffer("c", &c)
+        .with_buffer("d", &d)
+        .with_buffer("x", &x)
+        .with_buffer("v", &v)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build unified buffers");
+
+    let chain = runner
+        .build_compute_chain(
+            unified_buffers,
+            &runner
+                .dispatch_specs(1)
+                .expect("Failed to describe dispatches"),
+        )
+        .expect("Failed to build compute chain");
+    // The view handed to the renderer, taken before any execution
+    let x_view = chain.subbuffer::<Vec2>("x").unwrap();
+    assert_eq!(x_view.len(), 8);
+
+    for frame in 1..=3u32 {
+        chain.execute().expect("Compute pass failed");
+
+        // The buffers stay resident, so every execution builds on the previous one
+        assert_eq!(chain.read::<u32>("a").unwrap(), vec![1 + 1110 * frame; 8]);
+        let expected_x = 0.5 + 0.1 * frame as f32;
+        for actual in x_view.read().unwrap().iter() {
+            assert!(
+                (actual.x - expected_x).abs() < 1e-5 && (actual.y - 0.5).abs() < 1e-5,
+                "Frame {frame}: x = {actual:?}, expected {expected_x}"
+            );
+        }
+    }
+
+    // Host writes between executions are picked up by the next one
+    chain.write("a", &[0u32; 8]).unwrap();
+    chain.execute().expect("Compute pass failed");
+    assert_eq!(chain.read::<u32>("a").unwrap(), vec![1110; 8]);
+}
+
+#[test]
+fn test_grid_and_particle_dispatches_in_one_pass() {
+    let runner = create_full_compute_pass_runner();
+
+    // 1000 particles and a 40 x 30 grid: the two kinds of kernels need different
+    // workgroup counts and push constants
+    let n_particles = 1000u32;
+    let (n_grid_x, n_grid_y) = (40u32, 30u32);
+    let a = vec![1u32; n_particles as usize];
+    let b = vec![2u32; n_particles as usize];
+    let x = vec![Vec2::new(0.5, 0.25); n_particles as usize];
+    let v = vec![Vec2::new(0.125, 0.0); n_particles as usize];
+    let grid = vec![
+        GridCell {
+            v: Vec2::ONE,
+            mass: 1.0
+        };
+        (n_grid_x * n_grid_y) as usize
+    ];
+    // u32 -> binding 0, Vec2 -> binding 1, GridCell -> binding 2
+    let unified_buffers = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("b", &b)
+        .with_buffer("x", &x)
+        .with_buffer("v", &v)
+        .with_buffer("grid", &grid)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build unified buffers");
+
+    let dispatches = [
+        bindless_dispatch(
+            "adder_ab",
+            "adder",
+            vec!["a", "b"],
+            num_workgroups_1d(n_particles),
+            move |u| {
+                Ok(AdderPushConstants {
+                    a_offset: u.offset("a")?,
+                    b_offset: u.offset("b")?,
+                    buffer_size: n_particles,
+                    _padding: 0,
+                })
+            },
+        ),
+        bindless_dispatch(
+            "clear_grid",
+            "clear_grid",
+            vec!["grid"],
+            num_workgroups_2d(n_grid_x, n_grid_y),
+            move |u| {
+                Ok(BindlessGridPushConstants {
+                    grid_offset: u.offset("grid")?,
+                    n_grid_x,
+                    n_grid_y,
+                    _padding: 0,
+                })
+            },
+        ),
+        bindless_dispatch(
+            "step_particles",
+            "step_particles",
+            vec!["x", "v"],
+            num_workgroups_1d(n_particles),
+            move |u| {
+                Ok(ParticlePushConstants {
+                    x_offset: u.offset("x")?,
+                    v_offset: u.offset("v")?,
+                    buffer_size: n_particles,
+                    _padding: 0,
+                })
+            },
+        ),
+    ];
+    assert_eq!(dispatches[1].num_workgroups(), [5, 4, 1]);
+
+    runner
+        .run_dispatches(&unified_buffers, &dispatches)
+        .expect("Compute pass failed");
+
+    assert_eq!(
+        unified_buffers.read::<u32>("a").unwrap(),
+        vec![3u32; n_particles as usize]
+    );
+    for (i, actual) in unified_buffers
+        .read::<Vec2>("x")
+        .unwrap()
+        .iter()
+        .enumerate()
+    {
+        assert!(
+            (actual.x - 0.625).abs() < 1e-6 && (actual.y - 0.25).abs() < 1e-6,
+            "Buffer x[{i}] incorrect: {actual:?}"
+        );
+    }
+    for (i, cell) in unified_buffers
+        .read::<GridCell>("grid")
+        .unwrap()
+        .iter()
+        .enumerate()
+    {
+        assert!(
+            cell.mass == 0.0 && cell.v == Vec2::ZERO,
+            "Grid cell {i} not cleared: {cell:?}"
+        );
+    }
+}
+
+#[test]
+fn test_offsets_push_constants_reject_unsupported_invocations() {
+    // the offsets push constants only describe adder and the particle kernels
+    let info = ComputePassInvocationInfo::from_lists(vec![(
+        "clear",
+        vec!["grid"],
+        ("clear_grid", vec![4]),
+    )]);
+    assert!(matches!(
+        BindlessDispatchSpec::from_invocation_info(&info, [1, 1, 1]),
+        Err(ChimeraError::KernelNotFound(ref name)) if name == "clear_grid"
+    ));
+
+    // ... with the offsets of at most two buffers
+    let info = ComputePassInvocationInfo::from_lists(vec![(
+        "adder_abc",
+        vec!["a", "b", "c"],
+        ("adder", vec![0, 1]),
+    )]);
+    assert!(matches!(
+        BindlessDispatchSpec::from_invocation_info(&info, [1, 1, 1]),
+        Err(ChimeraError::PushConstantBufferCountOutOfRange {
+            min: 1,
+            max: 2,
+            found: 3,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_unified_buffer_read_write_errors() {
+    let runner = create_full_compute_pass_runner();
+
+    let a = vec![1u32; 8];
+    let x = vec![Vec2::ZERO; 4];
+    let unified_buffers = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("x", &x)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build unified buffers");
+
+    unified_buffers
+        .write("a", &[7u32; 8])
+        .expect("Failed to write buffer a");
+    assert_eq!(unified_buffers.read::<u32>("a").unwrap(), vec![7u32; 8]);
+
+    assert!(matches!(
+        unified_buffers.read::<u32>("missing"),
+        Err(ChimeraError::DescriptorSetNameNotFound(_))
+    ));
+    assert!(matches!(
+        unified_buffers.read::<Vec2>("a"),
+        Err(ChimeraError::BufferTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        unified_buffers.write("x", &[Vec2::ONE; 3]),
+        Err(ChimeraError::BufferLengthMismatch {
+            buffer_len: 4,
+            data_len: 3
+        })
+    ));
+
+    let duplicate = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("a", &x)
+        .build(runner.memory_allocator().clone());
+    assert!(matches!(
+        duplicate,
+        Err(ChimeraError::DuplicateBufferName(name)) if name == "a"
+    ));
+}
+
+#[test]
+fn test_alignment_applies_to_buffers_added_before_it() {
+    let runner = create_full_compute_pass_runner();
+
+    let a = vec![1u32; 3];
+    let b = vec![2u32; 5];
+    let unified_buffers = UnifiedBufferTracker::builder()
+        .with_buffer("a", &a)
+        .with_buffer("b", &b)
+        .with_alignment(64)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build unified buffers");
+
+    // 64 bytes are 16 `u32`s
+    assert_eq!(unified_buffers.offset("a").unwrap(), 0);
+    assert_eq!(unified_buffers.offset("b").unwrap(), 16);
+    assert_eq!(unified_buffers.read::<u32>("b").unwrap(), b);
+}
+
+#[test]
+fn test_heap_p2g_matches_cpu_p2g() {
+    let runner = create_full_compute_pass_runner();
+
+    let n = 301;
+    let mut x: Vec<Vec2> = (0..n)
+        .map(|i| Vec2::new(0.3 + 0.002 * (i % 20) as f32, 0.3 + 0.002 * (i / 20) as f32))
+        .collect();
+    let mut v = vec![Vec2::new(0.5, -0.25); n];
+    let materials = [Material::Fluid, Material::Jelly, Material::Snow];
+    let mut material: Vec<MaterialPod> = (0..n).map(|i| materials[i % 3].into()).collect();
+    let mut matrices = vec![ParticleMatrices::new(); n];
+    let mut deformation = vec![ParticleDeformation::new(); n];
+    let mut grid = vec![GridCell::zeroed(); N_GRID_TOTAL as usize];
+
+    let heap = BindlessHeap::builder()
+        .with_buffer("x", &x)
+        .with_buffer("v", &v)
+        .with_buffer("material", &material)
+        .with_buffer("grid", &grid)
+        .with_buffer("matrices", &matrices)
+        .with_buffer("deformation", &deformation)
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build heap");
+    let offset = |name| heap.byte_offset(name).unwrap();
+    let push_constants = P2gHeapPushConstants {
+        x: offset("x"),
+        v: offset("v"),
+        grid: offset("grid"),
+        matrices: offset("matrices"),
+        deformation: offset("deformation"),
+        material: offset("material"),
+        num_particles: n as u32,
+        _padding: 0,
+    };
+    runner
+        .run_heap_kernel(&heap, "p2g", push_constants, num_workgroups_1d(n as u32))
+        .expect("Heap p2g failed");
+
+    // the bindful p2g on the CPU as reference
+    dispatch_serial(num_workgroups_1d(n as u32), LOCAL_SIZE_1D, |id| {
+        shaders::p2g::p2g(
+            id,
+            &mut x,
+            &v,
+            &mut grid,
+            &mut matrices,
+            &mut deformation,
+            &mut material,
+        )
+    });
+
+    let heap_grid = heap.read::<GridCell>("grid").unwrap();
+    for (i, (cell, expected)) in heap_grid.iter().zip(grid.iter()).enumerate() {
+        assert!(
+            (cell.mass - expected.mass).abs() <= 1e-3 * expected.mass.max(1e-6),
+            "cell {i}: mass {} != {}",
+            cell.mass,
+            expected.mass
+        );
+        assert!(
+            (cell.v - expected.v).length() <= 1e-3 * (1.0 + expected.v.length()),
+            "cell {i}: v {:?} != {:?}",
+            cell.v,
+            expected.v
+        );
+    }
+    let heap_deformation = heap.read::<ParticleDeformation>("deformation").unwrap();
+    for i in 0..n {
+        assert!(
+            (heap_deformation[i].J - deformation[i].J).abs() < 1e-4,
+            "J[{i}]"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+// Integration tests for the descriptor indexing runner
+//
+// The descriptor array runner runs the same `ComputePassInvocationInfo` as the
+// bindless runner (adder x3, step_particles x4, wrap_particles), with each logical
+// buffer in its own slot of a runtime array of storage buffer descriptors. These
+// tests check that both binding models produce the same buffers.
+//
+// Descriptor indexing is core in Vulkan 1.2, so the tests also run under a software
+// ICD such as lavapipe:
+//
+//     VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json \
+//         cargo test --test compute_pass_descriptor_array
+
+mod common;
+
+use common::full_compute_pass_info;
+use glam::Vec2;
+use rust_gpu_chimera_demo::error::ChimeraError;
+use rust_gpu_chimera_demo::runners::vulkano::shader_buffer_mapping::ComputePassInvocationInfo;
+use rust_gpu_chimera_demo::runners::vulkano_descriptor_array::buffers::DescriptorArrayBuffers;
+use rust_gpu_chimera_demo::runners::vulkano_descriptor_array::DescriptorArray;
+use rust_gpu_chimera_demo::runners::VulkanoDescriptorArrayRunner;
+
+#[test]
+fn test_descriptor_array_matches_bindless() {
+    common::assert_matches_bindless::<DescriptorArray>();
+}
+
+#[test]
+fn test_descriptor_array_slot_order_does_not_matter() {
+    let runner = VulkanoDescriptorArrayRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoDescriptorArrayRunner");
+
+    // the particle buffers first, and the adder operands in reverse order
+    let buffers = DescriptorArrayBuffers::builder()
+        .with_buffer("v", &[Vec2::new(0.1, 0.2); 64])
+        .with_buffer("x", &[Vec2::new(0.5, 0.5); 64])
+        .with_buffer("d", &[1000u32; 64])
+        .with_buffer("c", &[100u32; 64])
+        .with_buffer("b", &[10u32; 64])
+        .with_buffer("a", &[1u32; 64])
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build buffers");
+    assert_eq!(buffers.slot("v").unwrap(), 0);
+    assert_eq!(buffers.slot("a").unwrap(), 5);
+
+    runner
+        .run_compute(&buffers, 1)
+        .expect("Compute pass failed");
+
+    assert_eq!(buffers.read::<u32>("a").unwrap(), vec![1111u32; 64]);
+    for x in buffers.read::<Vec2>("x").unwrap() {
+        // 0.5 + 4 * (0.1, 0.2) = (0.9, 1.3), wrapped to (0.9, 0.3)
+        assert!((x - Vec2::new(0.9, 0.3)).length() < 1e-5, "x = {x:?}");
+    }
+    // the inputs are untouched
+    assert_eq!(buffers.read::<u32>("b").unwrap(), vec![10u32; 64]);
+}
+
+#[test]
+fn test_descriptor_array_buffers() {
+    let runner = VulkanoDescriptorArrayRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoDescriptorArrayRunner");
+    let buffers = DescriptorArrayBuffers::builder()
+        .with_buffer("a", &[1u32; 8])
+        .with_buffer("x", &[Vec2::ZERO; 4])
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build buffers");
+
+    assert_eq!(buffers.num_slots(), 2);
+    assert_eq!(buffers.slot("a").unwrap(), 0);
+    assert_eq!(buffers.slot("x").unwrap(), 1);
+    assert_eq!(buffers.len("x").unwrap(), 4);
+
+    buffers.write("a", &[7u32; 8]).unwrap();
+    assert_eq!(buffers.read::<u32>("a").unwrap(), vec![7u32; 8]);
+
+    assert!(matches!(
+        buffers.slot("missing"),
+        Err(ChimeraError::DescriptorSetNameNotFound(_))
+    ));
+    assert!(matches!(
+        buffers.read::<Vec2>("a"),
+        Err(ChimeraError::BufferTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        buffers.write("a", &[7u32; 3]),
+        Err(ChimeraError::BufferLengthMismatch {
+            buffer_len: 8,
+            data_len: 3
+        })
+    ));
+    assert!(matches!(
+        DescriptorArrayBuffers::builder()
+            .with_buffer("a", &[1u32])
+            .with_buffer("a", &[Vec2::ZERO])
+            .build(runner.memory_allocator().clone()),
+        Err(ChimeraError::DuplicateBufferName(_))
+    ));
+}
+
+#[test]
+fn test_descriptor_array_rejects_invocations_with_too_many_buffers() {
+    // the push constants hold the slots of one or two buffers
+    let compute_pass_info = ComputePassInvocationInfo::from_lists(vec![(
+        "adder_abc",
+        vec!["a", "b", "c"],
+        ("adder", vec![0, 1]),
+    )]);
+    let runner = VulkanoDescriptorArrayRunner::new(compute_pass_info)
+        .expect("Failed to create VulkanoDescriptorArrayRunner");
+    let buffers = DescriptorArrayBuffers::builder()
+        .with_buffer("a", &[1u32; 64])
+        .with_buffer("b", &[10u32; 64])
+        .with_buffer("c", &[100u32; 64])
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build buffers");
+
+    assert!(matches!(
+        runner.run_compute(&buffers, 1),
+        Err(ChimeraError::PushConstantBufferCountOutOfRange {
+            min: 1,
+            max: 2,
+            found: 3,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,95 @@
+// Integration tests for the buffer device address runner
+//
+// The device address runner runs the same `ComputePassInvocationInfo` as the
+// bindless runner (adder x3, step_particles x4, wrap_particles), with each logical
+// buffer in its own allocation and passed to the shaders by address. These tests
+// check that both binding models produce the same buffers.
+
+mod common;
+
+use common::full_compute_pass_info;
+use glam::Vec2;
+use rust_gpu_chimera_demo::error::ChimeraError;
+use rust_gpu_chimera_demo::runners::vulkano::shader_buffer_mapping::ComputePassInvocationInfo;
+use rust_gpu_chimera_demo::runners::vulkano_device_address::buffers::DeviceAddressBuffers;
+use rust_gpu_chimera_demo::runners::vulkano_device_address::DeviceAddress;
+use rust_gpu_chimera_demo::runners::VulkanoDeviceAddressRunner;
+
+#[test]
+fn test_device_address_matches_bindless() {
+    common::assert_matches_bindless::<DeviceAddress>();
+}
+
+#[test]
+fn test_device_address_buffers() {
+    let runner = VulkanoDeviceAddressRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoDeviceAddressRunner");
+    let buffers = DeviceAddressBuffers::builder()
+        .with_buffer("a", &[1u32; 8])
+        .with_buffer("x", &[Vec2::ZERO; 4])
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build buffers");
+
+    // separate allocations, with distinct non-null addresses
+    let (a, x) = (buffers.address("a").unwrap(), buffers.address("x").unwrap());
+    assert_ne!(a, x);
+    assert_eq!(buffers.len("x").unwrap(), 4);
+
+    buffers.write("a", &[7u32; 8]).unwrap();
+    assert_eq!(buffers.read::<u32>("a").unwrap(), vec![7u32; 8]);
+
+    assert!(matches!(
+        buffers.address("missing"),
+        Err(ChimeraError::DescriptorSetNameNotFound(_))
+    ));
+    assert!(matches!(
+        buffers.read::<Vec2>("a"),
+        Err(ChimeraError::BufferTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        buffers.write("a", &[7u32; 3]),
+        Err(ChimeraError::BufferLengthMismatch {
+            buffer_len: 8,
+            data_len: 3
+        })
+    ));
+    assert!(matches!(
+        DeviceAddressBuffers::builder()
+            .with_buffer("a", &[1u32])
+            .with_buffer("a", &[Vec2::ZERO])
+            .build(runner.memory_allocator().clone()),
+        Err(ChimeraError::DuplicateBufferName(_))
+    ));
+}
+
+#[test]
+fn test_device_address_rejects_invalid_inputs() {
+    let runner = VulkanoDeviceAddressRunner::new(full_compute_pass_info())
+        .expect("Failed to create VulkanoDeviceAddressRunner");
+    let (mut a, b) = (vec![1u32; 8], vec![10u32; 4]);
+    let (c, d) = (vec![100u32; 8], vec![1000u32; 8]);
+    let (mut x, v) = (vec![Vec2::ZERO; 8], vec![Vec2::ZERO; 8]);
+    assert!(matches!(
+        runner.run_compute_and_get_buffer(&mut a, &b, &c, &d, &mut x, &v),
+        Err(ChimeraError::BufferLengthMismatch {
+            buffer_len: 8,
+            data_len: 4
+        })
+    ));
+
+    // only adder and the particle kernels take buffer addresses in push constants
+    let runner = VulkanoDeviceAddressRunner::new(ComputePassInvocationInfo::from_lists(vec![(
+        "clear",
+        vec!["grid"],
+        ("clear_grid", vec![4]),
+    )]))
+    .expect("Failed to create VulkanoDeviceAddressRunner");
+    let buffers = DeviceAddressBuffers::builder()
+        .with_buffer("grid", &[0u32; 64])
+        .build(runner.memory_allocator().clone())
+        .expect("Failed to build buffers");
+    assert!(matches!(
+        runner.run_compute(&buffers, 1),
+        Err(ChimeraError::KernelNotFound(ref name)) if name == "clear_grid"
+    ));
+}
@@ -0,0 +1,345 @@
+//! Tests for running shader entry points on the CPU with the dispatch emulator
+//!
+//! - Every `global_invocation_id` of a dispatch is visited exactly once, in parallel
+//!   and serially
+//! - Simple shaders produce the expected results
+//! - Float atomics are emulated correctly under contention
+//! - The MPM shaders match the `cpu_mpm` reference implementation
+//! - The bindless heap p2g matches the bindful p2g
+//! - The device address shaders work with host pointers as addresses
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bytemuck::Zeroable;
+use glam::{UVec3, Vec2};
+use rust_gpu_chimera_demo::{
+    cpu_mpm,
+    error::ChimeraError,
+    runners::{
+        cpu_dispatch::{
+            atomic_f_add, dispatch, dispatch_serial, SharedSlice, LOCAL_SIZE_1D, LOCAL_SIZE_2D,
+        },
+        vulkano_bindless::heap::BindlessHeap,
+    },
+};
+use shared::{
+    grid::{linear_grid_index_unit_xy, BoundaryCondition, GridCell, GridUpdateParams},
+    num_workgroups_1d, num_workgroups_2d,
+    particles::{Material, MaterialPod, ParticleDeformation, ParticleMatrices},
+    AdderAddressPushConstants, P2gHeapPushConstants, ParticleAddressPushConstants, N_GRID_TOTAL,
+    N_GRID_X,
+};
+
+/// A square block of particles with a few materials and a swirling velocity field
+fn particles(n_side: u32) -> (Vec<Vec2>, Vec<Vec2>, Vec<MaterialPod>) {
+    let materials = [Material::Fluid, Material::Jelly, Material::Snow];
+    let n = (n_side * n_side) as usize;
+    let x = (0..n as u32)
+        .map(|i| Vec2::new(0.4, 0.4) + 0.0031 * Vec2::new((i % n_side) as f32, (i / n_side) as f32))
+        .collect::<Vec<_>>();
+    let v = x
+        .iter()
+        .map(|x| (*x - Vec2::splat(0.45)).perp() * 2.0)
+        .collect::<Vec<_>>();
+    let material = (0..n).map(|i| materials[i % 3].into()).collect();
+    (x, v, material)
+}
+
+fn assert_close(a: Vec2, b: Vec2, tol: f32, what: &str) {
+    assert!(
+        (a - b).length() <= tol * (1.0 + b.length()),
+        "{what}: {a:?} != {b:?}"
+    );
+}
+
+#[test]
+fn test_dispatch_visits_every_invocation_once() {
+    let num_workgroups = [3, 2, 2];
+    let local_size = [4, 2, 1];
+    let size = UVec3::from(num_workgroups) * UVec3::from(local_size);
+    let counts = (0..size.element_product())
+        .map(|_| AtomicU32::new(0))
+        .collect::<Vec<_>>();
+
+    dispatch(num_workgroups, local_size, |id| {
+        assert!(id.cmplt(size).all(), "invocation {id:?} outside {size:?}");
+        let i = id.x + size.x * (id.y + size.y * id.z);
+        counts[i as usize].fetch_add(1, Ordering::Relaxed);
+    });
+
+    assert!(counts.iter().all(|c| c.load(Ordering::Relaxed) == 1));
+}
+
+#[test]
+fn test_dispatch_serial_visits_every_invocation_once() {
+    let num_workgroups = [3, 2, 2];
+    let local_size = [4, 2, 1];
+    let size = UVec3::from(num_workgroups) * UVec3::from(local_size);
+    let mut counts = vec![0u32; size.element_product() as usize];
+
+    dispatch_serial(num_workgroups, local_size, |id| {
+        assert!(id.cmplt(size).all(), "invocation {id:?} outside {size:?}");
+        counts[(id.x + size.x * (id.y + size.y * id.z)) as usize] += 1;
+    });
+
+    assert!(counts.iter().all(|c| *c == 1));
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds")]
+fn test_shared_slice_checks_bounds() {
+    let mut a = vec![0u32; 4];
+    let a = SharedSlice::new(&mut a);
+    unsafe { a.write(4, 1) };
+}
+
+#[test]
+fn test_adder_shader() {
+    let n = 256;
+    let mut a = (0..n as u32).collect::<Vec<_>>();
+    let b = vec![7u32; n];
+
+    dispatch_serial(num_workgroups_1d(n as u32), LOCAL_SIZE_1D, |id| {
+        shaders::adder(id, &mut a, &b)
+    });
+
+    assert_eq!(a, (0..n as u32).map(|i| i + 7).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_device_address_shaders() {
+    // On the host, a device address is a plain pointer
+    let n = 100u32;
+    let mut a: Vec<u32> = (0..n).collect();
+    let b = vec![5u32; n as usize];
+    let mut x = vec![Vec2::new(0.5, 0.9); n as usize];
+    let v = vec![Vec2::new(0.25, 0.25); n as usize];
+
+    let adder = AdderAddressPushConstants {
+        a: a.as_mut_ptr() as u64,
+        b: b.as_ptr() as u64,
+        buffer_size: n,
+        _padding: 0,
+    };
+    let particles = ParticleAddressPushConstants {
+        x: x.as_mut_ptr() as u64,
+        v: v.as_ptr() as u64,
+        buffer_size: n,
+        _padding: 0,
+    };
+    dispatch(num_workgroups_1d(n), LOCAL_SIZE_1D, |id| {
+        shaders::device_address::adder(id, &adder)
+    });
+    dispatch(num_workgroups_1d(n), LOCAL_SIZE_1D, |id| {
+        shaders::device_address::step_particles(id, &particles)
+    });
+    dispatch(num_workgroups_1d(n), LOCAL_SIZE_1D, |id| {
+        shaders::device_address::wrap_particles(id, &particles)
+    });
+
+    assert_eq!(a, (5..n + 5).collect::<Vec<_>>());
+    for xi in &x {
+        assert_close(*xi, Vec2::new(0.75, 0.15), 1e-6, "x");
+    }
+}
+
+#[test]
+fn test_float_atomics_under_contention() {
+    // every particle adds 0.1 to the mass of the same cell, as in `p2g_simple_test`
+    let n = 64 * 64;
+    let x = vec![Vec2::new(0.5, 0.5); n];
+    let mut grid = vec![GridCell::zeroed(); N_GRID_TOTAL as usize];
+
+    let grid_shared = SharedSlice::new(&mut grid);
+    dispatch(num_workgroups_1d(n as u32), LOCAL_SIZE_1D, |id| {
+        let p = x[id.x as usize];
+        let cell = grid_shared.element_ptr(linear_grid_index_unit_xy(p.x, p.y));
+        unsafe { atomic_f_add(std::ptr::addr_of_mut!((*cell).mass), 0.1) };
+    });
+
+    // no adds may be lost; only f32 rounding of the running sum is allowed
+    let total: f32 = grid.iter().map(|c| c.mass).sum();
+    let expected = 0.1 * n as f32;
+    assert!(
+        (total - expected).abs() < 1e-4 * expected,
+        "total mass {total}, expected {expected}"
+    );
+    assert_eq!(grid.iter().filter(|c| c.mass > 0.0).count(), 1);
+}
+
+#[test]
+fn test_mpm_shaders_match_cpu_reference() {
+    let (mut x, mut v, mut material) = particles(24);
+    let n = x.len();
+    let mut matrices = vec![ParticleMatrices::new(); n];
+    let mut deformation = vec![ParticleDeformation::new(); n];
+    let mut grid = vec![GridCell::zeroed(); N_GRID_TOTAL as usize];
+    let params = [GridUpdateParams::new(
+        Vec2::new(0.0, 9.8),
+        BoundaryCondition::Separate,
+    )];
+
+    let mut reference = cpu_mpm::CpuMpm::new(x.clone(), v.clone(), material.clone(), params[0]);
+
+    let wg_particles = num_workgroups_1d(n as u32);
+    let wg_grid = num_workgroups_2d(N_GRID_X, N_GRID_X);
+    for step in 0..3 {
+        dispatch_serial(wg_grid, LOCAL_SIZE_2D, |id| {
+            shaders::clear_grid(id, &mut grid)
+        });
+        dispatch_serial(wg_particles, LOCAL_SIZE_1D, |id| {
+            shaders::p2g::p2g(
+                id,
+                &mut x,
+                &v,
+                &mut grid,
+                &mut matrices,
+                &mut deformation,
+                &mut material,
+            )
+        });
+        dispatch_serial(wg_grid, LOCAL_SIZE_2D, |id| {
+            shaders::grid_update::grid_update(id, &mut grid, &params)
+        });
+        dispatch_serial(wg_particles, LOCAL_SIZE_1D, |id| {
+            shaders::g2p::g2p(id, &mut x, &mut v, &grid, &mut matrices)
+        });
+        reference.step();
+
+        for (i, (cell, expected)) in grid.iter().zip(reference.grid.iter()).enumerate() {
+            assert!(
+                (cell.mass - expected.mass).abs() <= 1e-4 * expected.mass.max(1e-6),
+                "step {step}, cell {i}: mass {} != {}",
+                cell.mass,
+                expected.mass
+            );
+            assert_close(
+                cell.v,
+                expected.v,
+                1e-3,
+                &format!("step {step}, cell {i} v"),
+            );
+        }
+        for i in 0..n {
+            assert_close(x[i], reference.x[i], 1e-5, &format!("step {step}, x[{i}]"));
+            assert_close(v[i], reference.v[i], 1e-3, &format!("step {step}, v[{i}]"));
+            assert!(
+                matrices[i]
+                    .F
+                    .abs_diff_eq(reference.particle_matrices[i].F, 1e-5),
+                "step {step}, F[{i}]"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_bindless_heap_p2g_matches_bindful_p2g() {
+    // an odd particle count, so the single-byte materials don't end on a word boundary
+    let (mut x, v, mut material) = particles(23);
+    let n = x.len();
+    let mut matrices = vec![ParticleMatrices::new(); n];
+    let mut deformation = vec![ParticleDeformation::new(); n];
+    let mut grid = vec![GridCell::zeroed(); N_GRID_TOTAL as usize];
+
+    let (layout, mut heap) = BindlessHeap::builder()
+        .with_buffer("material", &material)
+        .with_buffer("x", &x)
+        .with_buffer("grid", &grid)
+        .with_buffer("v", &v)
+        .with_buffer("deformation", &deformation)
+        .with_buffer("matrices", &matrices)
+        .build_host()
+        .expect("Failed to lay out heap");
+    let offset = |name| layout.byte_offset(name).unwrap();
+    let push_constants = P2gHeapPushConstants {
+        x: offset("x"),
+        v: offset("v"),
+        grid: offset("grid"),
+        matrices: offset("matrices"),
+        deformation: offset("deformation"),
+        material: offset("material"),
+        num_particles: n as u32,
+        _padding: 0,
+    };
+
+    let wg_particles = num_workgroups_1d(n as u32);
+    dispatch_serial(wg_particles, LOCAL_SIZE_1D, |id| {
+        shaders::bindless::p2g(id, &mut heap, &push_constants)
+    });
+    dispatch_serial(wg_particles, LOCAL_SIZE_1D, |id| {
+        shaders::p2g::p2g(
+            id,
+            &mut x,
+            &v,
+            &mut grid,
+            &mut matrices,
+            &mut deformation,
+            &mut material,
+        )
+    });
+
+    let heap_grid = layout.read::<GridCell>(&heap, "grid").unwrap();
+    for (i, (cell, expected)) in heap_grid.iter().zip(grid.iter()).enumerate() {
+        assert!(
+            (cell.mass - expected.mass).abs() <= 1e-4 * expected.mass.max(1e-6),
+            "cell {i}: mass {} != {}",
+            cell.mass,
+            expected.mass
+        );
+        assert_close(cell.v, expected.v, 1e-4, &format!("cell {i} v"));
+    }
+    let heap_matrices = layout.read::<ParticleMatrices>(&heap, "matrices").unwrap();
+    let heap_deformation = layout
+        .read::<ParticleDeformation>(&heap, "deformation")
+        .unwrap();
+    for i in 0..n {
+        assert_eq!(heap_matrices[i].F, matrices[i].F, "F[{i}]");
+        assert_eq!(heap_deformation[i].J, deformation[i].J, "J[{i}]");
+    }
+    // p2g leaves the other particle buffers alone
+    assert_eq!(layout.read::<Vec2>(&heap, "x").unwrap(), x);
+    let heap_material = layout.read::<MaterialPod>(&heap, "material").unwrap();
+    assert!(heap_material
+        .iter()
+        .zip(material.iter())
+        .all(|(a, b)| a.u8() == b.u8()));
+}
+
+#[test]
+fn test_bindless_heap_layout() {
+    let material = vec![MaterialPod::from(Material::Snow); 3];
+    let grid = vec![GridCell::zeroed(); 5];
+    let (layout, mut heap) = BindlessHeap::builder()
+        .with_buffer("material", &material)
+        .with_buffer("grid", &grid)
+        .build_host()
+        .expect("Failed to lay out heap");
+
+    // 3 one-byte materials, then the grid at the next aligned offset
+    assert_eq!(layout.byte_offset("material").unwrap(), 0);
+    assert_eq!(
+        layout.byte_offset("grid").unwrap() as usize,
+        std::mem::align_of::<GridCell>().max(4)
+    );
+    assert_eq!(heap.len() * 4, layout.size_bytes());
+
+    layout
+        .write(&mut heap, "grid", &[GridCell::zeroed(); 5])
+        .unwrap();
+    assert!(matches!(
+        layout.read::<Vec2>(&heap, "grid"),
+        Err(ChimeraError::BufferTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        layout.write(&mut heap, "grid", &[GridCell::zeroed(); 4]),
+        Err(ChimeraError::BufferLengthMismatch { .. })
+    ));
+    assert!(matches!(
+        BindlessHeap::builder()
+            .with_buffer("grid", &grid)
+            .with_buffer("grid", &grid)
+            .build_host(),
+        Err(ChimeraError::DuplicateBufferName(_))
+    ));
+}
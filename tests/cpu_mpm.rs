@@ -0,0 +1,148 @@
+//! Tests for the CPU reference implementation of the MPM step
+//!
+//! These run without a GPU and check basic physical invariants:
+//! - p2g conserves mass and momentum
+//! - a fluid at rest falls freely under gravity for one step
+//! - a uniform translation is preserved by the APIC transfers
+//! - particles stay inside the domain over many steps
+
+use glam::Vec2;
+use rust_gpu_chimera_demo::cpu_mpm::{clear_grid, p2g, CpuMpm};
+use shared::{
+    grid::{BoundaryCondition, GridUpdateParams},
+    particles::{Material, MaterialPod},
+    DT, P_MASS,
+};
+
+const EPSILON: f32 = 1e-5;
+
+/// A square block of `side * side` particles centered at `center`, spaced `spacing` apart
+fn block(center: Vec2, side: u32, spacing: f32) -> Vec<Vec2> {
+    let half = (side - 1) as f32 * spacing * 0.5;
+    (0..side * side)
+        .map(|i| {
+            let (ix, iy) = (i % side, i / side);
+            center + Vec2::new(ix as f32 * spacing - half, iy as f32 * spacing - half)
+        })
+        .collect()
+}
+
+fn sim(x: Vec<Vec2>, v: Vec2, material: Material, gravity: Vec2) -> CpuMpm {
+    let n = x.len();
+    CpuMpm::new(
+        x,
+        vec![v; n],
+        vec![material.into(); n],
+        GridUpdateParams::new(gravity, BoundaryCondition::Separate),
+    )
+}
+
+#[test]
+fn test_p2g_conserves_mass_and_momentum() {
+    let v = Vec2::new(0.3, -0.2);
+    let mut s = sim(
+        block(Vec2::new(0.5, 0.5), 10, 0.0037),
+        v,
+        Material::Fluid,
+        Vec2::ZERO,
+    );
+    clear_grid(&mut s.grid);
+    p2g(
+        &s.x,
+        &s.v,
+        &mut s.grid,
+        &mut s.particle_matrices,
+        &mut s.particle_deformation,
+        &s.particle_material,
+    );
+
+    let n = s.x.len() as f32;
+    let total_mass: f32 = s.grid.iter().map(|c| c.mass).sum();
+    let total_momentum: Vec2 = s.grid.iter().map(|c| c.v).sum();
+    assert!(
+        (total_mass - n * P_MASS).abs() < 1e-4 * n * P_MASS,
+        "total mass {total_mass}, expected {}",
+        n * P_MASS
+    );
+    let expected_momentum = n * P_MASS * v;
+    assert!(
+        (total_momentum - expected_momentum).length() < 1e-4 * expected_momentum.length(),
+        "total momentum {total_momentum:?}, expected {expected_momentum:?}"
+    );
+}
+
+#[test]
+fn test_fluid_at_rest_falls_under_gravity() {
+    let gravity = Vec2::new(0.0, 9.8);
+    let x0 = block(Vec2::new(0.5, 0.5), 8, 0.004);
+    let mut s = sim(x0.clone(), Vec2::ZERO, Material::Fluid, gravity);
+    s.step();
+
+    let expected_v = DT * gravity;
+    for (i, (x, v)) in s.x.iter().zip(s.v.iter()).enumerate() {
+        assert!(
+            (*v - expected_v).length() < EPSILON,
+            "particle {i}: v = {v:?}, expected {expected_v:?}"
+        );
+        let expected_x = x0[i] + DT * expected_v;
+        assert!(
+            (*x - expected_x).length() < EPSILON,
+            "particle {i}: x = {x:?}, expected {expected_x:?}"
+        );
+    }
+}
+
+#[test]
+fn test_uniform_translation_is_preserved() {
+    let v0 = Vec2::new(0.5, 0.2);
+    let mut s = sim(
+        block(Vec2::new(0.5, 0.5), 8, 0.004),
+        v0,
+        Material::Jelly,
+        Vec2::ZERO,
+    );
+    for _ in 0..5 {
+        s.step();
+    }
+
+    for (i, (v, m)) in s.v.iter().zip(s.particle_matrices.iter()).enumerate() {
+        assert!(
+            (*v - v0).length() < 1e-4,
+            "particle {i}: v = {v:?}, expected {v0:?}"
+        );
+        assert!(
+            m.C.abs_diff_eq(glam::Mat2::ZERO, 1e-2),
+            "particle {i}: C = {:?}, expected zero",
+            m.C
+        );
+    }
+}
+
+#[test]
+fn test_particles_stay_in_domain() {
+    let materials = [Material::Fluid, Material::Jelly, Material::Snow];
+    let x = block(Vec2::new(0.5, 0.8), 15, 0.004);
+    let particle_material = (0..x.len())
+        .map(|i| materials[i % 3].into())
+        .collect::<Vec<MaterialPod>>();
+    let mut s = CpuMpm::new(
+        x.clone(),
+        vec![Vec2::ZERO; x.len()],
+        particle_material,
+        GridUpdateParams::new(Vec2::new(0.0, 9.8), BoundaryCondition::Separate),
+    );
+    for _ in 0..200 {
+        s.step();
+    }
+
+    for (i, x) in s.x.iter().enumerate() {
+        assert!(x.is_finite(), "particle {i} is not finite: {x:?}");
+        assert!(
+            x.cmpge(Vec2::ZERO).all() && x.cmple(Vec2::ONE).all(),
+            "particle {i} left the domain: {x:?}"
+        );
+    }
+    // gravity points towards +y, so the block must have moved down
+    let mean_y = s.x.iter().map(|x| x.y).sum::<f32>() / s.x.len() as f32;
+    assert!(mean_y > 0.8, "mean y = {mean_y}");
+}
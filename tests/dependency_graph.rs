@@ -0,0 +1,319 @@
+//! Tests for the dependency analysis of invocation chains
+//!
+//! The analysis only looks at the invocation specs and their buffer access
+//! annotations, so these tests need neither a Vulkan driver nor the shaders.
+
+use rust_gpu_chimera_demo::runners::vulkano::{
+    dependency_graph::{Dependency, DependencyGraph, Hazard, UnorderedWrite},
+    shader_pipeline_builder::{
+        invoc_spec, invoc_spec_indirect, kernel, BufferAccess, KernelConfig,
+    },
+};
+
+fn one_workgroup(entry_point_name: &'static str, bindings: Vec<u32>) -> KernelConfig {
+    kernel(entry_point_name, bindings, [1, 1, 1])
+}
+
+#[test]
+fn test_independent_invocations_need_no_barrier() {
+    let chain = vec![
+        invoc_spec(
+            "adder_ab",
+            vec!["a", "b"],
+            one_workgroup("adder", vec![0, 1]),
+        )
+        .with_access("b", BufferAccess::Read),
+        invoc_spec(
+            "clear_grid",
+            vec!["grid"],
+            one_workgroup("clear_grid", vec![4]),
+        )
+        .with_access("grid", BufferAccess::Write),
+        invoc_spec(
+            "adder_cb",
+            vec!["c", "b"],
+            one_workgroup("adder", vec![0, 1]),
+        )
+        .with_access("b", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert!(graph.dependencies().is_empty());
+    assert_eq!(graph.barrier_count(), 0);
+    assert!(graph.unordered_writes().is_empty());
+}
+
+#[test]
+fn test_unannotated_buffers_are_read_write() {
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("step_0", vec!["x", "v"], step.clone()),
+        invoc_spec("step_1", vec!["x", "v"], step),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(graph.barrier_count(), 1);
+    assert!(graph.needs_barrier_before(1));
+    assert!(graph.barrier_needs_memory_dependency(1));
+    assert!(graph.dependencies().contains(&Dependency {
+        from: 0,
+        to: 1,
+        buf_name: "v",
+        hazard: Hazard::ReadAfterWrite,
+    }));
+}
+
+#[test]
+fn test_write_after_read_only_orders_execution() {
+    let adder = one_workgroup("adder", vec![0, 1]);
+    let chain = vec![
+        invoc_spec("adder_ab", vec!["a", "b"], adder.clone()).with_access("b", BufferAccess::Read),
+        invoc_spec("adder_bc", vec!["b", "c"], adder).with_access("c", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(
+        graph.dependencies(),
+        &[Dependency {
+            from: 0,
+            to: 1,
+            buf_name: "b",
+            hazard: Hazard::WriteAfterRead,
+        }]
+    );
+    assert!(graph.needs_barrier_before(1));
+    assert!(!graph.barrier_needs_memory_dependency(1));
+}
+
+#[test]
+fn test_barrier_covers_its_whole_batch() {
+    let adder = one_workgroup("adder", vec![0, 1]);
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("adder_ab", vec!["a", "b"], adder.clone()).with_access("b", BufferAccess::Read),
+        invoc_spec("step_0", vec!["x", "v"], step.clone()).with_access("v", BufferAccess::Read),
+        // only a write-after-read hazard on b
+        invoc_spec("adder_bc", vec!["b", "c"], adder).with_access("c", BufferAccess::Read),
+        // same batch as adder_bc, but reads what step_0 wrote
+        invoc_spec("step_1", vec!["x", "v"], step).with_access("v", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(graph.barrier_count(), 1);
+    assert!(graph.needs_barrier_before(2));
+    assert!(graph.barrier_needs_memory_dependency(2));
+}
+
+#[test]
+fn test_barrier_starts_a_new_batch() {
+    let adder = one_workgroup("adder", vec![0, 1]);
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("adder_ab", vec!["a", "b"], adder.clone()).with_access("b", BufferAccess::Read),
+        invoc_spec("step_0", vec!["x", "v"], step.clone()).with_access("v", BufferAccess::Read),
+        // waits for adder_ab
+        invoc_spec("adder_ac", vec!["a", "c"], adder).with_access("c", BufferAccess::Read),
+        // waits for step_0, which ran before the previous barrier
+        invoc_spec("step_1", vec!["x", "v"], step).with_access("v", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(graph.dependencies().len(), 2);
+    assert_eq!(graph.barrier_count(), 1);
+    assert!(graph.needs_barrier_before(2));
+    assert!(!graph.needs_barrier_before(3));
+}
+
+#[test]
+fn test_atomic_scatters_are_unordered_and_reported() {
+    let p2g = one_workgroup("p2g::p2g", vec![2, 4]);
+    let chain = vec![
+        invoc_spec(
+            "clear_grid",
+            vec!["grid"],
+            one_workgroup("clear_grid", vec![4]),
+        )
+        .with_access("grid", BufferAccess::Write),
+        invoc_spec("p2g_0", vec!["x0", "grid"], p2g.clone())
+            .with_access("x0", BufferAccess::Read)
+            .with_access("grid", BufferAccess::Atomic),
+        invoc_spec("p2g_1", vec!["x1", "grid"], p2g)
+            .with_access("x1", BufferAccess::Read)
+            .with_access("grid", BufferAccess::Atomic),
+        invoc_spec(
+            "grid_update",
+            vec!["grid"],
+            one_workgroup("grid_update::grid_update", vec![4]),
+        ),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    // clear_grid | p2g_0, p2g_1 | grid_update
+    assert_eq!(graph.barrier_count(), 2);
+    assert!(graph.needs_barrier_before(1));
+    assert!(!graph.needs_barrier_before(2));
+    assert!(graph.needs_barrier_before(3));
+
+    // grid_update waits for both scatters
+    let grid_update_deps: Vec<usize> = graph.dependencies_of(3).map(|d| d.from).collect();
+    assert_eq!(grid_update_deps, vec![1, 2]);
+
+    assert_eq!(
+        graph.unordered_writes(),
+        &[UnorderedWrite {
+            first: 1,
+            second: 2,
+            buf_name: "grid",
+        }]
+    );
+    let warnings = graph.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("`p2g_0` and `p2g_1`"));
+    assert!(warnings[0].contains("`grid`"));
+}
+
+#[test]
+fn test_atomic_after_plain_write_is_ordered() {
+    let chain = vec![
+        invoc_spec("p2g_0", vec!["grid"], one_workgroup("p2g::p2g", vec![4]))
+            .with_access("grid", BufferAccess::Atomic),
+        invoc_spec(
+            "grid_update",
+            vec!["grid"],
+            one_workgroup("grid_update", vec![4]),
+        ),
+        invoc_spec("p2g_1", vec!["grid"], one_workgroup("p2g::p2g", vec![4]))
+            .with_access("grid", BufferAccess::Atomic),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(graph.barrier_count(), 2);
+    assert!(graph.unordered_writes().is_empty());
+    assert!(graph.warnings().is_empty());
+}
+
+#[test]
+fn test_indirect_dispatch_waits_for_its_arguments() {
+    let chain = vec![
+        invoc_spec(
+            "args",
+            vec!["count", "args"],
+            one_workgroup("indirect_args_1d", vec![9, 10]),
+        )
+        .with_access("count", BufferAccess::Read)
+        .with_access("args", BufferAccess::Write),
+        // reads `args` without binding it
+        invoc_spec_indirect(
+            "adder_ab",
+            vec!["a", "b"],
+            one_workgroup("adder", vec![0, 1]),
+            "args",
+        )
+        .with_access("b", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert_eq!(
+        graph.dependencies(),
+        &[Dependency {
+            from: 0,
+            to: 1,
+            buf_name: "args",
+            hazard: Hazard::ReadAfterWrite,
+        }]
+    );
+    assert!(graph.needs_barrier_before(1));
+    assert!(graph.barrier_needs_indirect_command_read(1));
+}
+
+#[test]
+fn test_plain_read_needs_no_indirect_command_read() {
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("step_0", vec!["x", "v"], step.clone()).with_access("v", BufferAccess::Read),
+        invoc_spec("step_1", vec!["x", "v"], step).with_access("v", BufferAccess::Read),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert!(graph.barrier_needs_memory_dependency(1));
+    assert!(!graph.barrier_needs_indirect_command_read(1));
+}
+
+#[test]
+fn test_repeat_waits_for_state_of_previous_run() {
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("step", vec!["x", "v"], step).with_access("v", BufferAccess::Read),
+        invoc_spec("wrap", vec!["x"], one_workgroup("wrap_particles", vec![2])),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    // the next step reads the wrapped positions
+    assert_eq!(
+        graph.repeat_dependencies(),
+        &[Dependency {
+            from: 1,
+            to: 0,
+            buf_name: "x",
+            hazard: Hazard::ReadAfterWrite,
+        }]
+    );
+    assert!(graph.needs_barrier_between_repeats());
+    assert!(graph.repeat_barrier_needs_memory_dependency());
+    assert!(!graph.repeat_barrier_needs_indirect_command_read());
+}
+
+#[test]
+fn test_repeat_of_read_only_chain_needs_no_barrier() {
+    let chain = vec![invoc_spec(
+        "adder_ab",
+        vec!["a", "b"],
+        one_workgroup("adder", vec![0, 1]),
+    )
+    .with_access("a", BufferAccess::Read)
+    .with_access("b", BufferAccess::Read)];
+    let graph = DependencyGraph::new(&chain);
+
+    assert!(graph.repeat_dependencies().is_empty());
+    assert!(!graph.needs_barrier_between_repeats());
+}
+
+#[test]
+fn test_repeat_of_indirect_dispatch_reads_previous_arguments() {
+    let chain = vec![
+        // reads the arguments the previous run wrote
+        invoc_spec_indirect(
+            "adder_ab",
+            vec!["a", "b"],
+            one_workgroup("adder", vec![0, 1]),
+            "args",
+        )
+        .with_access("b", BufferAccess::Read),
+        invoc_spec(
+            "args",
+            vec!["count", "args"],
+            one_workgroup("indirect_args_1d", vec![9, 10]),
+        )
+        .with_access("count", BufferAccess::Read)
+        .with_access("args", BufferAccess::Write),
+    ];
+    let graph = DependencyGraph::new(&chain);
+
+    assert!(graph.needs_barrier_between_repeats());
+    assert!(graph.repeat_barrier_needs_indirect_command_read());
+}
+
+#[test]
+fn test_report_lists_barriers_and_dependencies() {
+    let step = one_workgroup("step_particles", vec![2, 3]);
+    let chain = vec![
+        invoc_spec("step_0", vec!["x", "v"], step.clone()).with_access("v", BufferAccess::Read),
+        invoc_spec("step_1", vec!["x", "v"], step).with_access("v", BufferAccess::Read),
+    ];
+    let report = DependencyGraph::new(&chain).to_string();
+
+    assert_eq!(
+        report,
+        "2 invocations, 1 barriers:\n  step_0\n  -- barrier --\n  step_1 after step_0 (x: read after write)\n"
+    );
+}
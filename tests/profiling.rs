@@ -0,0 +1,85 @@
+//! Tests for the per-invocation timings of a chain profile
+//!
+//! The samples are added by hand, so these tests need no Vulkan driver; the
+//! `vulkano_backend` tests in `compute_pass.rs` profile a chain on the GPU.
+
+use std::time::Duration;
+
+use rust_gpu_chimera_demo::runners::vulkano::profiling::{ChainProfile, InvocationTimings};
+
+fn micros(us: u64) -> Duration {
+    Duration::from_micros(us)
+}
+
+#[test]
+fn test_timings_over_samples() {
+    let mut profile = ChainProfile::new(["p2g", "grid_update"], 8);
+    for us in [30, 10, 20] {
+        profile.record(0, micros(us));
+    }
+    profile.record(1, micros(5));
+
+    assert_eq!(
+        profile.timings("p2g"),
+        Some(InvocationTimings {
+            samples: 3,
+            last: micros(20),
+            mean: micros(20),
+            min: micros(10),
+            max: micros(30),
+        })
+    );
+    assert_eq!(profile.timings("grid_update").unwrap().samples, 1);
+    assert_eq!(profile.timings("g2p"), None);
+}
+
+#[test]
+fn test_window_drops_oldest_samples() {
+    let mut profile = ChainProfile::new(["adder_ab"], 2);
+    for us in [100, 10, 20] {
+        profile.record(0, micros(us));
+    }
+
+    let timings = profile.timings("adder_ab").unwrap();
+    assert_eq!(timings.samples, 2);
+    assert_eq!(timings.max, micros(20));
+    assert_eq!(timings.mean, micros(15));
+}
+
+#[test]
+fn test_invocations_without_samples_are_skipped() {
+    let mut profile = ChainProfile::new(["adder_ab", "clear_grid"], 4);
+    profile.record(1, micros(7));
+
+    let names: Vec<&str> = profile.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["clear_grid"]);
+    assert_eq!(profile.timings("adder_ab"), None);
+}
+
+#[test]
+fn test_csv_dump() {
+    let mut profile = ChainProfile::new(["p2g", "g2p"], 4);
+    profile.record(0, micros(12));
+    profile.record(0, Duration::from_nanos(8_500));
+    profile.record(1, micros(3));
+
+    assert_eq!(
+        profile.to_csv(),
+        "invocation,samples,last_us,mean_us,min_us,max_us\n\
+         p2g,2,8.500,10.250,8.500,12.000\n\
+         g2p,1,3.000,3.000,3.000,3.000\n"
+    );
+}
+
+#[test]
+fn test_text_dump_lists_each_invocation() {
+    let mut profile = ChainProfile::new(["grid_update"], 4);
+    profile.record(0, micros(42));
+
+    let report = profile.to_string();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("invocation "));
+    assert!(lines[1].starts_with("grid_update "));
+    assert!(lines[1].contains("42.000"));
+}
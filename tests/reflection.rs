@@ -0,0 +1,104 @@
+//! Tests for reflecting the compiled `OTHER_SHADERS_SPIRV` module
+//!
+//! The reflected bindings and element strides must match the `#[spirv(...)]`
+//! attributes in the shaders crate and the Rust sizes of the buffer element types.
+
+use std::mem::size_of;
+
+use glam::Vec2;
+use rust_gpu_chimera_demo::runners::vulkano::reflection::shaders_reflection;
+use shared::{
+    grid::{GridCell, GridUpdateParams},
+    particles::{MaterialPod, ParticleDeformation, ParticleMatrices},
+    GRID_WORKGROUP_SIZE, WORKGROUP_SIZE,
+};
+
+fn bindings_and_strides(entry_point: &str) -> Vec<(u32, Option<u32>)> {
+    shaders_reflection()
+        .expect("Failed to reflect shaders")
+        .entry_point(entry_point)
+        .expect("Entry point not found")
+        .storage_buffers
+        .iter()
+        .map(|b| (b.binding, b.element_stride))
+        .collect()
+}
+
+fn stride<T>() -> Option<u32> {
+    Some(size_of::<T>() as u32)
+}
+
+#[test]
+fn test_adder_bindings() {
+    assert_eq!(
+        bindings_and_strides("adder"),
+        vec![(0, stride::<u32>()), (1, stride::<u32>())]
+    );
+}
+
+#[test]
+fn test_mpm_kernel_bindings() {
+    assert_eq!(
+        bindings_and_strides("clear_grid"),
+        vec![(4, stride::<GridCell>())]
+    );
+    assert_eq!(
+        bindings_and_strides("p2g::p2g"),
+        vec![
+            (2, stride::<Vec2>()),
+            (3, stride::<Vec2>()),
+            (4, stride::<GridCell>()),
+            (5, stride::<ParticleMatrices>()),
+            (6, stride::<ParticleDeformation>()),
+            (7, stride::<MaterialPod>()),
+        ]
+    );
+    assert_eq!(
+        bindings_and_strides("grid_update::grid_update"),
+        vec![(4, stride::<GridCell>()), (8, stride::<GridUpdateParams>())]
+    );
+    assert_eq!(
+        bindings_and_strides("g2p::g2p"),
+        vec![
+            (2, stride::<Vec2>()),
+            (3, stride::<Vec2>()),
+            (4, stride::<GridCell>()),
+            (5, stride::<ParticleMatrices>()),
+        ]
+    );
+}
+
+fn local_size(entry_point: &str) -> [u32; 3] {
+    shaders_reflection()
+        .expect("Failed to reflect shaders")
+        .entry_point(entry_point)
+        .expect("Entry point not found")
+        .local_size()
+        .expect("Entry point has no local size")
+}
+
+#[test]
+fn test_local_sizes_match_shared_constants() {
+    for entry_point in ["adder", "step_particles", "p2g::p2g", "g2p::g2p"] {
+        assert_eq!(
+            local_size(entry_point),
+            [WORKGROUP_SIZE, 1, 1],
+            "{entry_point}"
+        );
+    }
+    for entry_point in ["clear_grid", "grid_update::grid_update"] {
+        assert_eq!(
+            local_size(entry_point),
+            [GRID_WORKGROUP_SIZE.0, GRID_WORKGROUP_SIZE.1, 1],
+            "{entry_point}"
+        );
+    }
+}
+
+#[test]
+fn test_unknown_entry_point() {
+    assert!(shaders_reflection()
+        .unwrap()
+        .entry_point("not_a_kernel")
+        .is_err());
+}